@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use anyhow::Result;
+
+fn aliases_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("aliases")
+}
+
+pub fn read() -> Result<Vec<(String, String)>> {
+    let contents = match std::fs::read_to_string(aliases_path_buf()) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(_) => return Err(anyhow!("Unable to read aliases.")),
+    };
+    Ok(contents.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(alias, friend)| (alias.to_string(), friend.to_string()))
+        .collect())
+}
+
+fn write(aliases: &[(String, String)]) -> Result<()> {
+    let contents: String = aliases.iter()
+        .map(|(alias, friend)| format!("{}\t{}\n", alias, friend))
+        .collect();
+    crate::fsutil::replace_private_file(&aliases_path_buf(), contents.as_bytes())
+        .map_err(|_| anyhow!("Unable to write aliases."))
+}
+
+pub fn lookup(alias: &str) -> Result<Option<String>> {
+    Ok(read()?
+        .into_iter()
+        .find(|(a, _)| a == alias)
+        .map(|(_, friend)| friend))
+}
+
+pub fn of(friend: &str) -> Result<Vec<String>> {
+    Ok(read()?
+        .into_iter()
+        .filter(|(_, f)| f == friend)
+        .map(|(alias, _)| alias)
+        .collect())
+}
+
+pub fn add(alias: &str, friend: &str) -> Result<()> {
+    let mut aliases = read()?;
+    if aliases.iter().any(|(a, _)| a == alias) {
+        return Err(anyhow!("Alias already exists."));
+    }
+    aliases.push((alias.to_string(), friend.to_string()));
+    write(&aliases)
+}
+
+pub fn remove(alias: &str) -> Result<()> {
+    let mut aliases = read()?;
+    let before = aliases.len();
+    aliases.retain(|(a, _)| a != alias);
+    if aliases.len() == before {
+        return Err(anyhow!("Alias not found."));
+    }
+    write(&aliases)
+}
+
+pub fn remove_friend(friend: &str) -> Result<()> {
+    let mut aliases = read()?;
+    aliases.retain(|(_, f)| f != friend);
+    write(&aliases)
+}
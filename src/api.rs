@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+use anyhow::Result;
+
+fn token_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("api-token")
+}
+
+pub fn new_token() -> Result<String> {
+    let token = sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(32));
+    let path = token_path_buf();
+    let _ = std::fs::remove_file(&path);
+    crate::fsutil::write_private_file(&path, token.as_bytes())?;
+    Ok(token)
+}
+
+pub fn token() -> Result<Option<String>> {
+    match std::fs::read_to_string(token_path_buf()) {
+        Ok(token) => Ok(Some(token.trim().to_string())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(_) => Err(anyhow!("Unable to read the API token.")),
+    }
+}
+
+pub fn authorized(header: Option<&str>, token: &str) -> bool {
+    match header.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(given) => sodiumoxide::utils::memcmp(given.trim().as_bytes(), token.as_bytes()),
+        None => false,
+    }
+}
@@ -0,0 +1,42 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use anyhow::Result;
+
+pub fn dir_buf() -> PathBuf {
+    crate::settings::state_path_buf("archives")
+}
+
+pub fn pack(files: &[(u64, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    for (id, contents) in files {
+        encoder.write_all(&id.to_le_bytes())?;
+        encoder.write_all(&(contents.len() as u64).to_le_bytes())?;
+        encoder.write_all(contents)?;
+    }
+    encoder.finish().map_err(|_| anyhow!("Unable to compress archive."))
+}
+
+fn read_u64(data: &[u8], offset: &mut usize) -> Result<u64> {
+    let bytes = data.get(*offset..*offset + 8).ok_or(anyhow!("Archive is corrupted."))?;
+    *offset += 8;
+    let mut word = [0u8; 8];
+    word.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(word))
+}
+
+pub fn unpack(packed: &[u8]) -> Result<Vec<(u64, Vec<u8>)>> {
+    let mut data = Vec::new();
+    flate2::read::GzDecoder::new(packed)
+        .read_to_end(&mut data)
+        .map_err(|_| anyhow!("Archive is corrupted."))?;
+    let mut files = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let id = read_u64(&data, &mut offset)?;
+        let len = read_u64(&data, &mut offset)? as usize;
+        let end = offset.checked_add(len).filter(|&end| end <= data.len()).ok_or(anyhow!("Archive is corrupted."))?;
+        files.push((id, data[offset..end].to_vec()));
+        offset = end;
+    }
+    Ok(files)
+}
@@ -0,0 +1,187 @@
+const BEGIN: &str = "-----BEGIN GQG PAYLOAD-----";
+const END: &str = "-----END GQG PAYLOAD-----";
+
+pub fn armor(payload: &str) -> String {
+    format!("{}\n{}\n{}\n", BEGIN, payload.trim_end(), END)
+}
+
+pub fn is_armored(text: &str) -> bool {
+    normalize(text).contains(BEGIN)
+}
+
+pub fn is_armor_end(line: &str) -> bool {
+    normalize(line).trim() == END
+}
+
+fn unquote(line: &str) -> &str {
+    let mut line = line.trim_start();
+    while let Some(rest) = line.strip_prefix('>') {
+        line = rest.trim_start();
+    }
+    line
+}
+
+fn normalize(text: &str) -> String {
+    let text: String = text.chars()
+        .map(|c| match c {
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{2033}' => '"',
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{2032}' => '\'',
+            '\u{a0}' | '\u{2007}' | '\u{202f}' => ' ',
+            c => c,
+        })
+        .filter(|&c| c != '\u{200b}' && c != '\u{feff}' && c != '\r')
+        .collect();
+    text.lines().map(unquote).collect::<Vec<_>>().join("\n")
+}
+
+fn unwrap_block(block: &str) -> String {
+    block.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+fn compact(payload: &str) -> String {
+    let (header, body) = match payload.split_once('\n') {
+        Some((header, body)) if header.starts_with("gqg-hint:") => (format!("{}\n", header), body),
+        _ => (String::new(), payload),
+    };
+    header + &body.split_whitespace().collect::<String>()
+}
+
+fn unescape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['&', '<']) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let close = if rest.starts_with('&') { ';' } else { '>' };
+        let token = match rest.find(close) {
+            Some(end) if end <= 10 && !rest[1..end].contains(['&', '<']) => &rest[..end + 1],
+            _ => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let replacement = match token.to_ascii_lowercase().as_str() {
+            "&amp;" => "&".to_string(),
+            "&lt;" => "<".to_string(),
+            "&gt;" => ">".to_string(),
+            "&quot;" => "\"".to_string(),
+            "&apos;" | "&#39;" => "'".to_string(),
+            "&nbsp;" => " ".to_string(),
+            "<br>" | "<br/>" | "<br />" | "<p>" | "</p>" | "<div>" | "</div>" => "\n".to_string(),
+            lower if lower.starts_with("&#x") => u32::from_str_radix(&lower[3..lower.len() - 1], 16).ok()
+                .and_then(char::from_u32)
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| token.to_string()),
+            lower if lower.starts_with("&#") => lower[2..lower.len() - 1].parse::<u32>().ok()
+                .and_then(char::from_u32)
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| token.to_string()),
+            _ => token.to_string(),
+        };
+        out.push_str(&replacement);
+        rest = &rest[token.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn clean(text: &str) -> String {
+    unwrap_block(&normalize(&unescape_html(text)))
+}
+
+pub fn candidates(text: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    let cleaned = if is_armored(text) {
+        extract(text).into_iter().next().unwrap_or_default()
+    }
+    else {
+        candidates.push(text.to_string());
+        clean(text)
+    };
+    let compacted = compact(&cleaned);
+    for candidate in [cleaned, compacted] {
+        if !candidate.is_empty() && !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+pub fn extract(text: &str) -> Vec<String> {
+    let text = normalize(&unescape_html(text));
+    let mut payloads = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find(BEGIN) {
+        let after = &rest[start + BEGIN.len()..];
+        match after.find(END) {
+            Some(end) => {
+                payloads.push(unwrap_block(&after[..end]));
+                rest = &after[end + END.len()..];
+            }
+            None => break,
+        }
+    }
+    payloads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &str = "gqg-hint: 0a1b2c3d\nQUJDREVGR0hJSktMTU5PUA==";
+
+    #[test]
+    fn round_trip() {
+        let armored = armor(PAYLOAD);
+        assert!(is_armored(&armored));
+        assert!(is_armor_end(END));
+        assert_eq!(extract(&armored), vec![PAYLOAD.to_string()]);
+    }
+
+    #[test]
+    fn finds_payloads_in_surrounding_text() {
+        let text = format!("Hi,\n\n{}\nand again:\n{}-- \nsig", armor(PAYLOAD), armor("QUJD"));
+        assert_eq!(extract(&text), vec![PAYLOAD.to_string(), "QUJD".to_string()]);
+        assert_eq!(candidates(&text), vec![PAYLOAD.to_string()]);
+    }
+
+    #[test]
+    fn normalizes_mangled_armor() {
+        let quoted = format!("> > {}\r\n> > gqg-hint: 0a1b2c3d\r\n> > QUJDREVGR0hJ\r\n> > SktMTU5PUA==\r\n> > {}\r\n", BEGIN, END);
+        assert_eq!(extract(&quoted), vec!["gqg-hint: 0a1b2c3d\nQUJDREVGR0hJ\nSktMTU5PUA==".to_string()]);
+        assert_eq!(candidates(&quoted).last(), Some(&PAYLOAD.to_string()));
+        let dashes = "\u{2014}\u{2014}\u{2014}\u{2014}\u{2014}BEGIN GQG PAYLOAD\u{2014}\u{2014}\u{2014}\u{2014}\u{2014}\n\
+            QUJD\u{200b}REVG\n-----END\u{a0}GQG PAYLOAD-----";
+        assert_eq!(extract(dashes), vec!["QUJDREVG".to_string()]);
+    }
+
+    #[test]
+    fn unescapes_html() {
+        let html = format!("<p>{}<br>QUJD<br/>&#x52;EVG<br>{}</p>", BEGIN, END);
+        assert_eq!(extract(&html), vec!["QUJD\nREVG".to_string()]);
+        assert_eq!(candidates(&html), vec!["QUJD\nREVG".to_string(), "QUJDREVG".to_string()]);
+        assert_eq!(unescape_html("a &amp b &lt; c &#99999999; <notatag"), "a &amp b < c &#99999999; <notatag");
+    }
+
+    #[test]
+    fn malformed_armor() {
+        assert!(extract("no payload here").is_empty());
+        assert!(extract(&format!("{}\nQUJD\n", BEGIN)).is_empty());
+        assert!(candidates(&format!("{}\nQUJD\n", BEGIN)).is_empty());
+        assert!(extract(&format!("{}\nQUJD\n{}", END, BEGIN)).is_empty());
+        assert!(!is_armored("-----BEGIN PGP MESSAGE-----"));
+    }
+
+    #[test]
+    fn tries_unarmored_payloads_verbatim_first() {
+        assert_eq!(candidates(PAYLOAD), vec![PAYLOAD.to_string()]);
+        let mangled = "> gqg-hint: 0a1b2c3d\r\n> QUJDREVGR0hJ \r\n> SktMTU5PUA==\r\n";
+        assert_eq!(candidates(mangled), vec![
+            mangled.to_string(),
+            "gqg-hint: 0a1b2c3d\nQUJDREVGR0hJ\nSktMTU5PUA==".to_string(),
+            PAYLOAD.to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,237 @@
+use std::io::Write;
+use std::path::PathBuf;
+use anyhow::Result;
+use sodiumoxide::crypto::auth;
+use sodiumoxide::crypto::hash::sha256;
+
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+pub struct Entry {
+    pub seq: u64,
+    pub time: String,
+    pub action: String,
+    pub subject: String,
+    pub detail: String,
+    pub prev: String,
+    pub hash: String,
+}
+
+impl Entry {
+    fn body(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\t{}",
+            self.seq, self.time, self.action, self.subject, self.detail, self.prev)
+    }
+
+    fn compute_hash(&self) -> String {
+        sodiumoxide::hex::encode(&sha256::hash(self.body().as_bytes()).0)
+    }
+
+    fn parse(line: &str) -> Option<Entry> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return None;
+        }
+        Some(Entry {
+            seq: fields[0].parse().ok()?,
+            time: fields[1].to_string(),
+            action: fields[2].to_string(),
+            subject: fields[3].to_string(),
+            detail: fields[4].to_string(),
+            prev: fields[5].to_string(),
+            hash: fields[6].to_string(),
+        })
+    }
+}
+
+pub fn log_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("audit.log")
+}
+
+fn head_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("audit.head")
+}
+
+fn head_tag(seq: u64, hash: &str, key: &auth::Key) -> String {
+    sodiumoxide::hex::encode(auth::authenticate(format!("{}\t{}", seq, hash).as_bytes(), key).0)
+}
+
+fn check_head(entries: &[Entry], head: Option<&str>, key: &auth::Key) -> Result<()> {
+    let head = match (head, entries.last()) {
+        (None, None) => return Ok(()),
+        (None, Some(_)) => return Err(anyhow!("Audit log verification failed: the signed head is missing.")),
+        (Some(head), _) => head.trim(),
+    };
+    let fields: Vec<&str> = head.split('\t').collect();
+    let last = entries.last();
+    let matches = fields.len() == 3
+        && last.map(|entry| entry.seq.to_string() == fields[0] && entry.hash == fields[1]).unwrap_or(false)
+        && sodiumoxide::hex::decode(fields[2]).ok()
+            .and_then(|bytes| auth::Tag::from_slice(&bytes))
+            .map(|tag| auth::verify(&tag, format!("{}\t{}", fields[0], fields[1]).as_bytes(), key))
+            .unwrap_or(false);
+    if !matches {
+        return Err(anyhow!("Audit log verification failed: entries were removed or the head was altered."));
+    }
+    Ok(())
+}
+
+fn store_head(entry: &Entry) -> Result<()> {
+    match crate::integrity::key()? {
+        Some(key) => {
+            let head = format!("{}\t{}\t{}", entry.seq, entry.hash, head_tag(entry.seq, &entry.hash, &key));
+            crate::fsutil::replace_private_file(&head_path_buf(), head.as_bytes())
+        }
+        None => match std::fs::remove_file(head_path_buf()) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(_) => Err(anyhow!("Unable to remove the audit log head.")),
+        },
+    }
+}
+
+fn clean(field: &str) -> String {
+    field.replace(|c: char| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+fn read_lines() -> Result<Vec<String>> {
+    match std::fs::read_to_string(log_path_buf()) {
+        Ok(contents) => Ok(contents.lines().map(|l| l.to_string()).collect()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read audit log.")),
+    }
+}
+
+pub fn find_last(action: &str, subject: &str) -> Option<Entry> {
+    read_lines().ok()?
+        .iter()
+        .rev()
+        .filter_map(|line| Entry::parse(line))
+        .find(|entry| entry.action == action && entry.subject == subject)
+}
+
+fn next_entry(last: Option<&Entry>, action: &str, subject: &str, detail: &str) -> Entry {
+    let (seq, prev) = match last {
+        None => (1, GENESIS.to_string()),
+        Some(last) => (last.seq + 1, last.hash.clone()),
+    };
+    let mut entry = Entry {
+        seq,
+        time: chrono::Utc::now().to_rfc3339(),
+        action: clean(action),
+        subject: clean(subject),
+        detail: clean(detail),
+        prev,
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash();
+    entry
+}
+
+pub fn record(action: &str, subject: &str, detail: &str) -> Result<()> {
+    let lines = read_lines()?;
+    let last = match lines.last() {
+        None => None,
+        Some(line) => Some(Entry::parse(line).ok_or(anyhow!("Audit log is corrupted."))?),
+    };
+    let entry = next_entry(last.as_ref(), action, subject, detail);
+    let mut file = crate::fsutil::append_private(&log_path_buf())
+        .map_err(|_| anyhow!("Unable to open audit log."))?;
+    writeln!(file, "{}\t{}", entry.body(), entry.hash)
+        .map_err(|_| anyhow!("Unable to write audit log."))?;
+    store_head(&entry)
+}
+
+pub fn verify() -> Result<Vec<Entry>> {
+    let entries = verify_chain(&read_lines()?)?;
+    if let Some(key) = crate::integrity::key()? {
+        let head = match std::fs::read_to_string(head_path_buf()) {
+            Ok(head) => Some(head),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(_) => return Err(anyhow!("Unable to read the audit log head.")),
+        };
+        check_head(&entries, head.as_deref(), &key)?;
+    }
+    Ok(entries)
+}
+
+fn verify_chain(lines: &[String]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut prev = GENESIS.to_string();
+    for (i, line) in lines.iter().enumerate() {
+        let seq = i as u64 + 1;
+        let entry = Entry::parse(line)
+            .ok_or(anyhow!("Audit log verification failed at entry {}: malformed line.", seq))?;
+        if entry.seq != seq || entry.prev != prev {
+            return Err(anyhow!("Audit log verification failed at entry {}: broken chain.", seq));
+        }
+        if entry.compute_hash() != entry.hash {
+            return Err(anyhow!("Audit log verification failed at entry {}: modified entry.", seq));
+        }
+        prev = entry.hash.clone();
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(count: usize) -> (Vec<Entry>, Vec<String>) {
+        let mut entries: Vec<Entry> = Vec::new();
+        for i in 0..count {
+            let entry = next_entry(entries.last(), "befriend", &format!("friend{}", i), "key");
+            entries.push(entry);
+        }
+        let lines = entries.iter().map(|entry| format!("{}\t{}", entry.body(), entry.hash)).collect();
+        (entries, lines)
+    }
+
+    #[test]
+    fn intact_chain_verifies() {
+        let (_, lines) = chain(3);
+        assert_eq!(verify_chain(&lines).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn modified_entry_fails() {
+        let (_, mut lines) = chain(3);
+        lines[1] = lines[1].replace("friend1", "mallory");
+        let err = verify_chain(&lines).unwrap_err().to_string();
+        assert!(err.contains("entry 2: modified entry"), "{}", err);
+    }
+
+    #[test]
+    fn broken_chain_fails() {
+        let (_, mut lines) = chain(3);
+        lines.remove(1);
+        let err = verify_chain(&lines).unwrap_err().to_string();
+        assert!(err.contains("entry 2: broken chain"), "{}", err);
+    }
+
+    #[test]
+    fn truncation_fails_against_the_head() {
+        sodiumoxide::init().unwrap();
+        let key = auth::gen_key();
+        let (entries, mut lines) = chain(3);
+        let last = entries.last().unwrap();
+        let head = format!("{}\t{}\t{}", last.seq, last.hash, head_tag(last.seq, &last.hash, &key));
+        assert!(check_head(&verify_chain(&lines).unwrap(), Some(&head), &key).is_ok());
+        lines.pop();
+        let truncated = verify_chain(&lines).unwrap();
+        assert!(check_head(&truncated, Some(&head), &key).is_err());
+        assert!(check_head(&truncated, None, &key).is_err());
+    }
+
+    #[test]
+    fn forged_head_fails() {
+        sodiumoxide::init().unwrap();
+        let key = auth::gen_key();
+        let (entries, mut lines) = chain(3);
+        lines.pop();
+        let truncated = verify_chain(&lines).unwrap();
+        let last = &entries[1];
+        let forged = format!("{}\t{}\t{}", last.seq, last.hash, head_tag(last.seq, &last.hash, &auth::gen_key()));
+        assert!(check_head(&truncated, Some(&forged), &key).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+const MAGIC: &[u8] = b"GQGCHAFF1\n";
+const MIN_LEN: usize = 16;
+const MAX_LEN: usize = 512;
+
+fn random_u32() -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&sodiumoxide::randombytes::randombytes(4));
+    u32::from_le_bytes(word)
+}
+
+pub fn is_chaff(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+pub fn generate() -> Vec<u8> {
+    let len = MIN_LEN + random_u32() as usize % (MAX_LEN - MIN_LEN + 1);
+    let mut contents = MAGIC.to_vec();
+    contents.extend_from_slice(&sodiumoxide::randombytes::randombytes(len));
+    contents
+}
+
+pub fn pick(count: usize) -> usize {
+    random_u32() as usize % count
+}
+
+pub fn delay(mean_seconds: f64) -> Duration {
+    let uniform = (random_u32() as f64 + 1.0) / (u32::MAX as f64 + 1.0);
+    Duration::from_secs_f64(-uniform.ln() * mean_seconds)
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::base64::Variant;
+use sodiumoxide::crypto::{secretbox, sign};
+
+const SHARE_PREFIX: &str = "gqgch1";
+const POST_HEADER: &str = "gqg-channel: ";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Channel {
+    pub key: String,
+    pub signer: String,
+    pub owner: bool,
+}
+
+pub fn dir_buf() -> PathBuf {
+    crate::settings::state_path_buf("channels")
+}
+
+pub fn path_buf(identity: &str) -> PathBuf {
+    dir_buf().join(format!("{}.gqg", crate::fsutil::sanitize_file_name(identity)))
+}
+
+pub fn parse(contents: &[u8]) -> Result<BTreeMap<String, Channel>> {
+    serde_json::from_slice(contents).map_err(|_| anyhow!("Channel list is corrupted."))
+}
+
+pub fn encode(channels: &BTreeMap<String, Channel>) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(channels)?)
+}
+
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn create(signer: &sign::PublicKey) -> Channel {
+    Channel {
+        key: sodiumoxide::base64::encode(secretbox::gen_key().0, Variant::UrlSafeNoPadding),
+        signer: crate::signing::encode_public(signer),
+        owner: true,
+    }
+}
+
+pub fn share(name: &str, channel: &Channel) -> String {
+    format!("{}.{}.{}.{}", SHARE_PREFIX, name, channel.key, channel.signer)
+}
+
+pub fn parse_share(text: &str) -> Result<(String, Channel)> {
+    let fields: Vec<&str> = text.trim().split('.').collect();
+    if fields.len() != 4 || fields[0] != SHARE_PREFIX || !valid_name(fields[1]) {
+        return Err(anyhow!("Invalid channel key."));
+    }
+    let channel = Channel { key: fields[2].to_string(), signer: fields[3].to_string(), owner: false };
+    secret_key(&channel)?;
+    crate::signing::decode_public(&channel.signer).ok_or(anyhow!("Invalid channel key."))?;
+    Ok((fields[1].to_string(), channel))
+}
+
+fn secret_key(channel: &Channel) -> Result<secretbox::Key> {
+    sodiumoxide::base64::decode(&channel.key, Variant::UrlSafeNoPadding).ok()
+        .and_then(|bytes| secretbox::Key::from_slice(&bytes))
+        .ok_or(anyhow!("Invalid channel key."))
+}
+
+pub fn id(channel: &Channel) -> String {
+    crate::sha256_hex(channel.key.as_bytes())[..16].to_string()
+}
+
+fn signed_bytes(id: &str, time: i64, body: &[u8]) -> Vec<u8> {
+    let mut data = id.as_bytes().to_vec();
+    data.extend_from_slice(&time.to_le_bytes());
+    data.extend_from_slice(body);
+    data
+}
+
+pub fn seal_post(channel: &Channel, signing_key: &sign::SecretKey, body: &[u8]) -> Result<String> {
+    let id = id(channel);
+    let time = chrono::Utc::now().timestamp();
+    let mut inner = sign::sign(&signed_bytes(&id, time, body), signing_key);
+    inner.truncate(sign::SIGNATUREBYTES);
+    inner.extend_from_slice(&time.to_le_bytes());
+    inner.extend_from_slice(body);
+    let nonce = secretbox::gen_nonce();
+    let mut sealed = nonce.0.to_vec();
+    sealed.extend_from_slice(&secretbox::seal(&inner, &nonce, &secret_key(channel)?));
+    Ok(format!("{}{}\n{}", POST_HEADER, id, sodiumoxide::base64::encode(sealed, Variant::UrlSafeNoPadding)))
+}
+
+pub fn split_posts(text: &str) -> Vec<(String, String)> {
+    text.split(POST_HEADER)
+        .skip(1)
+        .filter_map(|post| {
+            let (id, body) = post.split_once('\n')?;
+            let body: String = body.split_whitespace().collect();
+            Some((id.trim().to_string(), body))
+        })
+        .collect()
+}
+
+pub fn open_post(channel: &Channel, id: &str, body: &str) -> Result<(i64, Vec<u8>)> {
+    let corrupted = || anyhow!("Channel post is corrupted.");
+    let sealed = sodiumoxide::base64::decode(body, Variant::UrlSafeNoPadding).map_err(|_| corrupted())?;
+    if sealed.len() < secretbox::NONCEBYTES {
+        return Err(corrupted());
+    }
+    let nonce = secretbox::Nonce::from_slice(&sealed[..secretbox::NONCEBYTES]).ok_or_else(corrupted)?;
+    let inner = secretbox::open(&sealed[secretbox::NONCEBYTES..], &nonce, &secret_key(channel)?)
+        .map_err(|_| anyhow!("Unable to decrypt channel post."))?;
+    if inner.len() < sign::SIGNATUREBYTES + 8 {
+        return Err(corrupted());
+    }
+    let mut time = [0u8; 8];
+    time.copy_from_slice(&inner[sign::SIGNATUREBYTES..sign::SIGNATUREBYTES + 8]);
+    let time = i64::from_le_bytes(time);
+    let body = inner[sign::SIGNATUREBYTES + 8..].to_vec();
+    let signer = crate::signing::decode_public(&channel.signer).ok_or(anyhow!("Invalid channel key."))?;
+    let mut signed = inner[..sign::SIGNATUREBYTES].to_vec();
+    signed.extend_from_slice(&signed_bytes(id, time, &body));
+    if sign::verify(&signed, &signer).is_err() {
+        return Err(anyhow!("Channel post is not signed by the channel owner."));
+    }
+    Ok((time, body))
+}
@@ -0,0 +1,49 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::Result;
+
+fn command() -> Command {
+    if cfg!(target_os = "windows") {
+        crate::profile::command("clip")
+    }
+    else if cfg!(target_os = "macos") {
+        crate::profile::command("pbcopy")
+    }
+    else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        crate::profile::command("wl-copy")
+    }
+    else {
+        let mut command = crate::profile::command("xclip");
+        command.args(["-selection", "clipboard"]);
+        command
+    }
+}
+
+pub fn copy(contents: &[u8]) -> Result<()> {
+    let mut child = command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| anyhow!("No clipboard tool found."))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(contents).map_err(|_| anyhow!("Unable to write to the clipboard."))?;
+    }
+    let status = child.wait().map_err(|_| anyhow!("Unable to write to the clipboard."))?;
+    if !status.success() {
+        return Err(anyhow!("Unable to write to the clipboard."));
+    }
+    Ok(())
+}
+
+pub fn clear_after(seconds: u64) -> Result<()> {
+    let exe = std::env::current_exe().map_err(|_| anyhow!("Unable to schedule clipboard clearing."))?;
+    Command::new(exe)
+        .args(["vault", "clear-clipboard", &seconds.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| anyhow!("Unable to schedule clipboard clearing."))?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+const EXECUTABLE: &[&str] = &[
+    "exe", "com", "bat", "cmd", "scr", "pif", "msi", "msp", "cpl", "dll", "sys",
+    "ps1", "psm1", "vbs", "vbe", "js", "jse", "wsf", "wsh", "hta", "jar", "lnk", "reg", "inf",
+    "sh", "bash", "zsh", "csh", "command", "run", "bin", "appimage", "app", "dmg", "pkg",
+    "deb", "rpm", "apk", "py", "pl", "rb", "php", "iso", "img",
+];
+
+const MACRO_DOCUMENTS: &[&str] = &[
+    "docm", "dotm", "xlsm", "xltm", "xlam", "pptm", "potm", "ppsm", "ppam", "sldm",
+];
+
+const DECOY: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "txt", "rtf",
+    "jpg", "jpeg", "png", "gif", "mp3", "mp4", "avi", "mov", "zip",
+];
+
+const BIDI_CONTROLS: &[char] = &[
+    '\u{202a}', '\u{202b}', '\u{202c}', '\u{202d}', '\u{202e}',
+    '\u{2066}', '\u{2067}', '\u{2068}', '\u{2069}',
+];
+
+pub fn assess(file_name: &str, contents: &[u8]) -> Option<String> {
+    if file_name.contains(BIDI_CONTROLS) {
+        return Some("file name contains direction control characters that disguise its extension".to_string());
+    }
+    let lower = file_name.to_lowercase();
+    let parts: Vec<&str> = lower.trim().trim_end_matches('.').split('.').collect();
+    let extension = if parts.len() > 1 { parts[parts.len() - 1] } else { "" };
+    if parts.len() > 2 && EXECUTABLE.contains(&extension) && DECOY.contains(&parts[parts.len() - 2]) {
+        return Some(format!("double extension .{}.{} disguises an executable", parts[parts.len() - 2], extension));
+    }
+    if EXECUTABLE.contains(&extension) {
+        return Some(format!("executable or script file type .{}", extension));
+    }
+    if MACRO_DOCUMENTS.contains(&extension) {
+        return Some(format!("macro-enabled document .{}", extension));
+    }
+    if contents.starts_with(b"MZ") {
+        return Some("Windows executable content".to_string());
+    }
+    if contents.starts_with(b"\x7fELF") {
+        return Some("ELF executable content".to_string());
+    }
+    if contents.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]) || contents.starts_with(&[0xca, 0xfe, 0xba, 0xbe]) {
+        return Some("Mach-O executable content".to_string());
+    }
+    if contents.starts_with(b"#!") {
+        return Some("script with an interpreter line".to_string());
+    }
+    if contents.starts_with(&[0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1]) {
+        return Some("legacy Office document, which may contain macros".to_string());
+    }
+    None
+}
@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+
+const MAGIC: &[u8] = b"GQGDEDUP1\n";
+const MIN_CHUNK: usize = 16 << 10;
+const MAX_CHUNK: usize = 256 << 10;
+const MASK: u64 = (1 << 16) - 1;
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    file_name: String,
+    size: u64,
+    chunks: Vec<String>,
+}
+
+pub struct Packed {
+    pub bundle: Vec<u8>,
+    pub new_chunks: Vec<String>,
+    pub total: usize,
+}
+
+fn gear() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for entry in table.iter_mut() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *entry = z ^ (z >> 31);
+    }
+    table
+}
+
+fn chunks(data: &[u8]) -> Vec<&[u8]> {
+    let table = gear();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let end = (start + MAX_CHUNK).min(data.len());
+        let mut cut = end;
+        let mut hash: u64 = 0;
+        for (i, &byte) in data[start..end].iter().enumerate() {
+            hash = (hash << 1).wrapping_add(table[byte as usize]);
+            if i + 1 >= MIN_CHUNK && hash & MASK == 0 {
+                cut = start + i + 1;
+                break;
+            }
+        }
+        chunks.push(&data[start..cut]);
+        start = cut;
+    }
+    chunks
+}
+
+fn chunk_id(chunk: &[u8]) -> String {
+    sodiumoxide::hex::encode(sha256::hash(chunk).0)
+}
+
+fn store_dir_buf() -> PathBuf {
+    crate::settings::state_path_buf("chunks")
+}
+
+fn known_path_buf(friend: &str) -> PathBuf {
+    crate::settings::state_path_buf("dedup").join(crate::fsutil::sanitize_file_name(friend))
+}
+
+fn pending_path_buf(friend: &str) -> PathBuf {
+    crate::settings::state_path_buf("dedup").join(format!("{}.pending", crate::fsutil::sanitize_file_name(friend)))
+}
+
+fn read_ids(path: &Path) -> Result<BTreeSet<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().map(|line| line.to_string()).collect()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+        Err(_) => Err(anyhow!("Unable to read delivered chunks.")),
+    }
+}
+
+fn append_ids(path: &Path, ids: &[String]) -> Result<()> {
+    crate::fsutil::create_dir_private(path.parent().unwrap())?;
+    let mut file = crate::fsutil::append_private(path)
+        .map_err(|_| anyhow!("Unable to record delivered chunks."))?;
+    for id in ids {
+        writeln!(file, "{}", id).map_err(|_| anyhow!("Unable to record delivered chunks."))?;
+    }
+    Ok(())
+}
+
+fn remove(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(_) => Err(anyhow!("Unable to reset delivered chunks.")),
+    }
+}
+
+pub fn known(friend: &str) -> Result<BTreeSet<String>> {
+    read_ids(&known_path_buf(friend))
+}
+
+pub fn stage(friend: &str, ids: &[String]) -> Result<()> {
+    append_ids(&pending_path_buf(friend), ids)
+}
+
+pub fn confirm(friend: &str) -> Result<usize> {
+    let known = known(friend)?;
+    let pending: Vec<String> = read_ids(&pending_path_buf(friend))?.into_iter().filter(|id| !known.contains(id)).collect();
+    append_ids(&known_path_buf(friend), &pending)?;
+    remove(&pending_path_buf(friend))?;
+    Ok(pending.len())
+}
+
+pub fn forget(friend: &str) -> Result<()> {
+    remove(&known_path_buf(friend))?;
+    remove(&pending_path_buf(friend))
+}
+
+pub fn pack(file_name: &str, data: &[u8], known: &BTreeSet<String>) -> Result<Packed> {
+    let chunks = chunks(data);
+    let ids: Vec<String> = chunks.iter().map(|chunk| chunk_id(chunk)).collect();
+    let manifest = serde_json::to_vec(&Manifest {
+        file_name: file_name.to_string(),
+        size: data.len() as u64,
+        chunks: ids.clone(),
+    })?;
+    let mut bundle = MAGIC.to_vec();
+    bundle.extend_from_slice(&(manifest.len() as u32).to_le_bytes());
+    bundle.extend_from_slice(&manifest);
+    let mut new_chunks = Vec::new();
+    for (id, chunk) in ids.iter().zip(chunks.iter()) {
+        if known.contains(id) || new_chunks.contains(id) {
+            continue;
+        }
+        bundle.extend_from_slice(&sodiumoxide::hex::decode(id).unwrap_or_default());
+        bundle.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        bundle.extend_from_slice(chunk);
+        new_chunks.push(id.clone());
+    }
+    Ok(Packed { bundle, new_chunks, total: ids.len() })
+}
+
+fn take<'a>(data: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = offset.checked_add(len).filter(|&end| end <= data.len()).ok_or(anyhow!("Chunked file is corrupted."))?;
+    let bytes = &data[*offset..end];
+    *offset = end;
+    Ok(bytes)
+}
+
+fn take_u32(data: &[u8], offset: &mut usize) -> Result<usize> {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(take(data, offset, 4)?);
+    Ok(u32::from_le_bytes(word) as usize)
+}
+
+fn split(contents: &[u8]) -> Result<Option<(Manifest, BTreeMap<String, &[u8]>)>> {
+    if !contents.starts_with(MAGIC) {
+        return Ok(None);
+    }
+    let mut offset = MAGIC.len();
+    let manifest_len = take_u32(contents, &mut offset)?;
+    let manifest: Manifest = serde_json::from_slice(take(contents, &mut offset, manifest_len)?)
+        .map_err(|_| anyhow!("Chunked file is corrupted."))?;
+    let mut inline = BTreeMap::new();
+    while offset < contents.len() {
+        let id = sodiumoxide::hex::encode(take(contents, &mut offset, 32)?);
+        let len = take_u32(contents, &mut offset)?;
+        let chunk = take(contents, &mut offset, len)?;
+        if chunk_id(chunk) != id {
+            return Err(anyhow!("Chunked file is corrupted."));
+        }
+        inline.insert(id, chunk);
+    }
+    Ok(Some((manifest, inline)))
+}
+
+pub fn rebuild(contents: &[u8]) -> Result<Option<(String, Vec<u8>)>> {
+    Ok(rebuild_in(&store_dir_buf(), &[contents])?.pop().flatten())
+}
+
+pub fn rebuild_all(bundles: &[&[u8]]) -> Result<Vec<Option<(String, Vec<u8>)>>> {
+    rebuild_in(&store_dir_buf(), bundles)
+}
+
+fn rebuild_in(dir: &Path, bundles: &[&[u8]]) -> Result<Vec<Option<(String, Vec<u8>)>>> {
+    let mut split_bundles = Vec::new();
+    let mut pool = BTreeMap::new();
+    for contents in bundles {
+        let split = split(contents)?;
+        if let Some((_, inline)) = &split {
+            pool.extend(inline.iter().map(|(id, chunk)| (id.clone(), *chunk)));
+        }
+        split_bundles.push(split);
+    }
+    let mut rebuilt = Vec::new();
+    for split in split_bundles {
+        let manifest = match split {
+            Some((manifest, _)) => manifest,
+            None => {
+                rebuilt.push(None);
+                continue;
+            }
+        };
+        let mut data = Vec::new();
+        let mut missing = 0;
+        for id in &manifest.chunks {
+            if let Some(chunk) = pool.get(id) {
+                data.extend_from_slice(chunk);
+                continue;
+            }
+            match std::fs::read(dir.join(crate::fsutil::sanitize_file_name(id))) {
+                Ok(chunk) if chunk_id(&chunk) == *id => data.extend_from_slice(&chunk),
+                _ => missing += 1,
+            }
+        }
+        if missing > 0 {
+            return Err(anyhow!("{} {}", missing, crate::i18n::tr("dedup.missing")));
+        }
+        rebuilt.push(Some((manifest.file_name, data)));
+    }
+    Ok(rebuilt)
+}
+
+pub fn keep(bundles: &[&[u8]]) -> Result<()> {
+    keep_in(&store_dir_buf(), bundles)
+}
+
+fn keep_in(dir: &Path, bundles: &[&[u8]]) -> Result<()> {
+    for contents in bundles {
+        if let Some((_, inline)) = split(contents)? {
+            crate::fsutil::create_dir_private(dir)?;
+            for (id, chunk) in inline {
+                let path = dir.join(&id);
+                if !path.exists() {
+                    crate::fsutil::write_private_file(&path, chunk)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+
+    fn sample(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+
+    #[test]
+    fn chunks_cover_input() {
+        let data = sample(1 << 20, 1);
+        let chunks = chunks(&data);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), data);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= MIN_CHUNK && chunk.len() <= MAX_CHUNK);
+        }
+        assert!(super::chunks(&[]).is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = testutil::scratch("dedup", "round-trip");
+        let data = sample(1 << 20, 2);
+        let packed = pack("disk.img", &data, &BTreeSet::new()).unwrap();
+        assert_eq!(packed.new_chunks.len(), packed.total);
+        assert_eq!(rebuild_in(&dir, &[&packed.bundle]).unwrap(), vec![Some(("disk.img".to_string(), data.clone()))]);
+        keep_in(&dir, &[&packed.bundle]).unwrap();
+        let known: BTreeSet<String> = packed.new_chunks.into_iter().collect();
+        let mut edited = data.clone();
+        edited.extend_from_slice(b"appended");
+        let again = pack("disk.img", &edited, &known).unwrap();
+        assert!(again.new_chunks.len() < again.total);
+        assert!(again.bundle.len() < edited.len() / 2);
+        assert_eq!(rebuild_in(&dir, &[&again.bundle]).unwrap(), vec![Some(("disk.img".to_string(), edited))]);
+        assert!(rebuild_in(&testutil::scratch("dedup", "empty"), &[&again.bundle]).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rebuilds_in_memory_without_persisting() {
+        let dir = testutil::scratch("dedup", "memory");
+        let data = sample(1 << 20, 4);
+        let first = pack("a", &data, &BTreeSet::new()).unwrap();
+        let known: BTreeSet<String> = first.new_chunks.iter().cloned().collect();
+        let second = pack("b", &data, &known).unwrap();
+        assert!(second.new_chunks.is_empty());
+        let rebuilt = rebuild_in(&dir, &[&first.bundle, &second.bundle]).unwrap();
+        assert_eq!(rebuilt, vec![Some(("a".to_string(), data.clone())), Some(("b".to_string(), data))]);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn malformed_bundles() {
+        let dir = testutil::scratch("dedup", "malformed");
+        let packed = pack("f", &sample(100_000, 3), &BTreeSet::new()).unwrap();
+        assert_eq!(rebuild_in(&dir, &[b"not chunked"]).unwrap(), vec![None]);
+        assert!(rebuild_in(&dir, &[&packed.bundle[..packed.bundle.len() - 1]]).is_err());
+        assert!(rebuild_in(&dir, &[&packed.bundle[..MAGIC.len() + 2]]).is_err());
+        let mut tampered = packed.bundle.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(rebuild_in(&dir, &[&tampered]).is_err());
+        assert!(keep_in(&dir, &[&tampered]).is_err());
+        assert!(!dir.exists());
+    }
+}
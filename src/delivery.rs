@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const MAX_BACKOFF: i64 = 6 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Item {
+    pub id: u64,
+    pub created: String,
+    pub transport: String,
+    pub recipient: String,
+    pub target: String,
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt: i64,
+    pub last_error: String,
+    pub failed: bool,
+}
+
+fn path_buf() -> PathBuf {
+    crate::settings::state_path_buf("delivery-queue.json")
+}
+
+pub fn load() -> Result<Vec<Item>> {
+    match std::fs::read(path_buf()) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|_| anyhow!("Delivery queue is corrupted.")),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read the delivery queue.")),
+    }
+}
+
+pub fn store(items: &[Item]) -> Result<()> {
+    crate::fsutil::replace_private_file(&path_buf(), &serde_json::to_vec_pretty(items)?)
+}
+
+pub fn add(transport: &str, recipient: &str, target: &str, payload: &str) -> Result<u64> {
+    let mut items = load()?;
+    let id = items.iter().map(|item| item.id).max().unwrap_or(0) + 1;
+    items.push(Item {
+        id,
+        created: chrono::Utc::now().to_rfc3339(),
+        transport: transport.to_string(),
+        recipient: recipient.to_string(),
+        target: target.to_string(),
+        payload: payload.to_string(),
+        ..Default::default()
+    });
+    store(&items)?;
+    Ok(id)
+}
+
+pub fn backoff(base: i64, attempts: u32) -> i64 {
+    let doublings = attempts.saturating_sub(1).min(20);
+    base.saturating_mul(1 << doublings).min(MAX_BACKOFF)
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const MAGIC: &[u8] = b"GQGDELTA1\n";
+const BLOCK: usize = 2048;
+const COPY: u8 = b'C';
+const INSERT: u8 = b'I';
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    file_name: String,
+    base_sha256: String,
+    sha256: String,
+    size: u64,
+}
+
+enum Op<'a> {
+    Copy(u64, u64),
+    Insert(&'a [u8]),
+}
+
+fn weak(block: &[u8]) -> (u32, u32) {
+    let mut a: u32 = 0;
+    let mut b: u32 = 0;
+    for (i, &byte) in block.iter().enumerate() {
+        a = a.wrapping_add(byte as u32);
+        b = b.wrapping_add(((block.len() - i) as u32).wrapping_mul(byte as u32));
+    }
+    (a & 0xffff, b & 0xffff)
+}
+
+fn key(a: u32, b: u32) -> u32 {
+    a | (b << 16)
+}
+
+fn push<'a>(ops: &mut Vec<Op<'a>>, op: Op<'a>) {
+    if let Op::Insert(bytes) = &op {
+        if bytes.is_empty() {
+            return;
+        }
+    }
+    if let (Some(Op::Copy(offset, len)), Op::Copy(next, more)) = (ops.last_mut(), &op) {
+        if *offset + *len == *next {
+            *len += *more;
+            return;
+        }
+    }
+    ops.push(op);
+}
+
+pub fn diff(file_name: &str, base: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, block) in base.chunks_exact(BLOCK).enumerate() {
+        let (a, b) = weak(block);
+        index.entry(key(a, b)).or_default().push(i);
+    }
+    let mut ops = Vec::new();
+    let mut literal = 0;
+    let mut pos = 0;
+    let (mut a, mut b) = if data.len() >= BLOCK { weak(&data[..BLOCK]) } else { (0, 0) };
+    while pos + BLOCK <= data.len() {
+        let window = &data[pos..pos + BLOCK];
+        let found = index.get(&key(a, b))
+            .and_then(|blocks| blocks.iter().find(|&&i| base[i * BLOCK..(i + 1) * BLOCK] == *window))
+            .copied();
+        match found {
+            Some(i) => {
+                push(&mut ops, Op::Insert(&data[literal..pos]));
+                push(&mut ops, Op::Copy((i * BLOCK) as u64, BLOCK as u64));
+                pos += BLOCK;
+                literal = pos;
+                if pos + BLOCK <= data.len() {
+                    (a, b) = weak(&data[pos..pos + BLOCK]);
+                }
+            }
+            None => {
+                if pos + BLOCK < data.len() {
+                    let out = data[pos] as u32;
+                    let incoming = data[pos + BLOCK] as u32;
+                    a = a.wrapping_sub(out).wrapping_add(incoming) & 0xffff;
+                    b = b.wrapping_sub((BLOCK as u32).wrapping_mul(out)).wrapping_add(a) & 0xffff;
+                }
+                pos += 1;
+            }
+        }
+    }
+    push(&mut ops, Op::Insert(&data[literal..]));
+    let manifest = serde_json::to_vec(&Manifest {
+        file_name: file_name.to_string(),
+        base_sha256: crate::sha256_hex(base),
+        sha256: crate::sha256_hex(data),
+        size: data.len() as u64,
+    })?;
+    let mut bundle = MAGIC.to_vec();
+    bundle.extend_from_slice(&(manifest.len() as u32).to_le_bytes());
+    bundle.extend_from_slice(&manifest);
+    for op in ops {
+        match op {
+            Op::Copy(offset, len) => {
+                bundle.push(COPY);
+                bundle.extend_from_slice(&offset.to_le_bytes());
+                bundle.extend_from_slice(&len.to_le_bytes());
+            }
+            Op::Insert(bytes) => {
+                bundle.push(INSERT);
+                bundle.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                bundle.extend_from_slice(bytes);
+            }
+        }
+    }
+    Ok(bundle)
+}
+
+fn take<'a>(data: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = offset.checked_add(len).filter(|&end| end <= data.len()).ok_or(anyhow!("Delta is corrupted."))?;
+    let bytes = &data[*offset..end];
+    *offset = end;
+    Ok(bytes)
+}
+
+fn take_u64(data: &[u8], offset: &mut usize) -> Result<u64> {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(take(data, offset, 8)?);
+    Ok(u64::from_le_bytes(word))
+}
+
+fn manifest(contents: &[u8]) -> Result<Option<(Manifest, usize)>> {
+    if !contents.starts_with(MAGIC) {
+        return Ok(None);
+    }
+    let mut offset = MAGIC.len();
+    let mut word = [0u8; 4];
+    word.copy_from_slice(take(contents, &mut offset, 4)?);
+    let manifest = serde_json::from_slice(take(contents, &mut offset, u32::from_le_bytes(word) as usize)?)
+        .map_err(|_| anyhow!("Delta is corrupted."))?;
+    Ok(Some((manifest, offset)))
+}
+
+pub fn base_of(contents: &[u8]) -> Result<Option<(String, String)>> {
+    Ok(manifest(contents)?.map(|(manifest, _)| (manifest.file_name, manifest.base_sha256)))
+}
+
+pub fn apply(contents: &[u8], base: &[u8]) -> Result<Vec<u8>> {
+    let (manifest, mut offset) = manifest(contents)?.ok_or(anyhow!("Delta is corrupted."))?;
+    if crate::sha256_hex(base) != manifest.base_sha256 {
+        return Err(anyhow!("Delta base does not match."));
+    }
+    let mut data = Vec::new();
+    while offset < contents.len() {
+        let tag = take(contents, &mut offset, 1)?[0];
+        match tag {
+            COPY => {
+                let start = take_u64(contents, &mut offset)? as usize;
+                let len = take_u64(contents, &mut offset)? as usize;
+                let end = start.checked_add(len).filter(|&end| end <= base.len()).ok_or(anyhow!("Delta is corrupted."))?;
+                data.extend_from_slice(&base[start..end]);
+            }
+            INSERT => {
+                let len = take_u64(contents, &mut offset)? as usize;
+                data.extend_from_slice(take(contents, &mut offset, len)?);
+            }
+            _ => return Err(anyhow!("Delta is corrupted.")),
+        }
+        if data.len() as u64 > manifest.size {
+            return Err(anyhow!("Delta is corrupted."));
+        }
+    }
+    if data.len() as u64 != manifest.size || crate::sha256_hex(&data) != manifest.sha256 {
+        return Err(anyhow!("Delta result does not match its checksum."));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let base = sample(20_000, 1);
+        let mut data = base[..5000].to_vec();
+        data.extend_from_slice(&sample(100, 2));
+        data.extend_from_slice(&base[5000..]);
+        data[15_000] ^= 0xff;
+        let patch = diff("report.pdf", &base, &data).unwrap();
+        assert!(patch.len() < data.len() / 2);
+        assert_eq!(apply(&patch, &base).unwrap(), data);
+        assert_eq!(base_of(&patch).unwrap(), Some(("report.pdf".to_string(), crate::sha256_hex(&base))));
+    }
+
+    #[test]
+    fn short_and_unrelated_data() {
+        let base = sample(10_000, 3);
+        for data in [Vec::new(), sample(10, 4), sample(10_000, 5)] {
+            let patch = diff("f", &base, &data).unwrap();
+            assert_eq!(apply(&patch, &base).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_base() {
+        let base = sample(10_000, 6);
+        let patch = diff("f", &base, &sample(10_000, 7)).unwrap();
+        assert!(apply(&patch, &sample(10_000, 8)).is_err());
+    }
+
+    #[test]
+    fn malformed_delta() {
+        let base = sample(10_000, 9);
+        let mut data = base.clone();
+        data[0] ^= 1;
+        let patch = diff("f", &base, &data).unwrap();
+        assert!(base_of(b"not a delta").unwrap().is_none());
+        assert!(apply(b"not a delta", &base).is_err());
+        assert!(apply(&patch[..patch.len() - 1], &base).is_err());
+        assert!(base_of(&patch[..MAGIC.len() + 2]).is_err());
+        let mut tampered = patch.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(apply(&tampered, &base).is_err());
+        let mut overlong = patch;
+        overlong.push(COPY);
+        overlong.extend_from_slice(&0u64.to_le_bytes());
+        overlong.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(apply(&overlong, &base).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use anyhow::Result;
+
+pub const VERSION: &str = "gqg1";
+
+pub fn parse_fields(text: &str) -> BTreeMap<String, String> {
+    text.split([';', '\n'])
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+pub struct Record {
+    pub id: String,
+    pub sigkey: Option<String>,
+    pub published: Option<String>,
+}
+
+pub fn signed_text(id: &str, published: &str, sigkey: &str) -> String {
+    format!("v={};id={};published={};sigkey={}", VERSION, id, published, sigkey)
+}
+
+fn parse_record(text: &str) -> Option<Record> {
+    let fields = parse_fields(text);
+    if fields.get("v").map(|v| v.as_str()) != Some(VERSION) {
+        return None;
+    }
+    let id = fields.get("id")?;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || "+/=-_".contains(c)) {
+        return None;
+    }
+    let sigkey = match fields.get("sigkey") {
+        Some(sigkey) => Some((sigkey.clone(), crate::signing::decode_public(sigkey)?)),
+        None => None,
+    };
+    let published = match (fields.get("published"), &sigkey, fields.get("sig")) {
+        (Some(published), Some((encoded, public_key)), Some(sig)) => {
+            if !crate::signing::verify(signed_text(id, published, encoded).as_bytes(), sig, public_key) {
+                return None;
+            }
+            Some(published.clone())
+        }
+        _ => None,
+    };
+    Some(Record { id: id.clone(), sigkey: sigkey.map(|(encoded, _)| encoded), published })
+}
+
+fn pins_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("discovery-pins.json")
+}
+
+fn load_pins() -> Result<BTreeMap<String, String>> {
+    match std::fs::read(pins_path_buf()) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|_| anyhow!("Discovery pins are corrupted.")),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(_) => Err(anyhow!("Unable to read discovery pins.")),
+    }
+}
+
+pub fn pinned(address: &str) -> Result<Option<String>> {
+    Ok(load_pins()?.remove(&address.to_lowercase()))
+}
+
+pub fn pin(address: &str, sigkey: &str) -> Result<()> {
+    let mut pins = load_pins()?;
+    pins.insert(address.to_lowercase(), sigkey.to_string());
+    crate::fsutil::replace_private_file(&pins_path_buf(), &serde_json::to_vec_pretty(&pins)?)
+}
+
+pub fn split_address(address: &str) -> Result<(&str, &str)> {
+    match address.split_once('@') {
+        Some((user, domain)) if !user.is_empty() && domain.contains('.') && !domain.contains('/') => Ok((user, domain)),
+        _ => Err(anyhow!("Expected an address like user@example.org.")),
+    }
+}
+
+pub fn well_known_url(user: &str, domain: &str) -> String {
+    format!("https://{}/.well-known/gqg/{}", domain, user)
+}
+
+pub fn dns_name(user: &str, domain: &str) -> String {
+    format!("{}._gqg.{}", user, domain)
+}
+
+pub fn lookup_well_known(user: &str, domain: &str) -> Option<Record> {
+    let body = ureq::get(&well_known_url(user, domain)).call().ok()?.into_string().ok()?;
+    parse_record(&body)
+}
+
+pub fn lookup_dns(user: &str, domain: &str) -> Option<Record> {
+    let resolver = trust_dns_resolver::Resolver::from_system_conf().ok()?;
+    let lookup = resolver.txt_lookup(dns_name(user, domain).as_str()).ok()?;
+    lookup.iter().find_map(|txt| {
+        let text: String = txt.txt_data()
+            .iter()
+            .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+            .collect();
+        parse_record(&text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sodiumoxide::crypto::sign;
+
+    fn signed_record(id: &str) -> String {
+        sodiumoxide::init().unwrap();
+        let (public_key, secret_key) = sign::keypair_from_seed(&sign::Seed([4; sign::SEEDBYTES]));
+        let sigkey = crate::signing::encode_public(&public_key);
+        let published = "2026-01-01T00:00:00Z";
+        let sig = crate::signing::sign(signed_text(id, published, &sigkey).as_bytes(), &secret_key);
+        format!("v={}\nid={}\npublished={}\nsigkey={}\nsig={}\n", VERSION, id, published, sigkey, sig)
+    }
+
+    #[test]
+    fn parses_signed_records() {
+        let record = parse_record(&signed_record("abc+/=")).unwrap();
+        assert_eq!(record.id, "abc+/=");
+        assert_eq!(record.published.as_deref(), Some("2026-01-01T00:00:00Z"));
+        assert!(record.sigkey.is_some());
+    }
+
+    #[test]
+    fn parses_unsigned_records() {
+        let record = parse_record("v=gqg1; id=abc").unwrap();
+        assert!(record.sigkey.is_none() && record.published.is_none());
+        let sigkey = parse_record(&signed_record("abc")).unwrap().sigkey.unwrap();
+        let record = parse_record(&format!("v=gqg1; id=abc; sigkey={}", sigkey)).unwrap();
+        assert_eq!(record.sigkey, Some(sigkey));
+        assert!(record.published.is_none());
+    }
+
+    #[test]
+    fn rejects_bad_records() {
+        let signed = signed_record("abc");
+        assert!(parse_record(&signed.replace("id=abc", "id=abd")).is_none());
+        assert!(parse_record(&signed.replace("published=2026", "published=2027")).is_none());
+        assert!(parse_record(&signed.replace("v=gqg1", "v=gqg2")).is_none());
+        assert!(parse_record("v=gqg1; id=").is_none());
+        assert!(parse_record("v=gqg1; id=a b<c>").is_none());
+        assert!(parse_record("v=gqg1; id=abc; sigkey=!!").is_none());
+        assert!(parse_record("id=abc").is_none());
+    }
+
+    #[test]
+    fn splits_addresses() {
+        assert_eq!(split_address("alice@example.org").unwrap(), ("alice", "example.org"));
+        assert!(split_address("@example.org").is_err());
+        assert!(split_address("alice@localhost").is_err());
+        assert!(split_address("alice@example.org/x").is_err());
+        assert!(split_address("alice").is_err());
+    }
+}
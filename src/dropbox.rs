@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use crate::settings::Settings;
+
+pub fn dir_buf(settings: &Settings) -> PathBuf {
+    match settings.get("serve.dropbox_dir") {
+        "" => crate::settings::state_path_buf("dropbox"),
+        dir => PathBuf::from(dir),
+    }
+}
+
+pub fn looks_like_payload(body: &str) -> bool {
+    let body = body.trim();
+    !body.is_empty() && body.chars().all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
+}
+
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(_) => return Err(anyhow!("Unable to read {}.", dir.display())),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|ext| ext == "gqg").unwrap_or(false))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+pub fn store(dir: &Path, body: &str, max_count: usize) -> Result<PathBuf> {
+    crate::fsutil::create_dir_private(dir)?;
+    if max_count > 0 && list(dir)?.len() >= max_count {
+        return Err(anyhow!("Drop box is full."));
+    }
+    let name = format!("{}-{}.gqg",
+        chrono::Utc::now().format("%Y%m%d%H%M%S%6f"),
+        sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8)));
+    let path = dir.join(name);
+    crate::fsutil::replace_private_file(&path, body.trim().as_bytes())?;
+    Ok(path)
+}
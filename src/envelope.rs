@@ -0,0 +1,57 @@
+use sodiumoxide::crypto::hash::sha256;
+
+const HINT_HEADER: &str = "gqg-hint: ";
+
+pub fn hint(public_id: &str) -> String {
+    sodiumoxide::hex::encode(&sha256::hash(public_id.as_bytes()).0[..8])
+}
+
+pub fn wrap(hint: Option<String>, payload: String) -> String {
+    match hint {
+        None => payload,
+        Some(hint) => format!("{}{}\n{}", HINT_HEADER, hint, payload),
+    }
+}
+
+pub fn unwrap(payload: &str) -> (Option<String>, String) {
+    let payload = payload.replace("\r\n", "\n");
+    let trimmed = payload.trim_start();
+    if let Some(rest) = trimmed.strip_prefix(HINT_HEADER) {
+        let (hint, body) = rest.split_once('\n').unwrap_or((rest, ""));
+        return (Some(hint.trim().to_string()), body.to_string());
+    }
+    (None, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hints_are_short_and_stable() {
+        let hint = hint("alice-public-id");
+        assert_eq!(hint.len(), 16);
+        assert!(hint.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hint, super::hint("alice-public-id"));
+        assert_ne!(hint, super::hint("bob-public-id"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let hint = hint("alice-public-id");
+        let wrapped = wrap(Some(hint.clone()), "payload\nsecond line".to_string());
+        assert!(wrapped.starts_with(HINT_HEADER));
+        assert_eq!(unwrap(&wrapped), (Some(hint.clone()), "payload\nsecond line".to_string()));
+        assert_eq!(unwrap(&format!("\n  {}", wrapped)), (Some(hint), "payload\nsecond line".to_string()));
+        assert_eq!(wrap(None, "payload".to_string()), "payload");
+        assert_eq!(unwrap("payload"), (None, "payload".to_string()));
+    }
+
+    #[test]
+    fn unwraps_crlf_envelopes() {
+        let wrapped = wrap(Some("00ff00ff00ff00ff".to_string()), "line one\nline two".to_string());
+        let crlf = wrapped.replace('\n', "\r\n");
+        assert_eq!(unwrap(&crlf), (Some("00ff00ff00ff00ff".to_string()), "line one\nline two".to_string()));
+        assert_eq!(unwrap("payload\r\n"), (None, "payload\n".to_string()));
+    }
+}
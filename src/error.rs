@@ -0,0 +1,30 @@
+use crate::i18n;
+
+#[derive(Debug)]
+pub enum Error {
+    FriendNotFound,
+    NotAddressed,
+    DecryptFailed,
+    UnknownSenderRefused,
+    DangerousRefused,
+    FileExists,
+    TooLarge(u64),
+    ReadOnly,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::FriendNotFound => write!(f, "{}", i18n::tr("error.friend_not_found")),
+            Error::NotAddressed => write!(f, "{}", i18n::tr("receive.not_addressed")),
+            Error::DecryptFailed => write!(f, "{}", i18n::tr("receive.failed")),
+            Error::UnknownSenderRefused => write!(f, "{}", i18n::tr("receive.refused")),
+            Error::DangerousRefused => write!(f, "{}", i18n::tr("receive.dangerous_refused")),
+            Error::FileExists => write!(f, "{}", i18n::tr("error.file_exists")),
+            Error::TooLarge(limit) => write!(f, "{} {} bytes (limits.max_input).", i18n::tr("error.too_large"), limit),
+            Error::ReadOnly => write!(f, "{}", i18n::tr("error.read_only")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+use anyhow::Result;
+
+const RESERVED: &[u8] = b"GQG";
+const RAW: &[u8] = b"GQGRAW1\n";
+
+pub fn escape(contents: &[u8]) -> Cow<'_, [u8]> {
+    if !contents.starts_with(RESERVED) {
+        return Cow::Borrowed(contents);
+    }
+    let mut escaped = Vec::with_capacity(RAW.len() + contents.len());
+    escaped.extend_from_slice(RAW);
+    escaped.extend_from_slice(contents);
+    Cow::Owned(escaped)
+}
+
+pub fn unescape(contents: &[u8]) -> &[u8] {
+    contents.strip_prefix(RAW).unwrap_or(contents)
+}
+
+pub fn open(contents: &[u8]) -> Result<&[u8]> {
+    Ok(unescape(crate::padding::strip(contents)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOKALIKES: &[&[u8]] = &[
+        b"GQGPAD1\n\0\0\0\0\0\0\0\0",
+        b"GQGCHAFF1\nnot chaff",
+        b"GQGDEDUP1\n{}",
+        b"GQGDELTA1\n{}",
+        b"GQGSYNC1\n{}",
+        b"GQGMIME1\ntext/plain\nhello",
+        b"GQGRAW1\nalready escaped",
+        b"GQG",
+    ];
+
+    #[test]
+    fn plain_contents_are_borrowed() {
+        assert!(matches!(escape(b"hello"), Cow::Borrowed(_)));
+        assert!(matches!(escape(b""), Cow::Borrowed(_)));
+        assert_eq!(unescape(b"hello"), b"hello");
+    }
+
+    #[test]
+    fn lookalikes_round_trip() {
+        for contents in LOOKALIKES {
+            let escaped = escape(contents);
+            assert!(escaped.starts_with(RAW));
+            assert_eq!(unescape(&escaped), *contents);
+            assert!(!crate::chaff::is_chaff(&escaped));
+            assert_eq!(crate::mime::unwrap(&escaped), (None, &escaped[..]));
+            assert!(crate::dedup::rebuild(&escaped).unwrap().is_none());
+            assert!(crate::sync::unpack(&escaped).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn open_strips_padding_then_escape() {
+        for contents in LOOKALIKES.iter().chain([&b"hello"[..]].iter()) {
+            let escaped = escape(contents);
+            let padded = crate::padding::pad("padme", &escaped).unwrap().unwrap();
+            assert_eq!(open(&padded).unwrap(), *contents);
+            assert_eq!(open(&escaped).unwrap(), *contents);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::io::Write;
+use std::ops::Deref;
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::Result;
+
+const MAP_THRESHOLD: u64 = 1 << 20;
+const CHUNK_SIZE: usize = 1 << 20;
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+static SHARED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+pub fn set_shared(shared: bool) {
+    SHARED.store(shared, Ordering::Relaxed);
+}
+
+pub fn is_writable(path: &Path) -> bool {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().readonly() => false,
+        Ok(_) => std::fs::OpenOptions::new().append(true).open(path).is_ok(),
+        Err(_) => true,
+    }
+}
+
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    const LONG_PATH: usize = 248;
+    let text = path.to_string_lossy();
+    if text.len() < LONG_PATH || !path.is_absolute() || text.starts_with(r"\\?\") {
+        return Cow::Borrowed(path);
+    }
+    let text = text.replace('/', "\\");
+    match text.strip_prefix(r"\\") {
+        Some(unc) => Cow::Owned(std::path::PathBuf::from(format!(r"\\?\UNC\{}", unc))),
+        None => Cow::Owned(std::path::PathBuf::from(format!(r"\\?\{}", text))),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+#[cfg(unix)]
+fn is_private_to_writers(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o022 == 0
+}
+
+#[cfg(not(unix))]
+fn is_private_to_writers(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+pub fn read_file(path: &Path) -> Result<Box<dyn Deref<Target = [u8]>>> {
+    let path = long_path(path);
+    let file = std::fs::File::open(&path).map_err(|_| anyhow!("Unable to open file."))?;
+    let metadata = file.metadata().map_err(|_| anyhow!("Unable to open file."))?;
+    if metadata.len() < MAP_THRESHOLD || !is_private_to_writers(&metadata) {
+        let contents = std::fs::read(&path).map_err(|_| anyhow!("Unable to open file."))?;
+        return Ok(Box::new(contents));
+    }
+    // SAFETY: the map is only valid while nobody else changes the file. If another process truncates
+    // it while we read, the access raises SIGBUS and kills gqg. Files that group or others could write
+    // are read into memory instead, and Windows refuses to truncate a file that is mapped.
+    let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|_| anyhow!("Unable to map file."))?;
+    Ok(Box::new(map))
+}
+
+fn write_chunks(mut file: std::fs::File, path: &Path, data: &[u8]) -> Result<()> {
+    for chunk in data.chunks(CHUNK_SIZE) {
+        file.write_all(chunk).map_err(|_| anyhow!("Unable to write {}.", path.display()))?;
+    }
+    file.sync_all().map_err(|_| anyhow!("Unable to write {}.", path.display()))
+}
+
+pub fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    let file = if SHARED.load(Ordering::Relaxed) {
+        std::fs::OpenOptions::new().write(true).create_new(true).open(long_path(path))
+    }
+    else {
+        create_private(path)
+    };
+    let file = file.map_err(|_| anyhow!("Unable to create {}.", path.display()))?;
+    write_chunks(file, path, data)
+}
+
+pub fn write_private_file(path: &Path, data: &[u8]) -> Result<()> {
+    let file = create_private(path).map_err(|_| anyhow!("Unable to create {}.", path.display()))?;
+    write_chunks(file, path, data)
+}
+
+pub fn replace_private_file(path: &Path, data: &[u8]) -> Result<()> {
+    let temp = path.with_extension("tmp");
+    let _ = std::fs::remove_file(&temp);
+    write_private_file(&temp, data)?;
+    std::fs::rename(long_path(&temp), long_path(path)).map_err(|_| anyhow!("Unable to write {}.", path.display()))
+}
+
+pub fn create_dir_private(path: &Path) -> Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(long_path(path)).map_err(|_| anyhow!("Unable to create {}.", path.display()))?;
+    #[cfg(windows)]
+    restrict_acl(path, true);
+    Ok(())
+}
+
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(anyhow!("{} already exists.", to.display()));
+    }
+    let (from, to) = (long_path(from), long_path(to));
+    if std::fs::rename(&from, &to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(&from, &to).map_err(|_| anyhow!("Unable to move {}.", from.display()))?;
+    std::fs::remove_file(&from).map_err(|_| anyhow!("Unable to remove {}.", from.display()))
+}
+
+const MAX_NAME_BYTES: usize = 200;
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+fn truncate(name: &str, max: usize) -> &str {
+    let mut end = max.min(name.len());
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+pub fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(|c| c == '/' || c == '\\').next().unwrap_or("");
+    let cleaned: String = base.chars()
+        .map(|c| if c.is_control() || is_bidi_control(c) || "<>:\"|?*".contains(c) { '_' } else { c })
+        .collect();
+    let mut cleaned = cleaned
+        .trim_start_matches(|c: char| c == '.' || c.is_whitespace())
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+    if cleaned.is_empty() {
+        cleaned = "unnamed".to_string();
+    }
+    let stem = cleaned.split('.').next().unwrap_or("").trim_end().to_uppercase();
+    if RESERVED_NAMES.contains(&stem.as_str()) {
+        cleaned = format!("_{}", cleaned);
+    }
+    if cleaned.len() > MAX_NAME_BYTES {
+        cleaned = match cleaned.rfind('.') {
+            Some(dot) if cleaned.len() - dot <= 16 => {
+                let extension = cleaned[dot..].to_string();
+                format!("{}{}", truncate(&cleaned, MAX_NAME_BYTES - extension.len()), extension)
+            }
+            _ => truncate(&cleaned, MAX_NAME_BYTES).to_string(),
+        };
+    }
+    cleaned
+}
+
+pub fn create_private(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(long_path(path))?;
+    #[cfg(windows)]
+    restrict_acl(path, false);
+    Ok(file)
+}
+
+pub fn append_private(path: &Path) -> std::io::Result<std::fs::File> {
+    #[cfg(windows)]
+    let created = !path.exists();
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(long_path(path))?;
+    #[cfg(unix)]
+    restrict(path);
+    #[cfg(windows)]
+    if created {
+        restrict_acl(path, false);
+    }
+    Ok(file)
+}
+
+#[cfg(windows)]
+fn restrict_acl(path: &Path, dir: bool) {
+    let user = match std::env::var("USERNAME") {
+        Ok(user) if !user.is_empty() => user,
+        _ => return,
+    };
+    let grant = if dir { format!("{}:(OI)(CI)F", user) } else { format!("{}:F", user) };
+    let _ = std::process::Command::new("icacls")
+        .arg(long_path(path).as_os_str())
+        .args(["/inheritance:r", "/grant:r", &grant])
+        .output();
+}
+
+#[cfg(unix)]
+pub fn restrict(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        let mode = if metadata.is_dir() { 0o700 } else { 0o600 };
+        if metadata.permissions().mode() & 0o077 != 0 {
+            let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode));
+        }
+    }
+}
+
+#[cfg(windows)]
+pub fn restrict(path: &Path) {
+    if let Ok(metadata) = std::fs::metadata(long_path(path)) {
+        restrict_acl(path, metadata.is_dir());
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn restrict(_path: &Path) {}
+
+#[cfg(unix)]
+pub fn shared_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 { Some(mode) } else { None }
+}
+
+#[cfg(not(unix))]
+pub fn shared_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_directories() {
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name(r"C:\x\y"), "y");
+        assert_eq!(sanitize_file_name("/etc/shadow"), "shadow");
+        assert_eq!(sanitize_file_name("dir/"), "unnamed");
+    }
+
+    #[test]
+    fn empty_and_dot_names() {
+        assert_eq!(sanitize_file_name(""), "unnamed");
+        assert_eq!(sanitize_file_name(".."), "unnamed");
+        assert_eq!(sanitize_file_name("."), "unnamed");
+        assert_eq!(sanitize_file_name(" .hidden. "), "hidden");
+    }
+
+    #[test]
+    fn replaces_control_and_bidi_characters() {
+        assert_eq!(sanitize_file_name("a\u{0}b\nc\u{7f}"), "a_b_c_");
+        assert_eq!(sanitize_file_name("invoice\u{202e}fdp.exe"), "invoice_fdp.exe");
+        assert_eq!(sanitize_file_name("x\u{2066}y\u{200f}z"), "x_y_z");
+        assert_eq!(sanitize_file_name("a<b>c:d|e?f*g\"h"), "a_b_c_d_e_f_g_h");
+    }
+
+    #[test]
+    fn escapes_reserved_device_names() {
+        assert_eq!(sanitize_file_name("CON"), "_CON");
+        assert_eq!(sanitize_file_name("nul.txt"), "_nul.txt");
+        assert_eq!(sanitize_file_name("COM1.tar.gz"), "_COM1.tar.gz");
+        assert_eq!(sanitize_file_name("CONSOLE.txt"), "CONSOLE.txt");
+    }
+
+    #[test]
+    fn truncates_long_names() {
+        let long = "a".repeat(300);
+        assert_eq!(sanitize_file_name(&long), "a".repeat(MAX_NAME_BYTES));
+        let with_extension = sanitize_file_name(&format!("{}.txt", long));
+        assert_eq!(with_extension.len(), MAX_NAME_BYTES);
+        assert!(with_extension.ends_with(".txt"));
+        let long_extension = sanitize_file_name(&format!("a.{}", long));
+        assert_eq!(long_extension.len(), MAX_NAME_BYTES);
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        let wide = "\u{20ac}".repeat(100);
+        let truncated = sanitize_file_name(&wide);
+        assert!(truncated.len() <= MAX_NAME_BYTES);
+        assert_eq!(truncated, "\u{20ac}".repeat(MAX_NAME_BYTES / 3));
+        let with_extension = sanitize_file_name(&format!("{}.pdf", wide));
+        assert!(with_extension.len() <= MAX_NAME_BYTES);
+        assert!(with_extension.ends_with(".pdf"));
+        assert!(with_extension.trim_end_matches(".pdf").chars().all(|c| c == '\u{20ac}'));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn maps_only_files_others_cannot_write() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("gqg-fsutil-map-{}", std::process::id()));
+        std::fs::write(&path, b"x").unwrap();
+        for (mode, private) in [(0o600, true), (0o644, true), (0o664, false), (0o606, false)] {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            assert_eq!(is_private_to_writers(&std::fs::metadata(&path).unwrap()), private, "{:o}", mode);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn prefixes_only_long_absolute_paths() {
+        let short = Path::new(r"C:\Users\gqg\config.json");
+        assert_eq!(long_path(short), short);
+        let deep = format!(r"C:\{}\config.json", "d".repeat(260));
+        assert_eq!(long_path(Path::new(&deep)).to_string_lossy(), format!(r"\\?\{}", deep));
+        let mixed = format!("C:/{}/config.json", "d".repeat(260));
+        assert_eq!(long_path(Path::new(&mixed)).to_string_lossy(), format!(r"\\?\{}", deep));
+        let unc = format!(r"\\server\share\{}", "d".repeat(260));
+        assert_eq!(long_path(Path::new(&unc)).to_string_lossy(), format!(r"\\?\UNC\server\share\{}", "d".repeat(260)));
+        let relative = "d".repeat(300);
+        assert_eq!(long_path(Path::new(&relative)), Path::new(&relative));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn reaches_files_past_max_path() {
+        let root = std::env::temp_dir().join(format!("gqg-fsutil-long-{}", std::process::id()));
+        let dir = (0..6).fold(root.clone(), |dir, i| dir.join(format!("{}{}", i, "d".repeat(50))));
+        create_dir_private(&dir).unwrap();
+        let path = dir.join("state.json");
+        assert!(path.to_string_lossy().len() > 260);
+        write_private_file(&path, b"first").unwrap();
+        replace_private_file(&path, b"second").unwrap();
+        assert_eq!(&**read_file(&path).unwrap(), b"second");
+        let _ = std::fs::remove_dir_all(long_path(&root));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn restricts_private_files_to_the_user() {
+        let user = match std::env::var("USERNAME") {
+            Ok(user) if !user.is_empty() => user,
+            _ => return,
+        };
+        let path = std::env::temp_dir().join(format!("gqg-fsutil-acl-{}", std::process::id()));
+        write_private_file(&path, b"x").unwrap();
+        let output = std::process::Command::new("icacls").arg(&path).output().unwrap();
+        let acl = String::from_utf8_lossy(&output.stdout).to_string();
+        let _ = std::fs::remove_file(&path);
+        assert!(acl.contains(&user), "{}", acl);
+        assert!(!acl.contains("(I)"), "{}", acl);
+        assert!(!acl.contains("BUILTIN\\Users") && !acl.contains("Everyone"), "{}", acl);
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Entry {
+    pub id: u64,
+    pub time: String,
+    pub direction: String,
+    pub peer: String,
+    pub trust: String,
+    pub identity: String,
+    pub kind: String,
+    pub file_name: String,
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub quarantined: bool,
+    pub release_path: String,
+    pub archive: String,
+    pub pruned: bool,
+    pub sealed: bool,
+    pub tags: Vec<String>,
+    pub flagged: bool,
+    pub mime_type: String,
+}
+
+fn index_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("history.jsonl")
+}
+
+pub fn load() -> Result<Vec<Entry>> {
+    let contents = match std::fs::read_to_string(index_path_buf()) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(_) => return Err(anyhow!("Unable to read history.")),
+    };
+    contents.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|_| anyhow!("History is corrupted.")))
+        .collect()
+}
+
+pub fn find(id: u64) -> Result<Entry> {
+    load()?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or(anyhow!("No history entry {}.", id))
+}
+
+pub fn update(entry: &Entry) -> Result<()> {
+    let mut contents = String::new();
+    for mut existing in load()? {
+        if existing.id == entry.id {
+            existing = entry.clone();
+        }
+        contents.push_str(&serde_json::to_string(&existing)?);
+        contents.push('\n');
+    }
+    crate::fsutil::replace_private_file(&index_path_buf(), contents.as_bytes()).map_err(|_| anyhow!("Unable to write history."))
+}
+
+pub fn append(mut entry: Entry) -> Result<u64> {
+    entry.id = load()?.last().map(|last| last.id + 1).unwrap_or(1);
+    entry.time = chrono::Utc::now().to_rfc3339();
+    let mut file = crate::fsutil::append_private(&index_path_buf())
+        .map_err(|_| anyhow!("Unable to open history."))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)
+        .map_err(|_| anyhow!("Unable to write history."))?;
+    Ok(entry.id)
+}
@@ -0,0 +1,40 @@
+use std::io::Write;
+use anyhow::Result;
+
+pub fn run(hook: &str, env: &[(&str, &str)]) -> Result<std::process::ExitStatus> {
+    let mut command = crate::profile::command(hook);
+    for (key, value) in env {
+        command.env(key, value);
+    }
+    command.status().map_err(|err| anyhow!("Unable to run hook {}: {}", hook, err))
+}
+
+pub fn before_send(recipient: &str, kind: &str, file_name: Option<&str>, contents: &[u8]) -> Result<Option<Vec<u8>>> {
+    let settings = crate::settings::Settings::load()?;
+    let hook = settings.get("hooks.on_send");
+    if hook.is_empty() {
+        return Ok(None);
+    }
+    if crate::fsutil::is_read_only() {
+        return Err(anyhow!(crate::i18n::tr("hooks.read_only")));
+    }
+    let path = std::env::temp_dir().join(format!("gqg-send-{}",
+        sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8))));
+    crate::fsutil::create_private(&path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|_| anyhow!("Unable to write plaintext for hook."))?;
+    let plaintext = path.to_string_lossy().to_string();
+    let status = run(hook, &[
+        ("GQG_RECIPIENT", recipient),
+        ("GQG_TYPE", kind),
+        ("GQG_FILE_NAME", file_name.unwrap_or("")),
+        ("GQG_PLAINTEXT", plaintext.as_str()),
+    ]);
+    let edited = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+    let status = status?;
+    if !status.success() {
+        return Err(anyhow!("Send vetoed by hook ({}).", status));
+    }
+    Ok(Some(edited.map_err(|_| anyhow!("Unable to read plaintext after hook."))?))
+}
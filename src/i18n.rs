@@ -0,0 +1,375 @@
+use std::sync::OnceLock;
+
+type Catalog = &'static [(&'static str, &'static str)];
+
+static EN: Catalog = &[
+    ("help.usage", "Usage:"),
+    ("help.flags", "Flags:"),
+    ("help.list", "List of identities and friends."),
+    ("help.newid", "Create a new local identity with random key."),
+    ("help.befriend", "Add a friend."),
+    ("help.unfriend", "Remove a friend."),
+    ("help.receive", "Decrypt incoming message."),
+    ("help.receive_volumes", "Decrypt a payload split into volumes."),
+    ("help.send", "Encrypt outgoing message to friend."),
+    ("help.sendfile", "Encrypt outgoing file to friend."),
+    ("help.active", "Set local identity for outgoing messages."),
+    ("help.dirs", "List of paths to configuration file and local storage."),
+    ("help.audit-log", "Show and verify the log of key and trust operations. The head hash it prints can be kept elsewhere to detect a rewritten log; with integrity.mac on, the head is also authenticated."),
+    ("help.config", "Show or change settings."),
+    ("help.bench", "Measure raw libsodium key generation and encrypt, decrypt, armor and dearmor throughput."),
+    ("help.whoami", "Show the active identity."),
+    ("help.invite", "Create a signed invitation blob with your public id."),
+    ("help.accept", "Add the sender of an invitation as a friend. The signature only shows the blob was not changed after signing; pass --expect with the signing key id the sender gave you another way, or confirm the id shown."),
+    ("help.pair", "Exchange ids with a contact and verify them by a short code."),
+    ("help.alias", "Add another name for a friend."),
+    ("help.unalias", "Remove a friend alias."),
+    ("help.stats", "Summarize identities, friends and message history."),
+    ("help.id", "Print a public id, and nothing else."),
+    ("help.inspect", "Show what can be told about a payload without decrypting it."),
+    ("help.inbox", "List received messages and files, optionally only tagged or flagged ones."),
+    ("help.verify-stored", "Check a stored message or file against its recorded checksum."),
+    ("help.release", "Move quarantined content to its normal location."),
+    ("help.seal", "Encrypt stdin or a file to your active identity."),
+    ("help.note_add", "Store stdin as a note sealed to the active identity."),
+    ("help.note_list", "List notes, optionally only those with a tag."),
+    ("help.note_show", "Print a note."),
+    ("help.note_edit", "Edit a note in $EDITOR, or replace it with stdin; --tag replaces tags."),
+    ("help.note_rm", "Delete a note."),
+    ("help.vault_set", "Store stdin as a secret sealed to the active identity."),
+    ("help.vault_get", "Print a secret, or copy it to the clipboard for a while."),
+    ("help.vault_list", "List the names of stored secrets."),
+    ("help.vault_rm", "Delete a secret."),
+    ("help.sent_list", "List sent messages and files."),
+    ("help.sent_show", "Print the archived copy of something sent."),
+    ("help.queue", "Encrypt stdin for a friend now and keep it in the outbox."),
+    ("help.queue_list", "List payloads waiting in the outbox."),
+    ("help.queue_flush", "Move all queued payloads to a directory (default: current)."),
+    ("help.queue_status", "Show mail, mqtt and nostr deliveries waiting for a retry or given up."),
+    ("help.queue_run", "Retry deliveries that are due, e.g. from cron."),
+    ("help.queue_retry", "Retry queue entry n now, even after it was given up."),
+    ("help.queue_drop", "Remove queue entry n without delivering it."),
+    ("help.mail_send", "Encrypt stdin for a friend and mail it to an address."),
+    ("help.mail_fetch", "Receive gqg payloads from unseen mail in the IMAP folder."),
+    ("help.push", "Encrypt stdin for a friend and publish it over mqtt or nostr."),
+    ("help.subscribe", "Receive payloads published to your identities' topics."),
+    ("help.pull", "Receive payloads for your identities from the Nostr relays."),
+    ("help.open", "Handle a gqg:befriend?name=&key= or gqg:msg?payload= link."),
+    ("help.register_handler", "Make gqg the handler for gqg: links."),
+    ("help.discover", "Look up a published id over HTTPS and DNS and befriend it."),
+    ("help.publish", "Print the well-known file and DNS record for discover; a name argument sets the user."),
+    ("help.provision", "Add friends from name,id,trust rows; --bundles writes each member a roster. The verified column is ignored unless --trust-verified is given and confirmed."),
+    ("help.serve", "Serve list, inbox, send and receive as a local REST API; --new-token rotates the token."),
+    ("help.selftest", "Check crypto known answers and an encrypt/decrypt round trip."),
+    ("help.debug_decode", "Show how far decryption gets for each identity."),
+    ("help.archive", "Move received items older than 30d, 6m, 1y... into an archive sealed to self."),
+    ("help.unarchive", "Restore the archive holding inbox entry n."),
+    ("help.prune", "Delete received items beyond the retention.* limits."),
+    ("help.open_stored", "Page the latest message, optionally from one friend, or view inbox entry n."),
+    ("help.read", "Write the contents of inbox entry n to stdout."),
+    ("help.search", "Find received items containing every term (prefix* allowed), or rebuild the index."),
+    ("help.tag_msg", "Add a tag to inbox entry n, or remove it."),
+    ("help.flag", "Flag inbox entry n."),
+    ("help.unflag", "Clear the flag on inbox entry n."),
+    ("help.export_thread", "Print the conversation with a friend as md, html or json."),
+    ("help.thread", "Show the conversation with a friend in order."),
+    ("help.rekey", "Replace a friend's key, keeping the old one to recognize earlier messages."),
+    ("help.audit", "Report weak spots in permissions, keys, trust and storage."),
+    ("help.dedup_forget", "Send every chunk again on the next sendfile --dedup to this friend."),
+    ("help.dedup_confirm", "Mark the chunks sent to this friend as delivered once they have received the files; until then sendfile --dedup and sync send those chunks again."),
+    ("help.sync", "Send the changes in a folder since the last confirmed sync; received changes apply to the same folder. Run with --confirm once the friend has received a sync, until then each sync sends those changes again."),
+    ("help.channel_create", "Create a broadcast channel and print its key for followers."),
+    ("help.channel_share", "Print the key of a channel again."),
+    ("help.channel_follow", "Follow a channel by its key."),
+    ("help.channel_post", "Encrypt and sign a post from stdin for a channel you own."),
+    ("help.channel_read", "Decrypt channel posts from stdin."),
+    ("help.channel_list", "List channels you own or follow."),
+    ("help.channel_rm", "Forget a channel."),
+    ("help.serve_dropbox", "Accept ciphertext from anyone on POST /drop and store it for gqg pull --local; holds no keys."),
+    ("help.pull_local", "Receive payloads stored by gqg serve --dropbox and remove the ones that decrypt."),
+    ("help.integrity_status", "Check the database and settings against their integrity MAC."),
+    ("help.integrity_accept", "Accept changes made to the database or settings outside gqg and MAC them again."),
+    ("help.integrity_off", "Remove the integrity key and MAC and stop creating them."),
+    ("help.chaff", "Publish dummy payloads to friends at random intervals so real traffic cannot be told apart."),
+    ("help.undo", "Reverse the last unfriend, rekey or trust change within undo.window."),
+    ("help.trash_list", "List unfriended contacts kept for trash.days."),
+    ("help.restore", "Bring back an unfriended contact from the trash."),
+    ("help.signkey", "Print the signing public key as a minisign or signify key file."),
+    ("help.signfile", "Sign a file for minisign (default) or signify; writes <file>.minisig or <file>.sig."),
+    ("help.verifyfile", "Check a minisign or signify signature (--sig <file>, default next to the file)."),
+    ("help.roster_export", "Send a roster of friends (all or the named ones) to a friend."),
+    ("help.roster_import", "Add friends from a roster after checking who signed it."),
+    ("help.flag.stdout", "Output to stdout, instead of file."),
+    ("help.flag.insecure", "Ignore sender authentication."),
+    ("help.flag.batch", "Never prompt; exit with code 3 when input is required."),
+    ("help.flag.timeout", "Give up reading stdin after this many idle seconds (0 waits forever)."),
+    ("help.flag.output", "Write ciphertext of send and sendfile to a file."),
+    ("help.flag.output_dir", "Write ciphertext of send and sendfile to one file per recipient."),
+    ("help.flag.volume_size", "Split sendfile output into numbered volumes of at most this size."),
+    ("help.flag.dedup", "Send only file chunks the friend has confirmed receiving (gqg dedup confirm)."),
+    ("help.flag.mime_type", "With sendfile: label the file with this content type (see send.mime_type to detect it)."),
+    ("help.flag.delta", "Send only the changes against a previous version the friend already received."),
+    ("help.flag.read_only", "Never change the database; receive prints to stdout. Automatic when the config file is not writable."),
+    ("help.flag.portable", "Keep all data in gqg-profile next to the executable; automatic when a gqg-portable file is there. Not on Windows."),
+    ("help.windows", "Not available on Windows: --config and GQG_CONFIG, portable mode and the selftest round trip. gqg finds its data through the Windows known folders, so setting %APPDATA% does not move it. gqg audit does not check file permissions there."),
+    ("help.flag.config", "Move the whole gqg profile so that its database is at this path (also GQG_CONFIG). This is not an arbitrary file: gqg chooses the file and directory names, so the path must end in the same names as the default database path and only the directory above them changes. Where gqg finds its config through HOME this also moves received messages and files. Not on Windows."),
+    ("help.flag.lang", "Language of messages, instead of $LANG."),
+    ("help.flag.as_file", "With send: send stdin as a file (default name stdin.bin); automatic for non-UTF-8 input."),
+    ("list.identities", "Identities:"),
+    ("list.friends", "Friends:"),
+    ("whoami.name", "Name:"),
+    ("whoami.public_id", "Public id:"),
+    ("whoami.fingerprint", "Fingerprint:"),
+    ("whoami.created", "Created:"),
+    ("whoami.unknown", "unknown"),
+    ("invite.note", "Note:"),
+    ("invite.signed_by", "Signed by signing key (compare with their gqg signkey):"),
+    ("invite.unsigned", "This invitation is not signed."),
+    ("invite.confirm_signer", "Did the sender confirm this signing key id outside the invitation (in person, by phone)?"),
+    ("invite.confirm_unsigned", "Add this friend anyway? Anyone could have written or changed this invitation."),
+    ("invite.signer_mismatch", "The invitation is not signed by the key id given with --expect."),
+    ("invite.declined", "Invitation not accepted."),
+    ("invite.reply_with", "To send your id back, run: gqg invite --reply"),
+    ("invite.answered", "This answers your invitation."),
+    ("invite.unknown_token", "This reply does not match a pending invitation of yours."),
+    ("list.verified", "(verified)"),
+    ("pair.send_offer", "Send this to your contact:"),
+    ("pair.send_answer", "Send this back to your contact:"),
+    ("pair.send_reveal", "Send this to your contact as well:"),
+    ("pair.paste_answer", "Paste your contact's answer:"),
+    ("pair.paste_reveal", "Paste your contact's final payload:"),
+    ("pair.compare", "Compare this code with your contact over a trusted channel:"),
+    ("pair.matches", "Does your contact see the same code?"),
+    ("pair.mismatch", "Codes do not match. Nothing was changed."),
+    ("pair.bad_commitment", "Pairing payload does not match the offer. Someone may be interfering."),
+    ("pair.done", "Paired and verified:"),
+    ("stats.identities", "Identities:"),
+    ("stats.friends", "Friends:"),
+    ("stats.stored", "Stored data:"),
+    ("stats.oldest", "Oldest activity:"),
+    ("stats.newest", "Newest activity:"),
+    ("stats.per_friend", "Per friend (sent / received):"),
+    ("stats.per_month", "Per month (sent / received):"),
+    ("inspect.size", "Size:"),
+    ("inspect.lines", "Lines:"),
+    ("inspect.hint", "Recipient hint:"),
+    ("inspect.hint_none", "none (trial decryption needed)"),
+    ("inspect.hint_mine", "addressed to"),
+    ("inspect.hint_other", "not addressed to any of your identities"),
+    ("inspect.encoding", "Encoding:"),
+    ("inspect.content", "Content:"),
+    ("inspect.content_hidden", "encrypted; message or file is only known after decryption"),
+    ("inspect.truncated", "Payload looks truncated or damaged."),
+    ("receive.sealed", "Sealed by your identity"),
+    ("receive.addressed_to", "Addressed to:"),
+    ("receive.checksum", "SHA-256:"),
+    ("verify.ok", "Checksum matches:"),
+    ("verify.mismatch", "Checksum does NOT match the received content."),
+    ("verify.missing", "Stored content is missing."),
+    ("verify.unrecorded", "No checksum was recorded for this entry."),
+    ("receive.quarantined", "Quarantined. After checking it, release it with: gqg release"),
+    ("release.not_quarantined", "This entry is not quarantined."),
+    ("dirs.quarantine", "Quarantine:"),
+    ("note.not_sealed", "This note was not sealed by the active identity."),
+    ("vault.not_found", "No such secret."),
+    ("vault.copied", "Copied to the clipboard. It will be cleared in"),
+    ("sent.archived", "(archived)"),
+    ("sent.not_archived", "No archived copy of this entry; see send.keep_sent."),
+    ("mail.no_password", "No mail password stored. Store it with:"),
+    ("mqtt.no_password", "No MQTT password stored. Store it with:"),
+    ("mqtt.subscribed", "Subscribed to"),
+    ("error.unknown_transport", "Unknown transport. Supported:"),
+    ("nostr.accepted", "Relays that accepted the event:"),
+    ("open.missing", "The link has no parameter"),
+    ("open.unknown", "Unknown gqg: link."),
+    ("open.confirm_befriend", "Add this friend?"),
+    ("open.registered", "gqg: links now open with gqg."),
+    ("discover.conflict", "HTTPS and DNS publish different ids for this address. Not adding it."),
+    ("discover.sigkey_conflict", "HTTPS and DNS list different signing keys for this address. Not adding it."),
+    ("discover.both", "Found over HTTPS; DNS agrees."),
+    ("discover.https", "Found over HTTPS."),
+    ("discover.dns_only", "Found only in DNS, which is not authenticated. Check the fingerprint with its owner."),
+    ("discover.not_found", "No gqg id published for this address."),
+    ("discover.signed_pinned", "Signed by the key pinned for this address; published at"),
+    ("discover.signed_dns", "Signed by the key DNS also lists; published at"),
+    ("discover.signed_new", "Signed by a key not seen before for this address (pinned from now on); published at"),
+    ("discover.sigkey_changed", "The record is signed by a different key than the one pinned for this address. Not adding it."),
+    ("discover.unsigned_pinned", "This address had a signed record before, but this one is not signed. Not adding it."),
+    ("publish.file", "Serve this over HTTPS as"),
+    ("publish.dns", "And/or add this DNS record:"),
+    ("provision.verified_ignored", "rows are marked verified; pass --trust-verified to mark those friends as verified."),
+    ("provision.confirm_verified", "rows are marked verified. Mark those friends as verified? Only do this if you checked their ids with them yourself."),
+    ("provision.missing_id", "Every row needs a public id; gqg cannot create identities for other people. Ask them to run gqg newid and send their id. Row without an id:"),
+    ("serve.token", "New API token (send as Authorization: Bearer <token>):"),
+    ("serve.listening", "Listening on"),
+    ("selftest.pass", "pass"),
+    ("selftest.fail", "FAIL"),
+    ("selftest.skipped", "skipped (no separate profile on Windows)"),
+    ("selftest.skipped_read_only", "skipped (read-only mode)"),
+    ("selftest.not_isolated", "temporary profile was not picked up; refusing to write to the real config"),
+    ("selftest.failed", "self-test stage(s) failed."),
+    ("debug.armor_stripped", "mail armor removed"),
+    ("debug.armor_truncated", "begin marker without end marker; payload is truncated"),
+    ("debug.bad_char", "unexpected character"),
+    ("debug.no_hint", "no recipient hint; trying every identity"),
+    ("debug.hint_unmatched", "matches none of your identities; sent to someone else"),
+    ("debug.unknown_sender", "unknown sender"),
+    ("stdin.waiting", "Waiting for input on stdin (Ctrl-D to finish, Ctrl-C to abort)."),
+    ("stdin.timeout", "Timed out waiting for input on stdin after"),
+    ("stdin.paste", "Paste the payload. Armored payloads end at their END line; otherwise press Ctrl-D on a new line (Ctrl-Z then Enter on Windows). Ctrl-C aborts."),
+    ("receive.armor_truncated", "Payload is truncated: no end marker."),
+    ("receive.blocks_failed", "embedded payloads could not be received."),
+    ("stdin.empty", "No input received on stdin."),
+    ("archive.archived_in", "archived in"),
+    ("archive.missing", "Skipping missing"),
+    ("archive.nothing", "Nothing to archive."),
+    ("archive.done", "items archived to"),
+    ("archive.not_archived", "Entry is not archived."),
+    ("archive.restored", "items restored."),
+    ("prune.pruned", "pruned"),
+    ("prune.would_remove", "items would be removed."),
+    ("prune.removed", "items removed."),
+    ("open.no_message", "No stored message to open."),
+    ("open.press_enter", "Press Enter when done to delete the temporary copy."),
+    ("search.update_failed", "Search index not updated:"),
+    ("search.skipped", "Not indexed:"),
+    ("search.indexed", "items indexed."),
+    ("search.no_index", "No search index yet. Run gqg search --rebuild."),
+    ("thread.me", "me"),
+    ("thread.unverified", "unverified"),
+    ("thread.file", "File:"),
+    ("thread.unavailable", "(content not available)"),
+    ("receive.previous_key", "previous key"),
+    ("receive.previous_sender", "SIGNED WITH A PREVIOUS KEY OF:"),
+    ("rekey.retired", "Previous key kept:"),
+    ("audit.shared_mode", "accessible to other users, mode"),
+    ("audit.created", "identity created"),
+    ("audit.exchanges", "exchanges without verification"),
+    ("audit.items", "received items"),
+    ("audit.plaintext", "stored in plaintext; see receive.at_rest and gqg prune"),
+    ("audit.policy_allow", "messages from unknown senders are accepted silently"),
+    ("audit.quarantine_off", "content from unknown senders is not quarantined"),
+    ("audit.clean", "No findings."),
+    ("audit.head", "Head (keep a copy elsewhere to detect a rewritten log):"),
+    ("dedup.reused", "chunks already delivered."),
+    ("dedup.confirmed", "chunks marked as delivered."),
+    ("dedup.missing", "chunks of this file were never received. Ask the sender to run gqg dedup forget and send it again."),
+    ("sync.up_to_date", "Nothing changed since the last sync."),
+    ("sync.sent", "changes sent. Run gqg sync --confirm once they arrive."),
+    ("sync.confirmed", "Last sync confirmed; the next sync sends only newer changes."),
+    ("sync.nothing_pending", "No sent sync is waiting for confirmation."),
+    ("sync.unbound", "Received a sync for a folder that is not bound"),
+    ("sync.quarantined", "Sender is not verified, so the synced files were put in quarantine instead of the folder."),
+    ("channel.bad_name", "Channel names may only contain letters, digits, - and _."),
+    ("channel.exists", "A channel with this name already exists."),
+    ("channel.not_found", "No such channel."),
+    ("channel.not_owner", "Only the identity that created a channel can post to it."),
+    ("channel.no_posts", "No channel posts in input."),
+    ("channel.unknown", "Post is for a channel you do not follow."),
+    ("channel.owner", "owner"),
+    ("channel.follower", "follower"),
+    ("dropbox.storing", "Storing submissions in"),
+    ("dropbox.kept", "submissions could not be received and were kept."),
+    ("delta.usage", "--delta needs --base <previous file> and cannot be combined with --dedup."),
+    ("delta.size", "bytes in delta."),
+    ("delta.base", "Applying delta to"),
+    ("delta.no_base", "The base file of this delta was never received or is gone; ask for the full file."),
+    ("queue.attempt_failed", "Delivery failed for queue entry"),
+    ("queue.queued", "Not delivered yet; gqg queue status shows the pending retry of entry"),
+    ("queue.empty", "No pending deliveries."),
+    ("queue.pending", "pending"),
+    ("queue.failed", "failed"),
+    ("queue.attempts", "attempts, next"),
+    ("queue.still_pending", "deliveries are still pending."),
+    ("queue.not_found", "No such queue entry."),
+    ("error.read_only", "The database is read-only (--read-only or an unwritable config file) and this command would change it."),
+    ("hooks.read_only", "hooks.on_send needs a temporary plaintext file, which read-only mode does not allow."),
+    ("receive.read_only_quarantine", "Sender would be quarantined, which a read-only database cannot do; use --insecure to print it anyway."),
+    ("portable.windows", "Portable mode is not supported on Windows."),
+    ("integrity.mismatch", "The database or settings were changed outside gqg, or the integrity MAC was removed, since gqg last ran. Check gqg list for swapped keys; run gqg integrity accept only if the change was yours."),
+    ("integrity.no_key", "The integrity key is missing (GQG_INTEGRITY_KEY or integrity.key next to the database); the database cannot be verified."),
+    ("integrity.seal_failed", "Unable to update the integrity MAC:"),
+    ("integrity.off", "Integrity MAC is off (integrity.mac)."),
+    ("integrity.unsealed", "No integrity MAC yet; it is written after the next command."),
+    ("integrity.ok", "Database and settings match their integrity MAC."),
+    ("integrity.same_medium", "The integrity key is next to the database, so whoever can change the database can also delete the key and MAC. Set GQG_INTEGRITY_KEY to a key file on other media, such as a removable drive."),
+    ("integrity.external_key", "The integrity key comes from GQG_INTEGRITY_KEY; remove that file and unset the variable to turn the MAC off."),
+    ("integrity.disabled", "Integrity key and MAC removed; integrity.mac is now off."),
+    ("integrity.accepted", "Current database accepted."),
+    ("chaff.no_friends", "No friends to send chaff to (chaff.friends)."),
+    ("chaff.failed", "Chaff not delivered:"),
+    ("notify.webhook_failed", "Webhook notification failed:"),
+    ("config.windows", "--config and GQG_CONFIG are not supported on this platform."),
+    ("config.layout", "--config must be a path ending in"),
+    ("config.not_arbitrary", "gqg picks the database file name itself, so only the directory above it can be changed."),
+    ("undo.nothing", "Nothing to undo within undo.window."),
+    ("undo.conflict", "Cannot undo, a friend with this name exists again:"),
+    ("undo.done", "Undone:"),
+    ("trash.not_found", "No contact with this name in the trash."),
+    ("trash.restored", "Restored:"),
+    ("signfile.good", "Signature verified."),
+    ("signfile.bad", "The signature does not match the file."),
+    ("signfile.bad_comment", "The trusted comment of the signature was altered."),
+    ("signfile.other_key", "The file was signed with a different key."),
+    ("signfile.no_signature", "No .minisig or .sig file next to the file; use --sig."),
+    ("send.binary", "Input is not UTF-8 text; sending it as the file stdin.bin."),
+    ("open.dangerous", "Not opening a file with a dangerous content type:"),
+    ("roster.bad_signer", "The roster was not signed by the given friend."),
+    ("roster.added", "added"),
+    ("roster.unchanged", "unchanged"),
+    ("roster.conflict", "conflict"),
+    ("dirs.config", "Config file:"),
+    ("dirs.files", "File directory:"),
+    ("dirs.messages", "Message directory:"),
+    ("error", "Error:"),
+    ("error.friend_not_found", "Friend not found."),
+    ("error.name_in_use", "Name already in use."),
+    ("error.identity_not_found", "Identity not found."),
+    ("error.too_large", "Input exceeds the limit of"),
+    ("error.not_utf8", "Input is not valid UTF-8 text."),
+    ("error.invalid_path", "Invalid path."),
+    ("error.file_exists", "File already exists. Aborting."),
+    ("receive.verified", "VERIFIED:"),
+    ("receive.unknown_sender", "BEWARE. Unknown sender: This message is NOT sent by your friends."),
+    ("receive.refused", "Unknown sender: refusing to write output. Use --insecure to override."),
+    ("receive.not_addressed", "This message is not addressed to any of your identities."),
+    ("receive.hook_failed", "Receive hook failed:"),
+    ("receive.dangerous", "DANGER. This file may run code when opened:"),
+    ("receive.dangerous_refused", "Refusing a potentially dangerous file from a sender not verified by pair. Use --insecure to override."),
+    ("receive.failed", "Failed to decrypt."),
+];
+
+static CATALOGS: &[(&str, Catalog)] = &[
+    ("en", EN),
+];
+
+static LANG: OnceLock<Catalog> = OnceLock::new();
+
+fn language_code(locale: &str) -> String {
+    locale.split(|c| c == '_' || c == '.' || c == '-' || c == '@')
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+pub fn init(lang: Option<String>) {
+    let locale = lang
+        .or_else(|| std::env::var("LC_ALL").ok().filter(|v| !v.is_empty()))
+        .or_else(|| std::env::var("LANG").ok())
+        .unwrap_or_default();
+    let code = language_code(&locale);
+    let catalog = CATALOGS.iter()
+        .find(|(name, _)| *name == code)
+        .map(|(_, catalog)| *catalog)
+        .unwrap_or(EN);
+    let _ = LANG.set(catalog);
+}
+
+pub fn tr(key: &'static str) -> &'static str {
+    let catalog = LANG.get().copied().unwrap_or(EN);
+    catalog.iter()
+        .chain(EN.iter())
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+        .unwrap_or(key)
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use gqg_lib::database::Database;
+use sodiumoxide::crypto::auth;
+use crate::settings::Settings;
+
+pub fn key_path_buf() -> PathBuf {
+    match std::env::var_os("GQG_INTEGRITY_KEY").filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => crate::settings::state_path_buf("integrity.key"),
+    }
+}
+
+pub fn is_key_external() -> bool {
+    key_path_buf() != crate::settings::state_path_buf("integrity.key")
+}
+
+fn mac_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("integrity.mac")
+}
+
+fn read_or_empty(path: &std::path::Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(contents) => Ok(contents),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read {}.", path.display())),
+    }
+}
+
+fn protected_bytes() -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for path in [PathBuf::from(Database::config_path()), Settings::path_buf()] {
+        let contents = read_or_empty(&path)?;
+        bytes.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&contents);
+    }
+    Ok(bytes)
+}
+
+fn load_key(create: bool) -> Result<Option<auth::Key>> {
+    let path = key_path_buf();
+    match std::fs::read(&path) {
+        Ok(bytes) => Ok(Some(auth::Key::from_slice(&bytes).ok_or(anyhow!("Integrity key is corrupted."))?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && create => {
+            let key = auth::gen_key();
+            if let Some(dir) = path.parent() {
+                crate::fsutil::create_dir_private(dir)?;
+            }
+            crate::fsutil::write_private_file(&path, &key.0)?;
+            Ok(Some(key))
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(_) => Err(anyhow!("Unable to read {}.", path.display())),
+    }
+}
+
+pub fn key() -> Result<Option<auth::Key>> {
+    load_key(false)
+}
+
+pub fn is_sealed() -> bool {
+    mac_path_buf().exists()
+}
+
+pub fn check() -> Result<()> {
+    let mac = match std::fs::read_to_string(mac_path_buf()) {
+        Ok(mac) => Some(mac),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(_) => return Err(anyhow!("Unable to read the integrity MAC.")),
+    };
+    let (key, mac) = match (load_key(false)?, mac) {
+        (None, None) => return Ok(()),
+        (None, Some(_)) => return Err(anyhow!(crate::i18n::tr("integrity.no_key"))),
+        (Some(_), None) => return Err(anyhow!(crate::i18n::tr("integrity.mismatch"))),
+        (Some(key), Some(mac)) => (key, mac),
+    };
+    let tag = sodiumoxide::hex::decode(mac.trim()).ok()
+        .and_then(|bytes| auth::Tag::from_slice(&bytes))
+        .ok_or(anyhow!("Integrity MAC is corrupted."))?;
+    if !auth::verify(&tag, &protected_bytes()?, &key) {
+        return Err(anyhow!(crate::i18n::tr("integrity.mismatch")));
+    }
+    Ok(())
+}
+
+pub fn seal(settings: &Settings) -> Result<()> {
+    let key = match load_key(settings.get("integrity.mac") == "on")? {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+    let tag = auth::authenticate(&protected_bytes()?, &key);
+    crate::fsutil::replace_private_file(&mac_path_buf(), sodiumoxide::hex::encode(tag.0).as_bytes())
+}
+
+pub fn disable() -> Result<()> {
+    if is_key_external() {
+        return Err(anyhow!(crate::i18n::tr("integrity.external_key")));
+    }
+    for path in [mac_path_buf(), key_path_buf()] {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(_) => return Err(anyhow!("Unable to remove {}.", path.display())),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::Result;
+
+pub const EXIT_INTERACTION: i32 = 3;
+
+static BATCH: AtomicBool = AtomicBool::new(false);
+static STDIN_TIMEOUT: OnceLock<Option<u64>> = OnceLock::new();
+static COLORS: OnceLock<bool> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct InteractionRequired(pub String);
+
+impl std::fmt::Display for InteractionRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Interaction required in batch mode: {}", self.0)
+    }
+}
+
+impl std::error::Error for InteractionRequired {}
+
+#[cfg(windows)]
+fn enable_colors() -> bool {
+    ansi_term::enable_ansi_support().is_ok()
+}
+
+#[cfg(not(windows))]
+fn enable_colors() -> bool {
+    true
+}
+
+pub fn colors() -> bool {
+    *COLORS.get_or_init(enable_colors)
+}
+
+pub fn set_batch(batch: bool) {
+    BATCH.store(batch, Ordering::Relaxed);
+}
+
+pub fn is_batch() -> bool {
+    BATCH.load(Ordering::Relaxed)
+}
+
+pub fn set_stdin_timeout(seconds: Option<u64>) {
+    let _ = STDIN_TIMEOUT.set(seconds);
+}
+
+pub fn stdin_timeout() -> Option<u64> {
+    STDIN_TIMEOUT.get().copied().flatten()
+}
+
+pub fn require_piped_stdin() -> Result<()> {
+    if is_batch() && std::io::stdin().is_terminal() {
+        return Err(InteractionRequired("no input piped to stdin.".to_string()).into());
+    }
+    Ok(())
+}
+
+pub fn prompt_line(prompt: &str) -> Result<String> {
+    if is_batch() {
+        return Err(InteractionRequired(prompt.to_string()).into());
+    }
+    eprint!("{} ", prompt);
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+pub fn confirm(question: &str) -> Result<bool> {
+    let answer = prompt_line(&format!("{} [y/N]", question))?;
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+pub fn edit(contents: &[u8]) -> Result<Vec<u8>> {
+    if is_batch() {
+        return Err(InteractionRequired("an editor is needed.".to_string()).into());
+    }
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(target_os = "windows") { "notepad".to_string() } else { "vi".to_string() });
+    let path = std::env::temp_dir().join(format!("gqg-edit-{}",
+        sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8))));
+    crate::fsutil::create_private(&path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|_| anyhow!("Unable to write text for editor."))?;
+    let mut words = editor.split_whitespace();
+    let status = crate::profile::command(words.next().unwrap_or("vi"))
+        .args(words)
+        .arg(&path)
+        .status();
+    let edited = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+    let status = status.map_err(|err| anyhow!("Unable to run editor {}: {}", editor, err))?;
+    if !status.success() {
+        return Err(anyhow!("Editor exited with {}.", status));
+    }
+    edited.map_err(|_| anyhow!("Unable to read text after editing."))
+}
+
+pub fn sanitize_terminal(text: &str) -> String {
+    text.chars()
+        .filter(|&c| c != '\r')
+        .map(|c| if c.is_control() && c != '\n' && c != '\t' { '\u{fffd}' } else { c })
+        .collect()
+}
+
+pub fn page(text: &str) -> Result<()> {
+    let text = sanitize_terminal(text);
+    if !std::io::stdout().is_terminal() {
+        std::io::stdout().write_all(text.as_bytes())?;
+        return Ok(());
+    }
+    let pager = std::env::var("PAGER").unwrap_or_default();
+    let mut words = pager.split_whitespace();
+    let mut command = crate::profile::command(words.next().unwrap_or("less"));
+    command.args(words).stdin(std::process::Stdio::piped());
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => {
+            std::io::stdout().write_all(text.as_bytes())?;
+            return Ok(());
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait().map_err(|_| anyhow!("Unable to run the pager."))?;
+    Ok(())
+}
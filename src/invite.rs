@@ -0,0 +1,210 @@
+use anyhow::Result;
+use sodiumoxide::base64::Variant;
+use sodiumoxide::crypto::hash::sha256;
+use sodiumoxide::crypto::sign;
+
+const PREFIX: &str = "gqg-invite:";
+
+pub struct Invite {
+    pub name: String,
+    pub public_id: String,
+    pub note: String,
+    pub token: Option<String>,
+    pub reply: Option<String>,
+    pub signer: Option<sign::PublicKey>,
+}
+
+fn checksum(body: &str) -> String {
+    sodiumoxide::hex::encode(&sha256::hash(body.as_bytes()).0[..8])
+}
+
+fn clean(value: &str) -> String {
+    value.replace(|c: char| c == '\n' || c == '\r', " ")
+}
+
+impl Invite {
+    pub fn encode(&self, secret_key: &sign::SecretKey) -> Result<String> {
+        let public_key = crate::signing::public_key(secret_key).ok_or(anyhow!("Signing key is corrupted."))?;
+        let mut body = format!("name={}\nid={}\nnote={}\n", clean(&self.name), self.public_id, clean(&self.note));
+        if let Some(token) = &self.token {
+            body.push_str(&format!("token={}\n", token));
+        }
+        if let Some(reply) = &self.reply {
+            body.push_str(&format!("reply={}\n", reply));
+        }
+        body.push_str(&format!("sigkey={}\n", crate::signing::encode_public(&public_key)));
+        body.push_str(&format!("sig={}\n", crate::signing::sign(body.as_bytes(), secret_key)));
+        body.push_str(&format!("check={}\n", checksum(&body)));
+        Ok(format!("{}{}", PREFIX, sodiumoxide::base64::encode(body, Variant::UrlSafeNoPadding)))
+    }
+
+    pub fn decode(blob: &str) -> Result<Invite> {
+        let encoded = blob.trim().strip_prefix(PREFIX).ok_or(anyhow!("Not an invitation."))?;
+        let raw = sodiumoxide::base64::decode(encoded, Variant::UrlSafeNoPadding)
+            .map_err(|_| anyhow!("Invitation is damaged."))?;
+        let body = String::from_utf8(raw).map_err(|_| anyhow!("Invitation is damaged."))?;
+        let (content, check) = body.trim_end().rsplit_once("check=")
+            .ok_or(anyhow!("Invitation is damaged."))?;
+        if checksum(content) != check {
+            return Err(anyhow!("Invitation is damaged."));
+        }
+        let mut invite = Invite {
+            name: String::new(),
+            public_id: String::new(),
+            note: String::new(),
+            token: None,
+            reply: None,
+            signer: None,
+        };
+        let mut signer = None;
+        for line in content.lines() {
+            match line.split_once('=') {
+                Some(("name", value)) => invite.name = value.to_string(),
+                Some(("id", value)) => invite.public_id = value.to_string(),
+                Some(("note", value)) => invite.note = value.to_string(),
+                Some(("token", value)) => invite.token = Some(value.to_string()),
+                Some(("reply", value)) => invite.reply = Some(value.to_string()),
+                Some(("sigkey", value)) => signer = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        if let Some(signer) = signer {
+            let public_key = crate::signing::decode_public(&signer).ok_or(anyhow!("Invitation signature is invalid."))?;
+            let (signed, signature) = content.rsplit_once("\nsig=").ok_or(anyhow!("Invitation signature is invalid."))?;
+            if !crate::signing::verify(format!("{}\n", signed).as_bytes(), signature, &public_key) {
+                return Err(anyhow!("Invitation signature is invalid."));
+            }
+            invite.signer = Some(public_key);
+        }
+        if invite.name.is_empty() || invite.public_id.is_empty() {
+            return Err(anyhow!("Invitation is incomplete."));
+        }
+        Ok(invite)
+    }
+}
+
+fn tokens_path_buf() -> std::path::PathBuf {
+    crate::settings::state_path_buf("invites")
+}
+
+fn read_tokens() -> Result<Vec<String>> {
+    match std::fs::read_to_string(tokens_path_buf()) {
+        Ok(contents) => Ok(contents.lines().map(|l| l.to_string()).collect()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read pending invitations.")),
+    }
+}
+
+fn write_tokens(tokens: &[String]) -> Result<()> {
+    let mut contents = tokens.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    crate::fsutil::replace_private_file(&tokens_path_buf(), contents.as_bytes())
+        .map_err(|_| anyhow!("Unable to write pending invitations."))
+}
+
+pub fn new_token() -> Result<String> {
+    let token = sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(16));
+    let mut tokens = read_tokens()?;
+    tokens.push(token.clone());
+    write_tokens(&tokens)?;
+    Ok(token)
+}
+
+pub fn consume_token(token: &str) -> Result<bool> {
+    let mut tokens = read_tokens()?;
+    let before = tokens.len();
+    tokens.retain(|pending| pending != token);
+    if tokens.len() == before {
+        return Ok(false);
+    }
+    write_tokens(&tokens)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite() -> Invite {
+        Invite {
+            name: "alice".to_string(),
+            public_id: "gqg:abc".to_string(),
+            note: "hi\nfrom the conference".to_string(),
+            token: Some("00ff".to_string()),
+            reply: None,
+            signer: None,
+        }
+    }
+
+    fn secret_key() -> sign::SecretKey {
+        sodiumoxide::init().unwrap();
+        sign::keypair_from_seed(&sign::Seed([9; sign::SEEDBYTES])).1
+    }
+
+    fn reencode(body: &str) -> String {
+        let content = body.rsplit_once("check=").unwrap().0;
+        let body = format!("{}check={}\n", content, checksum(content));
+        format!("{}{}", PREFIX, sodiumoxide::base64::encode(body, Variant::UrlSafeNoPadding))
+    }
+
+    fn body_of(blob: &str) -> String {
+        let raw = sodiumoxide::base64::decode(blob.strip_prefix(PREFIX).unwrap(), Variant::UrlSafeNoPadding).unwrap();
+        String::from_utf8(raw).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let secret_key = secret_key();
+        let decoded = Invite::decode(&invite().encode(&secret_key).unwrap()).unwrap();
+        assert_eq!(decoded.name, "alice");
+        assert_eq!(decoded.public_id, "gqg:abc");
+        assert_eq!(decoded.note, "hi from the conference");
+        assert_eq!(decoded.token.as_deref(), Some("00ff"));
+        assert_eq!(decoded.reply, None);
+        assert_eq!(decoded.signer, crate::signing::public_key(&secret_key));
+    }
+
+    #[test]
+    fn rejects_damage() {
+        let blob = invite().encode(&secret_key()).unwrap();
+        let mut damaged = blob.clone().into_bytes();
+        let middle = damaged.len() / 2;
+        damaged[middle] = if damaged[middle] == b'A' { b'B' } else { b'A' };
+        assert!(Invite::decode(&String::from_utf8(damaged).unwrap()).is_err());
+        assert!(Invite::decode(&blob[PREFIX.len()..]).is_err());
+        assert!(Invite::decode("gqg-invite:!!!").is_err());
+        assert!(Invite::decode(&reencode("name=\nid=gqg:abc\ncheck=")).is_err());
+    }
+
+    #[test]
+    fn rejects_forged_signature() {
+        let body = body_of(&invite().encode(&secret_key()).unwrap());
+        assert!(Invite::decode(&reencode(&body.replacen("id=gqg:abc", "id=gqg:evil", 1))).is_err());
+        assert!(Invite::decode(&reencode(&body.replacen("\nsig=", "\nsig=AAAA", 1))).is_err());
+        let appended = body.replacen("\ncheck=", "\nname=mallory\ncheck=", 1);
+        assert!(Invite::decode(&reencode(&appended)).is_err());
+    }
+
+    #[test]
+    fn resigned_invites_carry_the_new_signer() {
+        let attacker = sign::keypair_from_seed(&sign::Seed([7; sign::SEEDBYTES])).1;
+        let original = body_of(&invite().encode(&secret_key()).unwrap());
+        let forged = body_of(&Invite { public_id: "gqg:evil".to_string(), ..invite() }.encode(&attacker).unwrap());
+        let (_, forged_signature) = forged.split_once("\nsigkey=").unwrap();
+        let (kept, _) = original.split_once("\nsigkey=").unwrap();
+        let swapped = format!("{}\nsigkey={}", kept.replacen("id=gqg:abc", "id=gqg:evil", 1), forged_signature);
+        let decoded = Invite::decode(&reencode(&swapped)).unwrap();
+        assert_eq!(decoded.public_id, "gqg:evil");
+        assert_eq!(decoded.signer, crate::signing::public_key(&attacker));
+        assert_ne!(decoded.signer, crate::signing::public_key(&secret_key()));
+    }
+
+    #[test]
+    fn accepts_unsigned_invites() {
+        let decoded = Invite::decode(&reencode("name=bob\nid=gqg:def\nnote=\ncheck=")).unwrap();
+        assert_eq!(decoded.name, "bob");
+        assert!(decoded.signer.is_none());
+    }
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const MAX_ENTRIES: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Friend {
+    pub name: String,
+    pub public_id: String,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Entry {
+    pub time: i64,
+    pub action: String,
+    pub subject: String,
+    pub removed: Vec<Friend>,
+    pub added: Vec<String>,
+    pub trust: bool,
+    pub verified: String,
+}
+
+fn path_buf() -> PathBuf {
+    crate::settings::state_path_buf("undo-journal.json")
+}
+
+pub fn load() -> Result<Vec<Entry>> {
+    match std::fs::read(path_buf()) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|_| anyhow!("Undo journal is corrupted.")),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read the undo journal.")),
+    }
+}
+
+pub fn store(entries: &[Entry]) -> Result<()> {
+    crate::fsutil::replace_private_file(&path_buf(), &serde_json::to_vec_pretty(entries)?)
+}
+
+pub fn record(entry: Entry) -> Result<()> {
+    let mut entries = load()?;
+    entries.push(Entry { time: chrono::Utc::now().timestamp(), ..entry });
+    if entries.len() > MAX_ENTRIES {
+        entries.drain(..entries.len() - MAX_ENTRIES);
+    }
+    store(&entries)
+}
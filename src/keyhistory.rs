@@ -0,0 +1,18 @@
+const PREFIX: &str = "~previous~";
+
+pub fn is_retired(name: &str) -> bool {
+    name.starts_with(PREFIX)
+}
+
+pub fn owner(name: &str) -> Option<String> {
+    let rest = name.strip_prefix(PREFIX)?;
+    let (_, owner) = rest.split_once('~')?;
+    Some(owner.to_string())
+}
+
+pub fn retired_name<'a, I: IntoIterator<Item = &'a str>>(owner: &str, existing: I) -> String {
+    let count = existing.into_iter()
+        .filter(|name| self::owner(name).as_deref() == Some(owner))
+        .count();
+    format!("{}{}~{}", PREFIX, count + 1, owner)
+}
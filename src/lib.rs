@@ -0,0 +1,4039 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use ansi_term::Color::{Red, Green};
+use anyhow::Result;
+use gqg_lib::database::Database;
+use gqg_lib;
+use sodiumoxide::crypto::hash::sha256;
+use error::Error;
+use settings::Settings;
+
+#[macro_use]
+extern crate anyhow;
+
+pub mod aliases;
+pub mod api;
+pub mod archive;
+pub mod armor;
+pub mod audit;
+pub mod chaff;
+pub mod channel;
+pub mod clipboard;
+pub mod danger;
+pub mod dedup;
+pub mod delta;
+pub mod delivery;
+pub mod discovery;
+pub mod dropbox;
+pub mod error;
+pub mod envelope;
+pub mod frame;
+pub mod fsutil;
+pub mod history;
+pub mod hooks;
+pub mod i18n;
+pub mod integrity;
+pub mod interact;
+pub mod invite;
+pub mod journal;
+pub mod keyhistory;
+pub mod mail;
+pub mod maildir;
+pub mod mime;
+pub mod minisign;
+pub mod mqtt;
+pub mod names;
+pub mod nostr;
+pub mod notes;
+pub mod notify;
+pub mod padding;
+pub mod pair;
+pub mod profile;
+pub mod roster;
+pub mod search;
+pub mod settings;
+pub mod signing;
+pub mod sync;
+pub mod syslog;
+pub mod transcript;
+pub mod trash;
+pub mod trust;
+pub mod uri;
+pub mod vault;
+pub mod volumes;
+#[cfg(test)]
+mod testutil;
+
+static GREY: ansi_term::Color = ansi_term::Color::Fixed(240);
+
+macro_rules! red {
+    ($a:expr) => {
+        if !interact::colors() {
+            $a
+        }
+        else {
+            Red.paint($a).deref().to_string()
+        }
+    }
+}
+
+macro_rules! green {
+    ($a:expr) => {
+        if !interact::colors() {
+            $a
+        }
+        else {
+            Green.paint($a).deref().to_string()
+        }
+    }
+}
+
+macro_rules! grey {
+    ($a:expr) => {
+        if !interact::colors() {
+            $a
+        }
+        else {
+            GREY.paint($a).deref().to_string()
+        }
+    }
+}
+
+fn logo() {
+    eprintln!("
+   __ _  __ _  __ _
+  / _` |/ _` |/ _` |
+ | (_| | (_| | (_| |
+  \\__, |\\__, |\\__, |
+   __/ |   | | __/ |
+  |___/    |_||___/ v{}
+", env!("CARGO_PKG_VERSION"));
+}
+
+static COMMANDS: &[(&str, &str)] = &[
+    ("gqg list", "help.list"),
+    ("gqg newid <local-name>", "help.newid"),
+    ("gqg befriend <friend-name> <id-string>", "help.befriend"),
+    ("gqg unfriend <friend-name>", "help.unfriend"),
+    ("gqg rekey <friend-name> <id-string>", "help.rekey"),
+    ("gqg receive", "help.receive"),
+    ("gqg send <friend-name>", "help.send"),
+    ("gqg sendfile <friend-name> <file-name>", "help.sendfile"),
+    ("gqg dedup forget <friend-name>", "help.dedup_forget"),
+    ("gqg dedup confirm <friend-name>", "help.dedup_confirm"),
+    ("gqg sync <friend-name> <dir> [--full | --confirm]", "help.sync"),
+    ("gqg channel create <name>", "help.channel_create"),
+    ("gqg channel share <name>", "help.channel_share"),
+    ("gqg channel follow <key> [<name>]", "help.channel_follow"),
+    ("gqg channel post <name>", "help.channel_post"),
+    ("gqg channel read", "help.channel_read"),
+    ("gqg channel list", "help.channel_list"),
+    ("gqg channel rm <name>", "help.channel_rm"),
+    ("gqg receive --volumes <file.001>", "help.receive_volumes"),
+    ("gqg active <local-name>", "help.active"),
+    ("gqg dirs", "help.dirs"),
+    ("gqg audit-log", "help.audit-log"),
+    ("gqg audit [--json]", "help.audit"),
+    ("gqg config [<key> [<value>]]", "help.config"),
+    ("gqg bench [--json]", "help.bench"),
+    ("gqg whoami [--json]", "help.whoami"),
+    ("gqg invite [--note <text>] [--token]", "help.invite"),
+    ("gqg accept <blob> [<friend-name>] [--expect <key-id>]", "help.accept"),
+    ("gqg pair [<payload>]", "help.pair"),
+    ("gqg alias <friend-name> <alias>", "help.alias"),
+    ("gqg unalias <alias>", "help.unalias"),
+    ("gqg stats [--json]", "help.stats"),
+    ("gqg id [<local-name>] [-o <file>]", "help.id"),
+    ("gqg inspect [<file>]", "help.inspect"),
+    ("gqg inbox [--tag <t>]... [--flagged]", "help.inbox"),
+    ("gqg tag-msg <n> <tag> [--remove]", "help.tag_msg"),
+    ("gqg flag <n>", "help.flag"),
+    ("gqg unflag <n>", "help.unflag"),
+    ("gqg verify-stored <n>", "help.verify-stored"),
+    ("gqg release <n>", "help.release"),
+    ("gqg archive --older-than <age>", "help.archive"),
+    ("gqg unarchive <n>", "help.unarchive"),
+    ("gqg prune [--dry-run]", "help.prune"),
+    ("gqg seal [<file-name>]", "help.seal"),
+    ("gqg note add <title> [--tag <tag>]...", "help.note_add"),
+    ("gqg note list [--tag <tag>]", "help.note_list"),
+    ("gqg note show <n>", "help.note_show"),
+    ("gqg note edit <n> [--title <title>]", "help.note_edit"),
+    ("gqg note rm <n>", "help.note_rm"),
+    ("gqg vault set <key>", "help.vault_set"),
+    ("gqg vault get <key> [--clip]", "help.vault_get"),
+    ("gqg vault list", "help.vault_list"),
+    ("gqg vault rm <key>", "help.vault_rm"),
+    ("gqg sent list", "help.sent_list"),
+    ("gqg sent show <n>", "help.sent_show"),
+    ("gqg queue <friend-name>", "help.queue"),
+    ("gqg queue list", "help.queue_list"),
+    ("gqg queue flush [--output-dir <dir>]", "help.queue_flush"),
+    ("gqg queue status", "help.queue_status"),
+    ("gqg queue run", "help.queue_run"),
+    ("gqg queue retry <n>", "help.queue_retry"),
+    ("gqg queue drop <n>", "help.queue_drop"),
+    ("gqg mail send <friend-name> <address>", "help.mail_send"),
+    ("gqg mail fetch [--insecure]", "help.mail_fetch"),
+    ("gqg push <friend-name> --via <via>", "help.push"),
+    ("gqg chaff --via <via> [--count <n>]", "help.chaff"),
+    ("gqg subscribe --via mqtt [--insecure]", "help.subscribe"),
+    ("gqg pull --via nostr [--insecure]", "help.pull"),
+    ("gqg pull --local [--dir <dir>]", "help.pull_local"),
+    ("gqg open <gqg:link>", "help.open"),
+    ("gqg open [<n> | --from <friend>]", "help.open_stored"),
+    ("gqg read <n>", "help.read"),
+    ("gqg search <term>... | --rebuild", "help.search"),
+    ("gqg thread <friend-name>", "help.thread"),
+    ("gqg export-thread <name> --format <f>", "help.export_thread"),
+    ("gqg register-handler", "help.register_handler"),
+    ("gqg discover <user@domain> [<name>]", "help.discover"),
+    ("gqg publish --format well-known", "help.publish"),
+    ("gqg provision <csv> [--bundles <dir>] [--trust-verified]", "help.provision"),
+    ("gqg serve [--listen <addr>]", "help.serve"),
+    ("gqg serve --dropbox [--listen <addr>]", "help.serve_dropbox"),
+    ("gqg integrity status", "help.integrity_status"),
+    ("gqg integrity accept", "help.integrity_accept"),
+    ("gqg integrity off", "help.integrity_off"),
+    ("gqg undo", "help.undo"),
+    ("gqg trash list", "help.trash_list"),
+    ("gqg restore <name>", "help.restore"),
+    ("gqg signkey [--format <f>]", "help.signkey"),
+    ("gqg signfile <file> [--format <f>]", "help.signfile"),
+    ("gqg verifyfile <file> --key <pub>", "help.verifyfile"),
+    ("gqg selftest", "help.selftest"),
+    ("gqg debug-decode [--volumes <file>]", "help.debug_decode"),
+    ("gqg roster export <friend> [<name>...]", "help.roster_export"),
+    ("gqg roster import <file> --signer <f>", "help.roster_import"),
+];
+
+static FLAGS: &[(&str, &str)] = &[
+    ("--stdout", "help.flag.stdout"),
+    ("--insecure", "help.flag.insecure"),
+    ("--batch", "help.flag.batch"),
+    ("--timeout <seconds>", "help.flag.timeout"),
+    ("-o, --output <file>", "help.flag.output"),
+    ("--output-dir <dir>", "help.flag.output_dir"),
+    ("--volume-size <size>", "help.flag.volume_size"),
+    ("--dedup", "help.flag.dedup"),
+    ("--delta --base <file>", "help.flag.delta"),
+    ("--mime-type <type>", "help.flag.mime_type"),
+    ("--read-only", "help.flag.read_only"),
+    ("--portable", "help.flag.portable"),
+    ("--config <file>", "help.flag.config"),
+    ("--lang <language>", "help.flag.lang"),
+    ("--as-file [--file-name <name>]", "help.flag.as_file"),
+];
+
+fn help() -> ! {
+    logo();
+    println!("{}", i18n::tr("help.usage"));
+    for &(usage, key) in COMMANDS {
+        println!("    {:<38} : {}", usage, i18n::tr(key));
+    }
+    println!("{}", i18n::tr("help.flags"));
+    for &(usage, key) in FLAGS {
+        println!("    {:<38} : {}", usage, i18n::tr(key));
+    }
+    if cfg!(target_os = "windows") {
+        println!("\n{}", i18n::tr("help.windows"));
+    }
+    println!("");
+    std::process::exit(1);
+}
+
+pub fn report(err: &anyhow::Error) -> i32 {
+    eprintln!("{}", red!(format!("{} {}", i18n::tr("error"), err)));
+    if err.downcast_ref::<interact::InteractionRequired>().is_some() {
+        return interact::EXIT_INTERACTION;
+    }
+    1
+}
+
+static READ_ONLY_COMMANDS: &[&str] = &[
+    "list", "recv", "receive", "send", "sendfile", "dirs", "audit-log", "audit", "whoami", "stats", "id",
+    "inspect", "inbox", "verify-stored", "read", "search", "thread", "export-thread", "sent", "selftest",
+    "debug-decode", "trash", "verifyfile",
+];
+
+fn read_only_safe(action: &str, args: &[String]) -> bool {
+    match action {
+        "config" => args.len() < 4,
+        "integrity" => args.get(2).map(|arg| arg == "status").unwrap_or(false),
+        "search" => !args.iter().any(|arg| arg == "--rebuild"),
+        _ => READ_ONLY_COMMANDS.contains(&action),
+    }
+}
+
+pub fn run() -> Result<()> {
+    sodiumoxide::init().map_err(|_| anyhow!("Unable to initialize libsodium."))?;
+    let mut args: Vec<String> = std::env::args().collect();
+    interact::set_batch(take_flag(&mut args, "--batch"));
+    let timeout = take_option(&mut args, "--timeout")
+        .map(|seconds| seconds.parse::<u64>().map_err(|_| anyhow!("Invalid --timeout value.")))
+        .transpose()?;
+    interact::set_stdin_timeout(timeout);
+    i18n::init(take_option(&mut args, "--lang"));
+    let portable = take_flag(&mut args, "--portable");
+    match take_option(&mut args, "--config").or_else(|| std::env::var("GQG_CONFIG").ok().filter(|path| !path.is_empty())) {
+        Some(path) => profile::use_config(&path)?,
+        None => profile::enter_portable(portable)?,
+    }
+    let read_only = take_flag(&mut args, "--read-only");
+    fsutil::set_read_only(read_only || !fsutil::is_writable(std::path::Path::new(&Database::config_path())));
+    let action = if args.len() > 1 { &args[1] } else { "receive" };
+    if fsutil::is_read_only() && !read_only_safe(action, &args) {
+        return Err(Error::ReadOnly.into());
+    }
+    let settings = Settings::load()?;
+    fsutil::set_shared(settings.get("files.permissions") == "umask");
+    if action != "integrity" {
+        integrity::check()?;
+    }
+    let mut db = Database::load();
+    let result = match action.as_ref() {
+        "list" => {
+            cmd_list(&db)
+        }
+        "newid" => {
+            cmd_newid(args, &mut db)
+        }
+        "befriend" => {
+            cmd_befriend(args, &mut db)
+        }
+        "unfriend" => {
+            cmd_unfriend(args, &mut db)
+        }
+        "rekey" => {
+            cmd_rekey(args, &mut db)
+        }
+        "recv" | "receive" => {
+            cmd_receive(args, &db)
+        }
+        "send" => {
+            cmd_send(args, &db)
+        }
+        "sendfile" => {
+            cmd_sendfile(args, &db)
+        }
+        "dirs" => {
+            cmd_dirs()
+        }
+        "active" => {
+            cmd_active(args, &mut db)
+        }
+        "audit-log" => {
+            cmd_audit_log()
+        }
+        "audit" => {
+            cmd_audit(args, &db)
+        }
+        "config" => {
+            cmd_config(args)
+        }
+        "bench" => {
+            cmd_bench(args, &db)
+        }
+        "whoami" => {
+            cmd_whoami(args, &db)
+        }
+        "invite" => {
+            cmd_invite(args, &db)
+        }
+        "accept" => {
+            cmd_accept(args, &mut db)
+        }
+        "pair" => {
+            cmd_pair(args, &mut db)
+        }
+        "alias" => {
+            cmd_alias(args, &db)
+        }
+        "unalias" => {
+            cmd_unalias(args)
+        }
+        "stats" => {
+            cmd_stats(args, &db)
+        }
+        "id" => {
+            cmd_id(args, &db)
+        }
+        "inspect" => {
+            cmd_inspect(args, &db)
+        }
+        "inbox" => {
+            cmd_inbox(args)
+        }
+        "verify-stored" => {
+            cmd_verify_stored(args, &db)
+        }
+        "read" => {
+            cmd_read(args, &db)
+        }
+        "tag-msg" => {
+            cmd_tag_msg(args)
+        }
+        "flag" => {
+            cmd_flag(args, true)
+        }
+        "unflag" => {
+            cmd_flag(args, false)
+        }
+        "search" => {
+            cmd_search(args, &db)
+        }
+        "dedup" => {
+            cmd_dedup(args, &db)
+        }
+        "sync" => {
+            cmd_sync(args, &db)
+        }
+        "channel" => {
+            cmd_channel(args, &db)
+        }
+        "thread" => {
+            cmd_thread(args, &db)
+        }
+        "export-thread" => {
+            cmd_export_thread(args, &db)
+        }
+        "release" => {
+            cmd_release(args)
+        }
+        "archive" => {
+            cmd_archive(args, &db)
+        }
+        "unarchive" => {
+            cmd_unarchive(args, &db)
+        }
+        "prune" => {
+            cmd_prune(args)
+        }
+        "seal" => {
+            cmd_seal(args, &db)
+        }
+        "note" => {
+            cmd_note(args, &db)
+        }
+        "vault" => {
+            cmd_vault(args, &db)
+        }
+        "sent" => {
+            cmd_sent(args, &db)
+        }
+        "queue" => {
+            cmd_queue(args, &db)
+        }
+        "mail" => {
+            cmd_mail(args, &db)
+        }
+        "push" => {
+            cmd_push(args, &db)
+        }
+        "chaff" => {
+            cmd_chaff(args, &db)
+        }
+        "subscribe" => {
+            cmd_subscribe(args, &db)
+        }
+        "pull" => {
+            cmd_pull(args, &db)
+        }
+        "open" => {
+            cmd_open(args, &mut db)
+        }
+        "register-handler" => {
+            cmd_register_handler()
+        }
+        "discover" => {
+            cmd_discover(args, &mut db)
+        }
+        "publish" => {
+            cmd_publish(args, &db)
+        }
+        "provision" => {
+            cmd_provision(args, &mut db)
+        }
+        "serve" => {
+            cmd_serve(args, &db)
+        }
+        "selftest" => {
+            cmd_selftest()
+        }
+        "debug-decode" => {
+            cmd_debug_decode(args, &db)
+        }
+        "roster" => {
+            cmd_roster(args, &mut db)
+        }
+        "integrity" => {
+            cmd_integrity(args)
+        }
+        "undo" => {
+            cmd_undo(&mut db)
+        }
+        "signkey" => {
+            cmd_signkey(args, &db)
+        }
+        "signfile" => {
+            cmd_signfile(args, &db)
+        }
+        "verifyfile" => {
+            cmd_verifyfile(args)
+        }
+        "trash" => {
+            cmd_trash(args)
+        }
+        "restore" => {
+            cmd_restore(args, &mut db)
+        }
+        _ => {
+            cmd_plugin(action, &args, &db)
+        }
+    };
+    let status_only = action == "integrity" && args.get(2).map(|arg| arg == "status").unwrap_or(false);
+    if !fsutil::is_read_only() {
+        fsutil::restrict(std::path::Path::new(&Database::config_path()));
+    }
+    if result.is_ok() && !fsutil::is_read_only() && !status_only {
+        if let Err(err) = Settings::load().and_then(|settings| integrity::seal(&settings)) {
+            eprintln!("{}", red!(format!("{} {}", i18n::tr("integrity.seal_failed"), err)));
+        }
+    }
+    result
+}
+
+macro_rules! arg {
+    ($args:expr, $i:expr) => {
+        if ($i < $args.len()) { &$args[$i] } else { help() }
+    }
+}
+
+fn cmd_integrity(args: Vec<String>) -> Result<()> {
+    let settings = Settings::load()?;
+    match arg!(&args, 2).as_str() {
+        "status" => {
+            if integrity::key()?.is_none() && !integrity::is_sealed() {
+                let state = if settings.get("integrity.mac") == "on" { "integrity.unsealed" } else { "integrity.off" };
+                println!("{}", i18n::tr(state));
+                return Ok(());
+            }
+            integrity::check()?;
+            println!("{} {}", green!(i18n::tr("integrity.ok").to_string()),
+                grey!(integrity::key_path_buf().display().to_string()));
+            if !integrity::is_key_external() {
+                eprintln!("{}", grey!(i18n::tr("integrity.same_medium").to_string()));
+            }
+            Ok(())
+        }
+        "accept" => {
+            if fsutil::is_read_only() {
+                return Err(Error::ReadOnly.into());
+            }
+            audit::record("integrity-accept", "database", "")?;
+            eprintln!("{}", i18n::tr("integrity.accepted"));
+            Ok(())
+        }
+        "off" => {
+            if fsutil::is_read_only() {
+                return Err(Error::ReadOnly.into());
+            }
+            integrity::check()?;
+            let mut settings = settings;
+            settings.set("integrity.mac", "off")?;
+            settings.save()?;
+            integrity::disable()?;
+            audit::record("integrity-off", "database", "")?;
+            eprintln!("{}", i18n::tr("integrity.disabled"));
+            Ok(())
+        }
+        _ => help(),
+    }
+}
+
+fn add_friends(db: &mut Database, friends: &[journal::Friend]) -> Result<()> {
+    for friend in friends {
+        db.add_friend(friend.name.clone(), friend.public_id.clone())?;
+        for alias in &friend.aliases {
+            if aliases::lookup(alias)?.is_none() {
+                aliases::add(alias, &friend.name)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn trash_days() -> Result<i64> {
+    Settings::load()?.get("trash.days").parse::<i64>()
+        .map_err(|_| anyhow!("Invalid number for trash.days."))
+}
+
+fn cmd_trash(args: Vec<String>) -> Result<()> {
+    match arg!(&args, 2).as_str() {
+        "list" => {
+            for item in trash::purge(trash_days()?)? {
+                let key = item.friends.first().map(|friend| fingerprint(&friend.public_id)).unwrap_or_default();
+                println!("{} {} {}", item.name, grey!(key), grey!(local_time(item.removed)));
+            }
+            Ok(())
+        }
+        _ => help(),
+    }
+}
+
+fn cmd_restore(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let name = arg!(&args, 2);
+    let item = trash::purge(trash_days()?)?
+        .into_iter()
+        .find(|item| item.name == *name)
+        .ok_or(anyhow!(i18n::tr("trash.not_found")))?;
+    if let Some(friend) = item.friends.iter().find(|friend| db.find_friend(&friend.name).is_some()) {
+        return Err(anyhow!("{} {}", i18n::tr("undo.conflict"), friend.name));
+    }
+    trash::take(name)?;
+    add_friends(db, &item.friends)?;
+    audit::record("restore", name, &item.friends.first().map(|friend| friend.public_id.clone()).unwrap_or_default())?;
+    eprintln!("{} {}", i18n::tr("trash.restored"), name);
+    Ok(())
+}
+
+fn cmd_undo(db: &mut Database) -> Result<()> {
+    let window = Settings::load()?.get("undo.window").parse::<i64>()
+        .map_err(|_| anyhow!("Invalid number for undo.window."))?;
+    let mut entries = journal::load()?;
+    let entry = match entries.pop() {
+        Some(entry) if window == 0 || chrono::Utc::now().timestamp() - entry.time <= window => entry,
+        _ => return Err(anyhow!(i18n::tr("undo.nothing"))),
+    };
+    for friend in &entry.removed {
+        if db.find_friend(&friend.name).is_some() && !entry.added.contains(&friend.name) {
+            return Err(anyhow!("{} {}", i18n::tr("undo.conflict"), friend.name));
+        }
+    }
+    for name in &entry.added {
+        if db.find_friend(name).is_some() {
+            db.del_friend(name.clone())?;
+        }
+    }
+    add_friends(db, &entry.removed)?;
+    if entry.action == "unfriend" {
+        trash::take(&entry.subject)?;
+    }
+    if entry.trust {
+        trust::set_verified(&entry.subject, &entry.verified, !entry.verified.is_empty())?;
+    }
+    journal::store(&entries)?;
+    audit::record("undo", &entry.subject, &entry.action)?;
+    eprintln!("{} {} {}", i18n::tr("undo.done"), entry.action, entry.subject);
+    Ok(())
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false
+    }
+}
+
+pub fn resolve_friend(db: &Database, name: &str) -> Result<String> {
+    if let Some(friend) = aliases::lookup(name)? {
+        return Ok(friend);
+    }
+    let mut names: Vec<String> = db.get_friends()
+        .into_iter()
+        .filter(|friend| !keyhistory::is_retired(&friend.name))
+        .map(|friend| friend.name.to_string())
+        .collect();
+    names.extend(aliases::read()?.into_iter().map(|(alias, _)| alias));
+    let allow_prefix = Settings::load()?.get("names.prefix_match") == "true";
+    names::resolve(name, &names, allow_prefix, i18n::tr("error.friend_not_found"))
+}
+
+pub fn resolve_identity(db: &Database, name: &str) -> Result<String> {
+    let names: Vec<String> = db.get_identities()
+        .into_iter()
+        .map(|id| id.name.to_string())
+        .collect();
+    let allow_prefix = Settings::load()?.get("names.prefix_match") == "true";
+    names::resolve(name, &names, allow_prefix, i18n::tr("error.identity_not_found"))
+}
+
+fn take_option(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == flag)?;
+    if i + 1 >= args.len() {
+        help();
+    }
+    args.remove(i);
+    Some(args.remove(i))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    sodiumoxide::hex::encode(&sha256::hash(data).0)
+}
+
+pub fn fingerprint(public_id: &str) -> String {
+    let digest = sodiumoxide::hex::encode(&sha256::hash(public_id.as_bytes()).0[..16]);
+    digest.as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn cmd_list(db: &Database) -> Result<()> {
+    logo();
+    let active_id = db.get_active_identity();
+    println!("{}", i18n::tr("list.identities"));
+    for id in db.get_identities() {
+        let name;
+        if id.name == active_id.name {
+            name = green!(format!("(*) {}", &id.name)).to_string()
+        }
+        else {
+            name = id.name.to_string()
+        };
+        println!("    {} {}", name, grey!(id.get_public_id()));
+    }
+    println!("");
+    let friends: Vec<_> = db.get_friends()
+        .into_iter()
+        .filter(|friend| !keyhistory::is_retired(&friend.name))
+        .collect();
+    if friends.len() > 0 {
+        println!("{}", i18n::tr("list.friends"));
+        for id in friends {
+            let public_id = id.get_public_id();
+            let mut name = id.name.to_string();
+            let aliases = aliases::of(&id.name)?;
+            if !aliases.is_empty() {
+                name = format!("{} ({})", name, aliases.join(", "));
+            }
+            if trust::is_verified(&id.name, &public_id) {
+                println!("    {} {} {}", name, public_id, green!(i18n::tr("list.verified").to_string()));
+            }
+            else {
+                println!("    {} {}", name, public_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_newid(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let name = arg!(&args, 2);
+    db.add_identity(name.clone())?;
+    let public_id = db.get_identities()
+        .into_iter()
+        .find(|id| &id.name == name)
+        .map(|id| id.get_public_id())
+        .unwrap_or_default();
+    audit::record("newid", name, &public_id)?;
+    Ok(())
+}
+
+fn cmd_befriend(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let name = arg!(&args, 2);
+    let key = arg!(&args, 3);
+    db.add_friend(name.clone(), key.clone())?;
+    audit::record("befriend", name, key)?;
+    Ok(())
+}
+
+fn cmd_rekey(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let key = arg!(&args, 3);
+    let old_key = db.find_friend(name)
+        .map(|friend| friend.get_public_id())
+        .ok_or(Error::FriendNotFound)?;
+    if &old_key == key {
+        return Ok(());
+    }
+    let names: Vec<String> = db.get_friends()
+        .into_iter()
+        .map(|friend| friend.name.to_string())
+        .collect();
+    let retired = keyhistory::retired_name(name, names.iter().map(|name| name.as_str()));
+    db.del_friend(name.clone())?;
+    db.add_friend(retired.clone(), old_key.clone())?;
+    db.add_friend(name.clone(), key.clone())?;
+    journal::record(journal::Entry {
+        action: "rekey".to_string(),
+        subject: name.clone(),
+        removed: vec![journal::Friend { name: name.clone(), public_id: old_key.clone(), ..Default::default() }],
+        added: vec![retired, name.clone()],
+        ..Default::default()
+    })?;
+    audit::record("rekey", name, &format!("{} -> {}", old_key, key))?;
+    eprintln!("{} {}", i18n::tr("rekey.retired"), grey!(fingerprint(&old_key)));
+    Ok(())
+}
+
+fn cmd_unfriend(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let key = db.find_friend(name)
+        .map(|friend| friend.get_public_id())
+        .unwrap_or_default();
+    let mut removed = vec![journal::Friend {
+        name: name.clone(),
+        public_id: key.clone(),
+        aliases: aliases::of(name)?,
+    }];
+    db.del_friend(name.clone())?;
+    let retired: Vec<(String, String)> = db.get_friends()
+        .into_iter()
+        .filter(|retired| keyhistory::owner(&retired.name).as_deref() == Some(name.as_str()))
+        .map(|retired| (retired.name.to_string(), retired.get_public_id()))
+        .collect();
+    for (retired, public_id) in retired {
+        db.del_friend(retired.clone())?;
+        removed.push(journal::Friend { name: retired, public_id, ..Default::default() });
+    }
+    aliases::remove_friend(name)?;
+    trash::purge(trash_days()?)?;
+    trash::add(name, removed.clone())?;
+    journal::record(journal::Entry {
+        action: "unfriend".to_string(),
+        subject: name.clone(),
+        removed,
+        ..Default::default()
+    })?;
+    audit::record("unfriend", name, &key)?;
+    Ok(())
+}
+
+pub fn trial_decode<K, M, F>(keys: Vec<K>, decode: F) -> Option<M>
+where
+    K: Send + 'static,
+    M: Send + 'static,
+    F: Fn(&K) -> Option<M> + Send + Sync + 'static,
+{
+    if keys.len() <= 1 {
+        return keys.iter().find_map(|key| decode(key));
+    }
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(keys.len());
+    let queue = Arc::new(Mutex::new(VecDeque::from(keys)));
+    let found = Arc::new(AtomicBool::new(false));
+    let decode = Arc::new(decode);
+    let (tx, rx) = std::sync::mpsc::channel();
+    for _ in 0..workers {
+        let queue = queue.clone();
+        let found = found.clone();
+        let decode = decode.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            while !found.load(Ordering::Relaxed) {
+                let key = match queue.lock().unwrap().pop_front() {
+                    Some(key) => key,
+                    None => break,
+                };
+                if let Some(msg) = decode(&key) {
+                    found.store(true, Ordering::Relaxed);
+                    let _ = tx.send(msg);
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+    rx.recv().ok()
+}
+
+pub fn render_file_name(template: &str, fields: &[(&str, &str)]) -> Result<String> {
+    let now = chrono::Local::now();
+    let mut name = String::new();
+    write!(name, "{}", now.format(template))
+        .map_err(|_| anyhow!("Invalid file name template."))?;
+    let date = now.format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut name = name.replace("{date}", &date);
+    for (placeholder, value) in fields {
+        name = name.replace(placeholder, value);
+    }
+    Ok(fsutil::sanitize_file_name(&name))
+}
+
+pub fn message_file_name(template: &str, sender: &str, contents: &[u8]) -> Result<String> {
+    let subject: String = String::from_utf8_lossy(contents)
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("")
+        .chars()
+        .take(40)
+        .collect();
+    render_file_name(template, &[("{sender}", sender), ("{subject}", subject.trim())])
+}
+
+fn quarantine_path_buf() -> std::path::PathBuf {
+    settings::state_path_buf("quarantine")
+}
+
+pub fn output_dir(mut dir: std::path::PathBuf, sender: &str, settings: &Settings) -> Result<std::path::PathBuf> {
+    if settings.get("receive.sender_dirs") == "true" {
+        dir.push(fsutil::sanitize_file_name(sender));
+        std::fs::create_dir_all(&dir)
+            .map_err(|_| anyhow!("Unable to create {}.", dir.display()))?;
+    }
+    Ok(dir)
+}
+
+fn read_stdin_chunks(limit: u64, timeout: u64) -> Result<Vec<u8>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin().take(limit);
+        let mut buf = vec![0u8; 1 << 16];
+        loop {
+            match stdin.read(&mut buf) {
+                Ok(0) => {
+                    let _ = tx.send(Ok(Vec::new()));
+                    break;
+                }
+                Ok(n) => {
+                    if tx.send(Ok(buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    let _ = tx.send(Err(()));
+                    break;
+                }
+            }
+        }
+    });
+    let mut input = Vec::new();
+    loop {
+        let chunk = if timeout == 0 {
+            rx.recv().map_err(|_| anyhow!("Unable to read stdin."))?
+        }
+        else {
+            match rx.recv_timeout(Duration::from_secs(timeout)) {
+                Ok(chunk) => chunk,
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                    return Err(anyhow!("{} {}s.", i18n::tr("stdin.timeout"), timeout));
+                }
+                Err(_) => return Err(anyhow!("Unable to read stdin.")),
+            }
+        };
+        match chunk {
+            Ok(chunk) if chunk.is_empty() => break,
+            Ok(chunk) => input.extend_from_slice(&chunk),
+            Err(()) => return Err(anyhow!("Unable to read stdin.")),
+        }
+    }
+    Ok(input)
+}
+
+pub fn read_stdin(settings: &Settings) -> Result<String> {
+    String::from_utf8(read_stdin_bytes(settings)?).map_err(|_| anyhow!(i18n::tr("error.not_utf8")))
+}
+
+pub fn read_stdin_bytes(settings: &Settings) -> Result<Vec<u8>> {
+    interact::require_piped_stdin()?;
+    let limit = settings.get_size("limits.max_input")?;
+    let terminal = std::io::stdin().is_terminal();
+    if terminal {
+        eprintln!("{}", grey!(i18n::tr("stdin.waiting").to_string()));
+    }
+    let timeout = match interact::stdin_timeout() {
+        Some(seconds) => seconds,
+        None if terminal => 0,
+        None => settings.get("limits.stdin_timeout").parse::<u64>()
+            .map_err(|_| anyhow!("Invalid number for limits.stdin_timeout."))?,
+    };
+    let input = read_stdin_chunks(limit.saturating_add(1), timeout)?;
+    if input.len() as u64 > limit {
+        return Err(Error::TooLarge(limit).into());
+    }
+    Ok(input)
+}
+
+fn read_pasted(settings: &Settings) -> Result<String> {
+    let limit = settings.get_size("limits.max_input")?;
+    eprintln!("{}", grey!(i18n::tr("stdin.paste").to_string()));
+    let mut input = String::new();
+    let mut armored = false;
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|_| anyhow!("Unable to read stdin."))?;
+        armored = armored || armor::is_armored(&line);
+        input.push_str(&line);
+        input.push('\n');
+        if input.len() as u64 > limit {
+            return Err(Error::TooLarge(limit).into());
+        }
+        if armored && armor::is_armor_end(&line) {
+            break;
+        }
+    }
+    if armored {
+        return armor::extract(&input).into_iter().next()
+            .ok_or(anyhow!(i18n::tr("receive.armor_truncated")));
+    }
+    Ok(input)
+}
+
+fn cmd_receive(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let insecure = take_flag(&mut args, "--insecure");
+    let settings = Settings::load()?;
+    let payload = match take_option(&mut args, "--volumes") {
+        Some(first) => volumes::join(std::path::Path::new(&first))?,
+        None if std::io::stdin().is_terminal() && !interact::is_batch() => read_pasted(&settings)?,
+        None => read_stdin(&settings)?,
+    };
+    if payload.trim().is_empty() {
+        return Err(anyhow!(i18n::tr("stdin.empty")));
+    }
+    if !armor::is_armored(&payload) {
+        receive_payload(&payload, insecure, &settings, db)?;
+        return Ok(());
+    }
+    let blocks = armor::extract(&payload);
+    if blocks.is_empty() {
+        return Err(anyhow!(i18n::tr("receive.armor_truncated")));
+    }
+    let mut failed = 0;
+    for block in &blocks {
+        if let Err(err) = receive_payload(block, insecure, &settings, db) {
+            if blocks.len() == 1 {
+                return Err(err);
+            }
+            failed += 1;
+            eprintln!("{}", red!(format!("{} {}", i18n::tr("error"), err)));
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} / {} {}", failed, blocks.len(), i18n::tr("receive.blocks_failed")));
+    }
+    Ok(())
+}
+
+fn receive_in_background(payload: &str, insecure: bool, settings: &Settings, db: &Database) -> bool {
+    match receive_payload(payload, insecure, settings, db) {
+        Ok(arrival) => {
+            let severity = if arrival.trust == "unknown" || arrival.trust == "previous-key" { syslog::WARNING } else { syslog::INFO };
+            if arrival.kind != "chaff" {
+                syslog::event(severity, "received", &[("sender", &arrival.sender), ("trust", &arrival.trust), ("kind", &arrival.kind)]);
+            }
+            notify::arrival(settings, &arrival);
+            if let Err(err) = notify::webhook(settings, &arrival) {
+                syslog::event(syslog::ERROR, "webhook-failed", &[("error", &err.to_string())]);
+                eprintln!("{}", red!(format!("{} {}", i18n::tr("error"), err)));
+            }
+            true
+        }
+        Err(err) => {
+            syslog::event(syslog::ERROR, "receive-failed", &[("error", &err.to_string())]);
+            eprintln!("{}", red!(format!("{} {}", i18n::tr("error"), err)));
+            false
+        }
+    }
+}
+
+fn payload_bodies(payload: &str) -> (Option<String>, Vec<String>) {
+    let candidates = armor::candidates(payload);
+    let hint = candidates.iter().find_map(|candidate| envelope::unwrap(candidate).0);
+    let mut bodies: Vec<String> = Vec::new();
+    for candidate in &candidates {
+        let (_, body) = envelope::unwrap(candidate);
+        if !bodies.contains(&body) {
+            bodies.push(body);
+        }
+    }
+    (hint, bodies)
+}
+
+pub fn receive_payload(payload: &str, insecure: bool, settings: &Settings, db: &Database) -> Result<notify::Arrival> {
+    let (hint, bodies) = payload_bodies(payload);
+    let keys: Vec<_> = db.get_identities()
+        .into_iter()
+        .filter(|id| match &hint {
+            Some(hint) => &envelope::hint(&id.get_public_id()) == hint,
+            None => true,
+        })
+        .map(|id| (id.name.to_string(), id.get_private_key()))
+        .collect();
+    if hint.is_some() && keys.is_empty() {
+        return Err(Error::NotAddressed.into());
+    }
+    let (identity, msg) = trial_decode(keys, move |(identity, key)| {
+        bodies.iter()
+            .find_map(|body| gqg_lib::decode(key, body.clone()).ok())
+            .map(|msg| (identity.clone(), msg))
+    })
+        .ok_or(Error::DecryptFailed)?;
+    eprintln!("{} {}", i18n::tr("receive.addressed_to"), identity);
+    let mut name = "untrusted";
+    let mut trust_status = "unknown";
+    let previous_owner: String;
+    let own_identity = db.get_identities()
+        .into_iter()
+        .find(|id| id.get_public_key() == msg.sender)
+        .map(|id| id.name.to_string());
+    match db.find_friend_by_key(&msg.sender) {
+        None if own_identity.is_some() => {
+            name = own_identity.as_deref().unwrap();
+            trust_status = "self";
+            eprintln!("{}", green!(format!("{} {}", i18n::tr("receive.sealed"), name)));
+        }
+        None => {
+            match settings.get("receive.policy") {
+                "strict" if !insecure => {
+                    return Err(Error::UnknownSenderRefused.into());
+                }
+                "allow" => {}
+                _ => {
+                    eprintln!("{}", red!(i18n::tr("receive.unknown_sender").to_string()));
+                }
+            }
+        }
+        Some(friend) => match keyhistory::owner(&friend.name) {
+            Some(owner) => {
+                eprintln!("{}", red!(format!("{} {}", i18n::tr("receive.previous_sender"), owner)));
+                previous_owner = owner;
+                name = &previous_owner;
+                trust_status = "previous-key";
+            }
+            None => {
+                eprintln!("{}", green!(format!("{} {}", i18n::tr("receive.verified"), friend.name)));
+                name = &friend.name;
+                trust_status = if trust::is_verified(&friend.name, &friend.get_public_id()) { "verified" } else { "friend" };
+            }
+        },
+    };
+    let decoded: Box<dyn AsRef<[u8]>>;
+    let data: &[u8];
+    let out_path;
+    let kind;
+    let mut hazard = None;
+    let mut rebuilt = None;
+    let mut mime_type = String::new();
+    match msg.data {
+        gqg_lib::DecodedData::Message { contents } => {
+            decoded = Box::new(contents);
+            let contents = padding::strip((*decoded).as_ref())?;
+            if chaff::is_chaff(contents) {
+                return Ok(notify::Arrival {
+                    sender: name.to_string(),
+                    trust: trust_status.to_string(),
+                    kind: "chaff".to_string(),
+                    path: String::new(),
+                });
+            }
+            let contents = frame::unescape(contents);
+            kind = "message";
+            let mut path = output_dir(Database::message_path_buf(), name, settings)?;
+            path.push(message_file_name(settings.get("receive.message_name"), name, contents)?);
+            data = contents;
+            out_path = path;
+        }
+        gqg_lib::DecodedData::File { file_name, contents } => {
+            decoded = Box::new(contents);
+            let contents = padding::strip((*decoded).as_ref())?;
+            let (declared, contents) = mime::unwrap(contents);
+            kind = "file";
+            if let Some((header, bundles)) = sync::unpack(contents)? {
+                if fsutil::is_read_only() {
+                    return Err(Error::ReadOnly.into());
+                }
+                return apply_sync(name, trust_status, &identity, header, bundles, settings, insecure);
+            }
+            let chunked = match dedup::rebuild(contents)? {
+                Some(rebuilt) => Some(rebuilt),
+                None => apply_delta(db, contents)?,
+            };
+            let contents = frame::unescape(contents);
+            let file_name = match &chunked {
+                Some((original, _)) => original.clone(),
+                None => file_name,
+            };
+            let body: &[u8] = match &chunked {
+                Some((_, body)) => body,
+                None => contents,
+            };
+            mime_type = declared.unwrap_or_else(|| mime::detect(&file_name, body).to_string());
+            hazard = danger::assess(&file_name, body).or_else(|| mime::assess(&mime_type));
+            rebuilt = chunked.map(|(_, body)| body);
+            let mut path = output_dir(Database::file_path_buf(), name, settings)?;
+            let original = fsutil::sanitize_file_name(&file_name);
+            path.push(render_file_name(settings.get("receive.file_name"), &[("{sender}", name), ("{original}", &original)])?);
+            data = contents;
+            out_path = path;
+        }
+    }
+    if let Some(reason) = &hazard {
+        check_dangerous(reason, trust_status, settings, insecure)?;
+    }
+    let quarantined = quarantines(settings, trust_status);
+    let maildir = kind == "message" && !quarantined && settings.get("receive.delivery") == "maildir";
+    let sealed = !maildir && settings.get("receive.at_rest") == "sealed";
+    let plaintext: &[u8] = match &rebuilt {
+        Some(body) => body,
+        None => data,
+    };
+    if fsutil::is_read_only() {
+        if quarantined && !insecure {
+            return Err(anyhow!(i18n::tr("receive.read_only_quarantine")));
+        }
+        std::io::stdout().write_all(plaintext).map_err(|_| anyhow!("Unable to write stdout."))?;
+        return Ok(notify::Arrival {
+            sender: name.to_string(),
+            trust: trust_status.to_string(),
+            kind: kind.to_string(),
+            path: "-".to_string(),
+        });
+    }
+    if rebuilt.is_some() && !quarantined {
+        dedup::keep(&[data])?;
+    }
+    let stored = if maildir {
+        maildir::message(name, trust_status, &identity, plaintext)
+    }
+    else if sealed {
+        seal_to_self(db, plaintext)?.into_bytes()
+    }
+    else {
+        plaintext.to_vec()
+    };
+    let out_path = if maildir {
+        maildir::new_path_buf(&maildir::dir_buf(settings)?)
+    }
+    else if sealed {
+        let mut path = out_path.into_os_string();
+        path.push(".gqg");
+        std::path::PathBuf::from(path)
+    }
+    else {
+        out_path
+    };
+    let release_path = out_path.to_str().unwrap().to_string();
+    let out_path = if quarantined {
+        let dir = quarantine_path_buf();
+        fsutil::create_dir_private(&dir)?;
+        dir.join(out_path.file_name().unwrap()).to_str().unwrap().to_string()
+    }
+    else {
+        release_path.clone()
+    };
+    if let Ok(_) = std::fs::metadata(&out_path) {
+        return Err(Error::FileExists.into());
+    }
+    if maildir {
+        maildir::deliver(std::path::Path::new(&out_path), &stored)?;
+    }
+    else if quarantined || sealed {
+        fsutil::write_private_file(std::path::Path::new(&out_path), &stored)?;
+    }
+    else {
+        fsutil::write_file(std::path::Path::new(&out_path), &stored)?;
+    }
+    println!("{}", out_path);
+    let checksum = sha256_hex(plaintext);
+    eprintln!("{} {}", i18n::tr("receive.checksum"), checksum);
+    let id = history::append(history::Entry {
+        direction: "received".to_string(),
+        peer: name.to_string(),
+        trust: trust_status.to_string(),
+        identity: identity.clone(),
+        kind: kind.to_string(),
+        path: out_path.clone(),
+        bytes: plaintext.len() as u64,
+        sha256: checksum,
+        quarantined,
+        release_path: if quarantined { release_path } else { String::new() },
+        sealed,
+        mime_type,
+        ..Default::default()
+    })?;
+    if settings.get("search.index") == "true" {
+        if let Err(err) = index_received(db, id, name, &out_path, kind, plaintext) {
+            eprintln!("{}", grey!(format!("{} {}", i18n::tr("search.update_failed"), err)));
+        }
+    }
+    if quarantined {
+        eprintln!("{}", red!(format!("{} {}", i18n::tr("receive.quarantined"), id)));
+    }
+    let hook = settings.get("hooks.on_receive");
+    if !hook.is_empty() {
+        let env = [
+            ("GQG_SENDER", name),
+            ("GQG_TRUST", trust_status),
+            ("GQG_IDENTITY", identity.as_str()),
+            ("GQG_TYPE", kind),
+            ("GQG_OUTPUT", out_path.as_str()),
+        ];
+        match hooks::run(hook, &env) {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                eprintln!("{}", red!(format!("{} {}", i18n::tr("receive.hook_failed"), status)));
+            }
+            Err(err) => {
+                eprintln!("{}", red!(format!("{} {}", i18n::tr("receive.hook_failed"), err)));
+            }
+        }
+    }
+    if settings.get("retention.auto") == "true" {
+        prune(settings, false)?;
+    }
+    Ok(notify::Arrival {
+        sender: name.to_string(),
+        trust: trust_status.to_string(),
+        kind: kind.to_string(),
+        path: out_path,
+    })
+}
+
+fn outgoing(public_id: &str, msg: String) -> Result<String> {
+    let settings = Settings::load()?;
+    let hint = if settings.get("send.recipient_hint") == "true" { Some(envelope::hint(public_id)) } else { None };
+    let payload = envelope::wrap(hint, msg);
+    if settings.get("send.armor") == "true" {
+        return Ok(armor::armor(&payload));
+    }
+    Ok(payload)
+}
+
+enum Output {
+    Stdout,
+    File(String),
+    Dir(String),
+}
+
+impl Output {
+    fn take(args: &mut Vec<String>) -> Output {
+        if let Some(path) = take_option(args, "-o").or_else(|| take_option(args, "--output")) {
+            return Output::File(path);
+        }
+        match take_option(args, "--output-dir") {
+            Some(dir) => Output::Dir(dir),
+            None => Output::Stdout,
+        }
+    }
+
+    fn emit_volumes(&self, recipient: &str, default_name: &str, payload: String, volume_size: u64) -> Result<()> {
+        let base = match self {
+            Output::Stdout => std::path::PathBuf::from(format!("{}.gqg", default_name)),
+            Output::File(path) => std::path::PathBuf::from(path),
+            Output::Dir(dir) => {
+                std::fs::create_dir_all(dir).map_err(|_| anyhow!("Unable to create {}.", dir))?;
+                std::path::Path::new(dir).join(format!("{}.gqg", fsutil::sanitize_file_name(recipient)))
+            }
+        };
+        for path in volumes::split(&payload, volume_size, &base)? {
+            eprintln!("{}", path.display());
+        }
+        Ok(())
+    }
+
+    fn emit(&self, recipient: &str, payload: String) -> Result<()> {
+        let path = match self {
+            Output::Stdout => {
+                println!("{}", payload);
+                return Ok(());
+            }
+            Output::File(path) => std::path::PathBuf::from(path),
+            Output::Dir(dir) => {
+                std::fs::create_dir_all(dir).map_err(|_| anyhow!("Unable to create {}.", dir))?;
+                std::path::Path::new(dir).join(format!("{}.gqg", fsutil::sanitize_file_name(recipient)))
+            }
+        };
+        fsutil::create_private(&path)
+            .and_then(|mut file| writeln!(file, "{}", payload))
+            .map_err(|_| anyhow!("Unable to write {}.", path.display()))?;
+        eprintln!("{}", path.display());
+        Ok(())
+    }
+}
+
+fn sent_path_buf() -> std::path::PathBuf {
+    settings::state_path_buf("sent")
+}
+
+pub fn record_sent(db: &Database, name: &str, kind: &str, file_name: Option<&str>, contents: &[u8]) -> Result<()> {
+    if fsutil::is_read_only() {
+        return Ok(());
+    }
+    let path = if Settings::load()?.get("send.keep_sent") == "true" {
+        let dir = sent_path_buf();
+        fsutil::create_dir_private(&dir)?;
+        let path = dir.join(format!("{}.gqg", sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8))));
+        fsutil::write_private_file(&path, seal_to_self(db, contents)?.as_bytes())?;
+        path.to_str().unwrap().to_string()
+    }
+    else {
+        String::new()
+    };
+    history::append(history::Entry {
+        direction: "sent".to_string(),
+        peer: name.to_string(),
+        kind: kind.to_string(),
+        file_name: file_name.unwrap_or_default().to_string(),
+        path,
+        bytes: contents.len() as u64,
+        ..Default::default()
+    })?;
+    Ok(())
+}
+
+pub fn encrypt_message(db: &Database, name: &str, contents: &[u8]) -> Result<String> {
+    encrypt_to(db, name, gqg_lib::Type::Message, &frame::escape(contents))
+}
+
+pub fn encrypt_file(db: &Database, name: &str, file_name: &str, contents: &[u8]) -> Result<String> {
+    encrypt_to(db, name, gqg_lib::Type::File { file_name: file_name.to_string() }, &frame::escape(contents))
+}
+
+fn encrypt_to(db: &Database, name: &str, kind: gqg_lib::Type, contents: &[u8]) -> Result<String> {
+    let padded = padding::pad(Settings::load()?.get("send.padding"), contents)?;
+    let contents = padded.as_deref().unwrap_or(contents);
+    match db.find_friend(&name.to_string()) {
+        None => {
+            Err(Error::FriendNotFound.into())
+        }
+        Some(friend) => {
+            let to = friend.get_public_key();
+            let active_id = db.get_active_identity();
+            let from = active_id.get_private_key();
+            match gqg_lib::encode(
+                &from,
+                &to,
+                kind, gqg_lib::EncodeFlags::None,
+                contents)
+            {
+                Err(err) => {
+                    Err(anyhow!("GQG library: {:?}", err))
+                }
+                Ok(msg) => {
+                    outgoing(&friend.get_public_id(), msg.to_string())
+                }
+            }
+        }
+    }
+}
+
+fn read_message(name: &str) -> Result<Vec<u8>> {
+    let contents = read_stdin_bytes(&Settings::load()?)?;
+    match hooks::before_send(name, "message", None, &contents)? {
+        Some(edited) => Ok(edited),
+        None => Ok(contents),
+    }
+}
+
+fn cmd_send(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let output = Output::take(&mut args);
+    let as_file = take_flag(&mut args, "--as-file");
+    let file_name = take_option(&mut args, "--file-name");
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let contents = read_stdin_bytes(&Settings::load()?)?;
+    if as_file || file_name.is_some() || std::str::from_utf8(&contents).is_err() {
+        if !as_file && file_name.is_none() {
+            eprintln!("{}", grey!(i18n::tr("send.binary").to_string()));
+        }
+        let file_name = fsutil::sanitize_file_name(file_name.as_deref().unwrap_or("stdin.bin"));
+        let contents = hooks::before_send(name, "file", Some(&file_name), &contents)?.unwrap_or(contents);
+        output.emit(name, encrypt_file(db, name, &file_name, &contents)?)?;
+        return record_sent(db, name, "file", Some(&file_name), &contents);
+    }
+    let contents = hooks::before_send(name, "message", None, &contents)?.unwrap_or(contents);
+    output.emit(name, encrypt_message(db, name, &contents)?)?;
+    record_sent(db, name, "message", None, &contents)
+}
+
+fn outbox_path_buf() -> std::path::PathBuf {
+    settings::state_path_buf("outbox")
+}
+
+fn queued() -> Result<Vec<std::path::PathBuf>> {
+    let mut paths: Vec<std::path::PathBuf> = match std::fs::read_dir(outbox_path_buf()) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(_) => return Err(anyhow!("Unable to read the outbox.")),
+    };
+    paths.sort();
+    Ok(paths)
+}
+
+fn cmd_queue(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let subcommand = arg!(&args, 2).clone();
+    match subcommand.as_str() {
+        "list" => {
+            for path in queued()? {
+                println!("{}", path.file_name().unwrap_or_default().to_string_lossy());
+            }
+            Ok(())
+        }
+        "flush" => {
+            let dir = std::path::PathBuf::from(take_option(&mut args, "--output-dir").unwrap_or_else(|| ".".to_string()));
+            std::fs::create_dir_all(&dir).map_err(|_| anyhow!("Unable to create {}.", dir.display()))?;
+            for path in queued()? {
+                let destination = dir.join(path.file_name().unwrap_or_default());
+                fsutil::move_file(&path, &destination)?;
+                println!("{}", destination.display());
+            }
+            Ok(())
+        }
+        "status" => {
+            let items = delivery::load()?;
+            if items.is_empty() {
+                eprintln!("{}", i18n::tr("queue.empty"));
+            }
+            for item in &items {
+                let state = if item.failed {
+                    red!(i18n::tr("queue.failed").to_string())
+                }
+                else {
+                    i18n::tr("queue.pending").to_string()
+                };
+                println!("{:>5} {:<8} {:<6} {} {} {}", item.id, state, item.transport, item.recipient,
+                    grey!(format!("{} {} {}", item.attempts, i18n::tr("queue.attempts"), local_time(item.next_attempt))),
+                    grey!(item.last_error.clone()));
+            }
+            Ok(())
+        }
+        "run" => {
+            let settings = Settings::load()?;
+            syslog::enable(&settings);
+            let failed = run_queue(&settings, db, None)?;
+            if !failed.is_empty() {
+                return Err(anyhow!("{} {}", failed.len(), i18n::tr("queue.still_pending")));
+            }
+            Ok(())
+        }
+        "retry" => {
+            let id = arg!(&args, 3).parse::<u64>().map_err(|_| anyhow!("Invalid queue entry."))?;
+            let mut items = delivery::load()?;
+            let item = items.iter_mut().find(|item| item.id == id).ok_or(anyhow!(i18n::tr("queue.not_found")))?;
+            item.failed = false;
+            item.attempts = 0;
+            delivery::store(&items)?;
+            if !run_queue(&Settings::load()?, db, Some(id))?.is_empty() {
+                return Err(anyhow!("{} {}.", i18n::tr("queue.queued"), id));
+            }
+            Ok(())
+        }
+        "drop" => {
+            let id = arg!(&args, 3).parse::<u64>().map_err(|_| anyhow!("Invalid queue entry."))?;
+            let mut items = delivery::load()?;
+            let count = items.len();
+            items.retain(|item| item.id != id);
+            if items.len() == count {
+                return Err(anyhow!(i18n::tr("queue.not_found")));
+            }
+            delivery::store(&items)
+        }
+        _ => {
+            let name = &resolve_friend(db, arg!(&args, 2))?;
+            let contents = read_message(name)?;
+            let payload = encrypt_message(db, name, &contents)?;
+            fsutil::create_dir_private(&outbox_path_buf())?;
+            let path = outbox_path_buf().join(format!("{}-{}.gqg",
+                chrono::Utc::now().format("%Y%m%d%H%M%S%3f"), fsutil::sanitize_file_name(name)));
+            fsutil::write_private_file(&path, format!("{}\n", payload).as_bytes())?;
+            eprintln!("{}", path.display());
+            record_sent(db, name, "message", None, &contents)
+        }
+    }
+}
+
+fn cmd_sendfile(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let output = Output::take(&mut args);
+    let dedup = take_flag(&mut args, "--dedup");
+    let delta = take_flag(&mut args, "--delta");
+    let base = take_option(&mut args, "--base");
+    let mime_type = take_option(&mut args, "--mime-type");
+    if delta && (base.is_none() || dedup) {
+        return Err(anyhow!(i18n::tr("delta.usage")));
+    }
+    if dedup && fsutil::is_read_only() {
+        return Err(Error::ReadOnly.into());
+    }
+    let volume_size = match take_option(&mut args, "--volume-size") {
+        Some(size) => Some(settings::parse_size(&size).ok_or(anyhow!("Invalid volume size."))?),
+        None => None,
+    };
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let file_path = arg!(&args, 3);
+    let file_name = &std::path::Path::new(file_path)
+        .file_name()
+        .ok_or(anyhow!(i18n::tr("error.invalid_path")))?
+        .to_str()
+        .ok_or(anyhow!(i18n::tr("error.invalid_path")))?
+        .to_string();
+    let limit = Settings::load()?.get_size("limits.max_input")?;
+    let size = std::fs::metadata(file_path).map_err(|_| anyhow!("Unable to open file."))?.len();
+    if size > limit {
+        return Err(Error::TooLarge(limit).into());
+    }
+    let mut contents = fsutil::read_file(std::path::Path::new(file_path))?;
+    if let Some(edited) = hooks::before_send(name, "file", Some(file_name), &contents[..])? {
+        contents = Box::new(edited);
+    }
+    let detect = Settings::load()?.get("send.mime_type") == "detect";
+    let mime_type = match mime_type {
+        Some(mime_type) if !mime::is_valid(&mime_type) => return Err(anyhow!("Invalid --mime-type value.")),
+        Some(mime_type) => Some(mime_type),
+        None if detect => Some(mime::detect(file_name, &contents[..]).to_string()),
+        None => None,
+    };
+    let packed = if dedup {
+        let packed = dedup::pack(file_name, &contents[..], &dedup::known(name)?)?;
+        eprintln!("{}", grey!(format!("{} / {} {}", packed.total - packed.new_chunks.len(), packed.total, i18n::tr("dedup.reused"))));
+        Some(packed)
+    }
+    else {
+        None
+    };
+    let patch = match &base {
+        Some(base) if delta => {
+            let base = fsutil::read_file(std::path::Path::new(base))?;
+            let patch = delta::diff(file_name, &base[..], &contents[..])?;
+            eprintln!("{}", grey!(format!("{} / {} {}", patch.len(), contents.len(), i18n::tr("delta.size"))));
+            Some(patch)
+        }
+        _ => None,
+    };
+    let escaped = frame::escape(&contents[..]);
+    let body: &[u8] = match (&packed, &patch) {
+        (Some(packed), _) => &packed.bundle,
+        (None, Some(patch)) => patch,
+        (None, None) => &escaped,
+    };
+    let labelled = mime_type.as_deref().map(|mime_type| mime::wrap(mime_type, body));
+    let body = labelled.as_deref().unwrap_or(body);
+    let padded = padding::pad(Settings::load()?.get("send.padding"), body)?;
+    let body = padded.as_deref().unwrap_or(body);
+    match db.find_friend(name) {
+        None => {
+            Err(Error::FriendNotFound.into())
+        }
+        Some(friend) => {
+            let to = friend.get_public_key();
+            let active_id = db.get_active_identity();
+            let from = active_id.get_private_key();
+            match gqg_lib::encode(
+                &from,
+                &to,
+                gqg_lib::Type::File { file_name }, gqg_lib::EncodeFlags::None,
+                body)
+            {
+                Err(err) => {
+                    Err(anyhow!("GQG library: {:?}", err))
+                }
+                Ok(msg) => {
+                    let payload = outgoing(&friend.get_public_id(), msg.to_string())?;
+                    match volume_size {
+                        Some(volume_size) => output.emit_volumes(name, file_name, payload, volume_size)?,
+                        None => output.emit(name, payload)?,
+                    }
+                    if let Some(packed) = &packed {
+                        dedup::stage(name, &packed.new_chunks)?;
+                    }
+                    record_sent(db, name, "file", Some(file_name), &contents[..])
+                }
+            }
+        }
+    }
+}
+
+fn apply_delta(db: &Database, contents: &[u8]) -> Result<Option<(String, Vec<u8>)>> {
+    let (file_name, base_sha256) = match delta::base_of(contents)? {
+        Some(base) => base,
+        None => return Ok(None),
+    };
+    for entry in history::load()?.iter().rev() {
+        if entry.direction != "received" || entry.kind != "file" || entry.sha256 != base_sha256 || entry.pruned {
+            continue;
+        }
+        if let Ok(base) = stored_contents(db, entry) {
+            if sha256_hex(&base) == base_sha256 {
+                eprintln!("{} {}", i18n::tr("delta.base"), entry.path);
+                return Ok(Some((file_name, delta::apply(contents, &base)?)));
+            }
+        }
+    }
+    Err(anyhow!(i18n::tr("delta.no_base")))
+}
+
+fn cmd_sync(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let output = Output::take(&mut args);
+    let full = take_flag(&mut args, "--full");
+    let confirm = take_flag(&mut args, "--confirm");
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let dir = std::fs::canonicalize(arg!(&args, 3)).map_err(|_| anyhow!("Unable to open {}.", arg!(&args, 3)))?;
+    let binding = sync::bind(name, &dir)?;
+    if confirm {
+        if !sync::confirm_state(&binding)? {
+            return Err(anyhow!(i18n::tr("sync.nothing_pending")));
+        }
+        dedup::confirm(name)?;
+        eprintln!("{}", i18n::tr("sync.confirmed"));
+        return Ok(());
+    }
+    let previous = if full { BTreeMap::new() } else { sync::load_state(&binding)? };
+    let current = sync::scan(&dir)?;
+    let changes = sync::diff(&previous, &current);
+    if changes.is_empty() {
+        eprintln!("{}", i18n::tr("sync.up_to_date"));
+        return Ok(());
+    }
+    let mut known = if full { Default::default() } else { dedup::known(name)? };
+    let mut bundles = Vec::new();
+    let mut new_chunks = Vec::new();
+    for change in changes.iter().filter(|change| !change.delete) {
+        let path = dir.join(&change.path);
+        let contents = std::fs::read(&path).map_err(|_| anyhow!("Unable to read {}.", path.display()))?;
+        let packed = dedup::pack(&change.path, &contents, &known)?;
+        known.extend(packed.new_chunks.iter().cloned());
+        new_chunks.extend(packed.new_chunks);
+        bundles.push(packed.bundle);
+    }
+    let count = changes.len();
+    let bundle = sync::pack(&sync::Header { name: binding.name.clone(), changes }, &bundles)?;
+    let file_name = format!("{}.gqgsync", binding.name);
+    output.emit(name, encrypt_to(db, name, gqg_lib::Type::File { file_name: file_name.clone() }, &bundle)?)?;
+    dedup::stage(name, &new_chunks)?;
+    sync::stage_state(&binding, &current)?;
+    record_sent(db, name, "file", Some(&file_name), &bundle)?;
+    eprintln!("{} {}", count, i18n::tr("sync.sent"));
+    Ok(())
+}
+
+fn check_dangerous(reason: &str, trust: &str, settings: &Settings, insecure: bool) -> Result<()> {
+    let trusted = trust == "verified" || trust == "self";
+    if settings.get("receive.dangerous") == "refuse-unverified" && !trusted && !insecure {
+        return Err(Error::DangerousRefused.into());
+    }
+    eprintln!("{}", red!(format!("{} {}.", i18n::tr("receive.dangerous"), reason)));
+    Ok(())
+}
+
+fn quarantines(settings: &Settings, trust: &str) -> bool {
+    match settings.get("receive.quarantine") {
+        "unknown" => trust == "unknown",
+        "unverified" => trust != "verified" && trust != "self",
+        _ => false,
+    }
+}
+
+fn apply_sync(sender: &str, trust: &str, identity: &str, header: sync::Header, bundles: Vec<&[u8]>,
+    settings: &Settings, insecure: bool) -> Result<notify::Arrival> {
+    let binding = sync::bindings()?
+        .into_iter()
+        .find(|binding| trust != "unknown" && binding.friend == sender && binding.name == header.name)
+        .ok_or(anyhow!("{} {} ({}). gqg sync {} <dir>", i18n::tr("sync.unbound"), header.name, sender, sender))?;
+    let dir = std::path::PathBuf::from(&binding.dir);
+    if bundles.len() != header.changes.iter().filter(|change| !change.delete).count() {
+        return Err(anyhow!("Sync bundle is corrupted."));
+    }
+    let mut rebuilt = dedup::rebuild_all(&bundles)?.into_iter();
+    let mut received = Vec::new();
+    for change in &header.changes {
+        let target = sync::safe_join(&dir, &change.path).ok_or(anyhow!("Sync bundle is corrupted."))?;
+        if change.delete {
+            received.push((change, target, None));
+            continue;
+        }
+        let (_, data) = rebuilt.next().flatten().ok_or(anyhow!("Sync bundle is corrupted."))?;
+        if sha256_hex(&data) != change.sha256 {
+            return Err(anyhow!("Sync bundle is corrupted."));
+        }
+        if let Some(reason) = danger::assess(&change.path, &data) {
+            eprintln!("{}", change.path);
+            check_dangerous(&reason, trust, settings, insecure)?;
+        }
+        received.push((change, target, Some(data)));
+    }
+    let bytes: u64 = received.iter().filter_map(|(_, _, data)| data.as_ref()).map(|data| data.len() as u64).sum();
+    if quarantines(settings, trust) {
+        let quarantine = quarantine_path_buf();
+        fsutil::create_dir_private(&quarantine)?;
+        for (change, _, data) in &received {
+            if let Some(data) = data {
+                let flat = change.path.replace(['/', '\\'], "_");
+                let path = quarantine.join(format!("{}-{}", fsutil::sanitize_file_name(&binding.name), fsutil::sanitize_file_name(&flat)));
+                fsutil::replace_private_file(&path, data)?;
+                println!("{}", path.display());
+            }
+        }
+        eprintln!("{}", red!(i18n::tr("sync.quarantined").to_string()));
+        return Ok(notify::Arrival {
+            sender: sender.to_string(),
+            trust: trust.to_string(),
+            kind: "sync".to_string(),
+            path: quarantine.to_string_lossy().to_string(),
+        });
+    }
+    dedup::keep(&bundles)?;
+    let mut state = sync::load_state(&binding)?;
+    for (change, target, data) in received {
+        let local = std::fs::read(&target).ok().map(|contents| sha256_hex(&contents));
+        let locally_changed = local.is_some() && local.as_ref() != state.get(&change.path);
+        let data = match data {
+            Some(data) => data,
+            None => {
+                if !locally_changed {
+                    let _ = std::fs::remove_file(&target);
+                }
+                state.remove(&change.path);
+                println!("{} {}", red!("-".to_string()), change.path);
+                continue;
+            }
+        };
+        if locally_changed && local.as_deref() != Some(change.sha256.as_str()) {
+            let conflict = sync::safe_join(&dir, &format!("{}.{}-conflict", change.path, fsutil::sanitize_file_name(sender)))
+                .ok_or(anyhow!("Sync bundle is corrupted."))?;
+            fsutil::replace_private_file(&conflict, &data)?;
+            println!("{} {}", red!("!".to_string()), conflict.display());
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| anyhow!("Unable to create {}.", parent.display()))?;
+        }
+        fsutil::replace_private_file(&target, &data)?;
+        state.insert(change.path.clone(), change.sha256.clone());
+        println!("{} {}", green!("+".to_string()), change.path);
+    }
+    sync::store_state(&binding, &state)?;
+    history::append(history::Entry {
+        direction: "received".to_string(),
+        peer: sender.to_string(),
+        trust: trust.to_string(),
+        identity: identity.to_string(),
+        kind: "sync".to_string(),
+        path: binding.dir.clone(),
+        bytes,
+        ..Default::default()
+    })?;
+    Ok(notify::Arrival {
+        sender: sender.to_string(),
+        trust: trust.to_string(),
+        kind: "sync".to_string(),
+        path: binding.dir,
+    })
+}
+
+fn local_time(unix: i64) -> String {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(unix.max(0) as u64);
+    chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn load_channels(db: &Database, identity: &str) -> Result<BTreeMap<String, channel::Channel>> {
+    let path = channel::path_buf(identity);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    channel::parse(&unseal(db, &path)?)
+}
+
+fn store_channels(db: &Database, identity: &str, channels: &BTreeMap<String, channel::Channel>) -> Result<()> {
+    fsutil::create_dir_private(&channel::dir_buf())?;
+    fsutil::replace_private_file(&channel::path_buf(identity), seal_to_self(db, &channel::encode(channels)?)?.as_bytes())
+}
+
+fn cmd_channel(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let output = Output::take(&mut args);
+    let identity = db.get_active_identity().name.to_string();
+    let mut channels = load_channels(db, &identity)?;
+    let subcommand = arg!(&args, 2).clone();
+    match subcommand.as_str() {
+        "create" => {
+            let name = arg!(&args, 3);
+            if !channel::valid_name(name) {
+                return Err(anyhow!(i18n::tr("channel.bad_name")));
+            }
+            if channels.contains_key(name) {
+                return Err(anyhow!(i18n::tr("channel.exists")));
+            }
+            let signer = signing::public_key(&signing_key(db)?).ok_or(anyhow!("Signing key is corrupted."))?;
+            let created = channel::create(&signer);
+            println!("{}", channel::share(name, &created));
+            channels.insert(name.clone(), created);
+            store_channels(db, &identity, &channels)
+        }
+        "share" => {
+            let name = arg!(&args, 3);
+            let found = channels.get(name).ok_or(anyhow!(i18n::tr("channel.not_found")))?;
+            println!("{}", channel::share(name, found));
+            Ok(())
+        }
+        "follow" => {
+            let (name, followed) = channel::parse_share(arg!(&args, 3))?;
+            let name = if args.len() > 4 { args[4].clone() } else { name };
+            if channels.contains_key(&name) {
+                return Err(anyhow!(i18n::tr("channel.exists")));
+            }
+            channels.insert(name, followed);
+            store_channels(db, &identity, &channels)
+        }
+        "post" => {
+            let name = arg!(&args, 3);
+            let found = channels.get(name).ok_or(anyhow!(i18n::tr("channel.not_found")))?;
+            let secret_key = signing_key(db)?;
+            if !found.owner || signing::public_key(&secret_key).map(|key| signing::encode_public(&key)) != Some(found.signer.clone()) {
+                return Err(anyhow!(i18n::tr("channel.not_owner")));
+            }
+            let body = read_stdin(&Settings::load()?)?;
+            output.emit(name, channel::seal_post(found, &secret_key, body.as_bytes())?)
+        }
+        "read" => {
+            let text = read_stdin(&Settings::load()?)?;
+            let posts = channel::split_posts(&text);
+            if posts.is_empty() {
+                return Err(anyhow!(i18n::tr("channel.no_posts")));
+            }
+            for (id, body) in posts {
+                let (name, found) = channels.iter()
+                    .find(|(_, found)| channel::id(found) == id)
+                    .ok_or(anyhow!(i18n::tr("channel.unknown")))?;
+                let (time, contents) = channel::open_post(found, &id, &body)?;
+                println!("{} {}", green!(name.clone()), grey!(local_time(time)));
+                println!("{}", interact::sanitize_terminal(&String::from_utf8_lossy(&contents)));
+            }
+            Ok(())
+        }
+        "list" => {
+            for (name, found) in &channels {
+                let role = if found.owner { i18n::tr("channel.owner") } else { i18n::tr("channel.follower") };
+                println!("{} {}", name, grey!(role.to_string()));
+            }
+            Ok(())
+        }
+        "rm" => {
+            channels.remove(arg!(&args, 3)).ok_or(anyhow!(i18n::tr("channel.not_found")))?;
+            store_channels(db, &identity, &channels)
+        }
+        _ => help(),
+    }
+}
+
+fn cmd_dedup(args: Vec<String>, db: &Database) -> Result<()> {
+    match arg!(&args, 2).as_str() {
+        "forget" => dedup::forget(&resolve_friend(db, arg!(&args, 3))?),
+        "confirm" => {
+            let confirmed = dedup::confirm(&resolve_friend(db, arg!(&args, 3))?)?;
+            eprintln!("{} {}", confirmed, i18n::tr("dedup.confirmed"));
+            Ok(())
+        }
+        _ => help(),
+    }
+}
+
+fn cmd_dirs() -> Result<()> {
+    logo();
+    println!("{:<18} {}", i18n::tr("dirs.config"), Database::config_path());
+    println!("{:<18} {}", i18n::tr("dirs.files"), Database::file_path_buf().to_str().unwrap().to_string());
+    println!("{:<18} {}", i18n::tr("dirs.messages"), Database::message_path_buf().to_str().unwrap().to_string());
+    println!("{:<18} {}", i18n::tr("dirs.quarantine"), quarantine_path_buf().to_str().unwrap().to_string());
+    println!("");
+    Ok(())
+}
+
+fn cmd_active(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let name = &resolve_identity(db, arg!(&args, 2))?;
+    db.set_active_identity(name)?;
+    audit::record("active", name, "")?;
+    Ok(())
+}
+
+fn cmd_audit_log() -> Result<()> {
+    let entries = audit::verify()?;
+    for entry in &entries {
+        println!("{:>5} {} {:<9} {} {}",
+            entry.seq,
+            entry.time,
+            entry.action,
+            entry.subject,
+            grey!(entry.detail.clone()));
+    }
+    eprintln!("{}", green!(format!("Audit log verified: {} entries.", entries.len())));
+    if let Some(last) = entries.last() {
+        eprintln!("{} {} {}", i18n::tr("audit.head"), last.seq, last.hash);
+    }
+    Ok(())
+}
+
+fn cmd_audit(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let json = take_flag(&mut args, "--json");
+    let settings = Settings::load()?;
+    let mut findings: Vec<(&str, String, String)> = Vec::new();
+    let paths = [
+        std::path::PathBuf::from(Database::config_path()),
+        settings::state_dir_buf(),
+        Database::message_path_buf(),
+        Database::file_path_buf(),
+        Settings::path_buf(),
+    ];
+    for path in &paths {
+        if let Some(mode) = fsutil::shared_mode(path) {
+            findings.push(("permissions", path.display().to_string(), format!("{} {:o}", i18n::tr("audit.shared_mode"), mode)));
+        }
+    }
+    let max_age = settings::parse_age(settings.get("audit.key_max_age"))
+        .ok_or(anyhow!("Invalid age for audit.key_max_age."))?;
+    for id in db.get_identities() {
+        let created = audit::find_last("newid", &id.name)
+            .and_then(|entry| chrono::DateTime::parse_from_rfc3339(&entry.time).ok());
+        if let Some(created) = created {
+            if created < chrono::Utc::now() - max_age {
+                findings.push(("key-age", id.name.to_string(), format!("{} {}", i18n::tr("audit.created"), created.to_rfc3339())));
+            }
+        }
+    }
+    let threshold = settings.get("audit.unverified_traffic").parse::<usize>()
+        .map_err(|_| anyhow!("Invalid number for audit.unverified_traffic."))?;
+    let entries = history::load()?;
+    for friend in db.get_friends().into_iter().filter(|friend| !keyhistory::is_retired(&friend.name)) {
+        if trust::is_verified(&friend.name, &friend.get_public_id()) {
+            continue;
+        }
+        let traffic = entries.iter().filter(|entry| entry.peer == friend.name).count();
+        if traffic >= threshold {
+            findings.push(("unverified", friend.name.to_string(), format!("{} {}", traffic, i18n::tr("audit.exchanges"))));
+        }
+    }
+    let plaintext = entries.iter()
+        .filter(|entry| entry.direction == "received" && !entry.sealed && !entry.pruned && entry.archive.is_empty())
+        .filter(|entry| std::path::Path::new(&entry.path).exists())
+        .count();
+    if plaintext > 0 {
+        findings.push(("plaintext", format!("{} {}", plaintext, i18n::tr("audit.items")), i18n::tr("audit.plaintext").to_string()));
+    }
+    if settings.get("receive.policy") == "allow" {
+        findings.push(("policy", "receive.policy".to_string(), i18n::tr("audit.policy_allow").to_string()));
+    }
+    if settings.get("receive.quarantine") == "off" {
+        findings.push(("quarantine", "receive.quarantine".to_string(), i18n::tr("audit.quarantine_off").to_string()));
+    }
+    if json {
+        let findings: Vec<serde_json::Value> = findings.iter()
+            .map(|(check, subject, detail)| serde_json::json!({ "check": check, "subject": subject, "detail": detail }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "findings": findings }))?);
+        return Ok(());
+    }
+    for (check, subject, detail) in &findings {
+        println!("{} {} {}", red!(format!("{:<12}", check)), subject, grey!(detail.clone()));
+    }
+    if findings.is_empty() {
+        eprintln!("{}", green!(i18n::tr("audit.clean").to_string()));
+    }
+    Ok(())
+}
+
+fn cmd_config(args: Vec<String>) -> Result<()> {
+    let mut settings = Settings::load()?;
+    if args.len() < 3 {
+        for setting in settings::SETTINGS {
+            println!("{} = \"{}\" {}", setting.key, settings.get(setting.key), grey!(format!("# {}", setting.description)));
+        }
+        return Ok(());
+    }
+    let key = arg!(&args, 2);
+    if args.len() < 4 {
+        settings::find(key).ok_or(anyhow!("Unknown setting."))?;
+        println!("{}", settings.get(key));
+        return Ok(());
+    }
+    let value = arg!(&args, 3);
+    settings.set(key, value)?;
+    settings.save()?;
+    audit::record("config", key, value)?;
+    Ok(())
+}
+
+fn cmd_bench(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let json = take_flag(&mut args, "--json");
+    let active_id = db.get_active_identity();
+    let from = active_id.get_private_key();
+    let to = active_id.get_public_key();
+    let mut results = Vec::new();
+
+    let rounds = 1000;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        sodiumoxide::crypto::box_::gen_keypair();
+    }
+    results.push(("box-keygen", 0, rounds, start.elapsed().as_secs_f64(), 0));
+
+    for &size in &[1usize << 10, 64 << 10, 1 << 20, 16 << 20] {
+        let data = vec![0x5a_u8; size];
+        let rounds = ((16 << 20) / size).min(1000);
+        let mut encoded = String::new();
+        let start = Instant::now();
+        for _ in 0..rounds {
+            encoded = gqg_lib::encode(
+                &from,
+                &to,
+                gqg_lib::Type::Message, gqg_lib::EncodeFlags::None,
+                &data)
+                .map_err(|err| anyhow!("GQG library: {:?}", err))?
+                .to_string();
+        }
+        results.push(("encrypt", size, rounds, start.elapsed().as_secs_f64(), encoded.len()));
+        let start = Instant::now();
+        for _ in 0..rounds {
+            gqg_lib::decode(&from, encoded.clone())
+                .map_err(|err| anyhow!("GQG library: {:?}", err))?;
+        }
+        results.push(("decrypt", size, rounds, start.elapsed().as_secs_f64(), encoded.len()));
+        let mut armored = String::new();
+        let start = Instant::now();
+        for _ in 0..rounds {
+            armored = armor::armor(&encoded);
+        }
+        results.push(("armor", size, rounds, start.elapsed().as_secs_f64(), armored.len()));
+        let start = Instant::now();
+        for _ in 0..rounds {
+            if armor::extract(&armored).is_empty() {
+                return Err(anyhow!("Armor round trip failed."));
+            }
+        }
+        results.push(("dearmor", size, rounds, start.elapsed().as_secs_f64(), armored.len()));
+    }
+
+    if json {
+        let results: Vec<_> = results.iter()
+            .map(|&(operation, size, rounds, seconds, armored)| serde_json::json!({
+                "operation": operation,
+                "payload_bytes": size,
+                "armored_bytes": armored,
+                "rounds": rounds,
+                "seconds": seconds,
+            }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+    logo();
+    println!("{:<10} {:>10} {:>12} {:>7} {:>12}", "Operation", "Payload", "Armored", "Rounds", "Throughput");
+    for &(operation, size, rounds, seconds, armored) in &results {
+        let throughput = if size == 0 {
+            format!("{:.0} /s", rounds as f64 / seconds)
+        }
+        else {
+            format!("{:.1} MiB/s", (size * rounds) as f64 / seconds / (1 << 20) as f64)
+        };
+        println!("{:<10} {:>10} {:>12} {:>7} {:>12}", operation, size, armored, rounds, throughput);
+    }
+    println!("");
+    Ok(())
+}
+
+fn cmd_whoami(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let json = take_flag(&mut args, "--json");
+    let active_id = db.get_active_identity();
+    let public_id = active_id.get_public_id();
+    let created = audit::find_last("newid", &active_id.name).map(|entry| entry.time);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+            "name": active_id.name.to_string(),
+            "public_id": public_id,
+            "fingerprint": fingerprint(&public_id),
+            "created": created,
+        }))?);
+        return Ok(());
+    }
+    println!("{:<13} {}", i18n::tr("whoami.name"), active_id.name);
+    println!("{:<13} {}", i18n::tr("whoami.public_id"), public_id);
+    println!("{:<13} {}", i18n::tr("whoami.fingerprint"), fingerprint(&public_id));
+    println!("{:<13} {}", i18n::tr("whoami.created"), created.as_deref().unwrap_or(i18n::tr("whoami.unknown")));
+    Ok(())
+}
+
+fn cmd_invite(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let note = take_option(&mut args, "--note").unwrap_or_default();
+    let reply = take_option(&mut args, "--reply");
+    let token = if take_flag(&mut args, "--token") {
+        Some(invite::new_token()?)
+    }
+    else {
+        None
+    };
+    let active_id = db.get_active_identity();
+    let blob = invite::Invite {
+        name: active_id.name.to_string(),
+        public_id: active_id.get_public_id(),
+        note,
+        token,
+        reply,
+        signer: None,
+    };
+    println!("{}", blob.encode(&signing_key(db)?)?);
+    Ok(())
+}
+
+fn cmd_accept(mut args: Vec<String>, db: &mut Database) -> Result<()> {
+    let expect = take_option(&mut args, "--expect");
+    let blob = invite::Invite::decode(arg!(&args, 2))?;
+    let signer = blob.signer.as_ref().map(minisign::key_id_hex);
+    match (&signer, &expect) {
+        (Some(signer), Some(expect)) if signer.eq_ignore_ascii_case(expect.trim()) => {
+            eprintln!("{} {}", i18n::tr("invite.signed_by"), green!(signer.clone()));
+        }
+        (_, Some(_)) => return Err(anyhow!(i18n::tr("invite.signer_mismatch"))),
+        (Some(signer), None) => {
+            eprintln!("{} {}", i18n::tr("invite.signed_by"), signer);
+            if !interact::confirm(i18n::tr("invite.confirm_signer"))? {
+                return Err(anyhow!(i18n::tr("invite.declined")));
+            }
+        }
+        (None, None) => {
+            eprintln!("{}", red!(i18n::tr("invite.unsigned").to_string()));
+            if !interact::confirm(i18n::tr("invite.confirm_unsigned"))? {
+                return Err(anyhow!(i18n::tr("invite.declined")));
+            }
+        }
+    }
+    if let Some(reply) = &blob.reply {
+        if !invite::consume_token(reply)? {
+            return Err(anyhow!(i18n::tr("invite.unknown_token")));
+        }
+        eprintln!("{}", green!(i18n::tr("invite.answered").to_string()));
+    }
+    let name = if args.len() > 3 { args[3].clone() } else { blob.name.clone() };
+    if !blob.note.is_empty() {
+        eprintln!("{} {}", i18n::tr("invite.note"), blob.note);
+    }
+    db.add_friend(name.clone(), blob.public_id.clone())?;
+    audit::record("accept", &name, &blob.public_id)?;
+    eprintln!("{} {}", name, grey!(fingerprint(&blob.public_id)));
+    if let Some(token) = &blob.token {
+        eprintln!("{} {}", i18n::tr("invite.reply_with"), token);
+    }
+    Ok(())
+}
+
+fn cmd_pair(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let active_id = db.get_active_identity();
+    let my_name = active_id.name.to_string();
+    let my_id = active_id.get_public_id();
+    let my_nonce = pair::nonce();
+    let (friend, code) = if args.len() > 2 {
+        let offer = pair::decode_party(pair::OFFER, &args[2])?;
+        eprintln!("{}", i18n::tr("pair.send_answer"));
+        println!("{}", pair::encode(pair::ANSWER, &[&my_name, &my_id, &my_nonce]));
+        let their_nonce = pair::decode_reveal(&interact::prompt_line(i18n::tr("pair.paste_reveal"))?)?;
+        if pair::commit(&offer.public_id, &their_nonce) != offer.value {
+            return Err(anyhow!(i18n::tr("pair.bad_commitment")));
+        }
+        let code = pair::short_code(&offer.public_id, &my_id, &their_nonce, &my_nonce);
+        (offer, code)
+    }
+    else {
+        eprintln!("{}", i18n::tr("pair.send_offer"));
+        println!("{}", pair::encode(pair::OFFER, &[&my_name, &my_id, &pair::commit(&my_id, &my_nonce)]));
+        let answer = pair::decode_party(pair::ANSWER, &interact::prompt_line(i18n::tr("pair.paste_answer"))?)?;
+        eprintln!("{}", i18n::tr("pair.send_reveal"));
+        println!("{}", pair::encode(pair::REVEAL, &[&my_nonce]));
+        let code = pair::short_code(&my_id, &answer.public_id, &my_nonce, &answer.value);
+        (answer, code)
+    };
+    eprintln!("{}", i18n::tr("pair.compare"));
+    eprintln!("    {}", green!(code));
+    if !interact::confirm(i18n::tr("pair.matches"))? {
+        return Err(anyhow!(i18n::tr("pair.mismatch")));
+    }
+    db.add_friend(friend.name.clone(), friend.public_id.clone())?;
+    audit::record("pair", &friend.name, &friend.public_id)?;
+    trust::set_verified(&friend.name, &friend.public_id, true)?;
+    eprintln!("{} {}", i18n::tr("pair.done"), friend.name);
+    Ok(())
+}
+
+fn cmd_alias(args: Vec<String>, db: &Database) -> Result<()> {
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let alias = arg!(&args, 3);
+    if db.find_friend(alias).is_some() {
+        return Err(anyhow!(i18n::tr("error.name_in_use")));
+    }
+    aliases::add(alias, name)?;
+    audit::record("alias", alias, name)?;
+    Ok(())
+}
+
+fn cmd_unalias(args: Vec<String>) -> Result<()> {
+    let alias = arg!(&args, 2);
+    aliases::remove(alias)?;
+    audit::record("unalias", alias, "")?;
+    Ok(())
+}
+
+fn find_plugin(action: &str) -> Option<std::path::PathBuf> {
+    let name = format!("gqg-{}{}", action, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+}
+
+fn cmd_plugin(action: &str, args: &[String], db: &Database) -> Result<()> {
+    if action.starts_with('-') || action.contains(std::path::is_separator) {
+        help();
+    }
+    let plugin = match find_plugin(action) {
+        Some(plugin) => plugin,
+        None => help(),
+    };
+    let active_id = db.get_active_identity();
+    let context = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "config": Database::config_path().to_string(),
+        "state_dir": settings::state_dir_buf().to_string_lossy().to_string(),
+        "file_dir": Database::file_path_buf().to_string_lossy().to_string(),
+        "message_dir": Database::message_path_buf().to_string_lossy().to_string(),
+        "active_identity": {
+            "name": active_id.name.to_string(),
+            "public_id": active_id.get_public_id(),
+        },
+        "batch": interact::is_batch(),
+        "gqg": std::env::current_exe().map(|exe| exe.to_string_lossy().to_string()).unwrap_or_default(),
+    });
+    let status = profile::command(&plugin)
+        .args(&args[2..])
+        .env("GQG_CONTEXT", context.to_string())
+        .env("GQG_CONFIG", Database::config_path().to_string())
+        .status()
+        .map_err(|err| anyhow!("Unable to run {}: {}", plugin.display(), err))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn dir_size(dir: &std::path::Path) -> u64 {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn cmd_stats(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let json = take_flag(&mut args, "--json");
+    let entries = history::load()?;
+    let mut per_friend: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    let mut per_month: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for entry in &entries {
+        let month = entry.time.chars().take(7).collect::<String>();
+        for counts in vec![
+            per_friend.entry(entry.peer.clone()).or_default(),
+            per_month.entry(month).or_default(),
+        ] {
+            if entry.direction == "sent" {
+                counts.0 += 1;
+            }
+            else {
+                counts.1 += 1;
+            }
+        }
+    }
+    let identities = db.get_identities().into_iter().count();
+    let friends = db.get_friends().into_iter().filter(|friend| !keyhistory::is_retired(&friend.name)).count();
+    let stored = dir_size(&Database::message_path_buf()) + dir_size(&Database::file_path_buf());
+    let oldest = entries.first().map(|entry| entry.time.clone());
+    let newest = entries.last().map(|entry| entry.time.clone());
+    if json {
+        let counts = |map: &BTreeMap<String, (u64, u64)>| -> serde_json::Value {
+            map.iter()
+                .map(|(key, (sent, received))| (key.clone(), serde_json::json!({ "sent": sent, "received": received })))
+                .collect::<serde_json::Map<_, _>>()
+                .into()
+        };
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+            "identities": identities,
+            "friends": friends,
+            "stored_bytes": stored,
+            "oldest": oldest,
+            "newest": newest,
+            "per_friend": counts(&per_friend),
+            "per_month": counts(&per_month),
+        }))?);
+        return Ok(());
+    }
+    logo();
+    println!("{:<17} {}", i18n::tr("stats.identities"), identities);
+    println!("{:<17} {}", i18n::tr("stats.friends"), friends);
+    println!("{:<17} {} bytes", i18n::tr("stats.stored"), stored);
+    println!("{:<17} {}", i18n::tr("stats.oldest"), oldest.unwrap_or_default());
+    println!("{:<17} {}", i18n::tr("stats.newest"), newest.unwrap_or_default());
+    for (title, map) in &[("stats.per_friend", &per_friend), ("stats.per_month", &per_month)] {
+        if map.is_empty() {
+            continue;
+        }
+        println!("");
+        println!("{}", i18n::tr(*title));
+        for (key, (sent, received)) in map.iter() {
+            println!("    {:<20} {:>6} / {}", key, sent, received);
+        }
+    }
+    println!("");
+    Ok(())
+}
+
+fn cmd_id(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let output = take_option(&mut args, "-o");
+    let public_id = if args.len() > 2 {
+        let name = resolve_identity(db, &args[2])?;
+        db.get_identities()
+            .into_iter()
+            .find(|id| id.name == name)
+            .map(|id| id.get_public_id())
+            .ok_or(anyhow!(i18n::tr("error.identity_not_found")))?
+    }
+    else {
+        db.get_active_identity().get_public_id()
+    };
+    match output {
+        Some(path) => {
+            fsutil::replace_private_file(std::path::Path::new(&path), format!("{}\n", public_id).as_bytes())
+                .map_err(|_| anyhow!("Unable to write {}.", path))
+        }
+        None => {
+            println!("{}", public_id);
+            Ok(())
+        }
+    }
+}
+
+fn cmd_inspect(args: Vec<String>, db: &Database) -> Result<()> {
+    let payload = if args.len() > 2 {
+        std::fs::read_to_string(&args[2]).map_err(|_| anyhow!("Unable to open file."))?
+    }
+    else {
+        read_stdin(&Settings::load()?)?
+    };
+    let (hint, body) = envelope::unwrap(&payload);
+    let compact: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let is_base64 = !compact.is_empty() && compact.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "+/=-_".contains(c));
+    let encoding = if is_base64 {
+        "base64"
+    }
+    else if compact.is_ascii() {
+        "text"
+    }
+    else {
+        "binary"
+    };
+    println!("{:<16} {} bytes", i18n::tr("inspect.size"), payload.len());
+    println!("{:<16} {}", i18n::tr("inspect.lines"), payload.lines().count());
+    let hint_text = match &hint {
+        None => i18n::tr("inspect.hint_none").to_string(),
+        Some(hint) => {
+            let owner = db.get_identities()
+                .into_iter()
+                .find(|id| &envelope::hint(&id.get_public_id()) == hint)
+                .map(|id| id.name.to_string());
+            match owner {
+                Some(owner) => format!("{} ({} {})", hint, i18n::tr("inspect.hint_mine"), owner),
+                None => format!("{} ({})", hint, i18n::tr("inspect.hint_other")),
+            }
+        }
+    };
+    println!("{:<16} {}", i18n::tr("inspect.hint"), hint_text);
+    println!("{:<16} {}", i18n::tr("inspect.encoding"), encoding);
+    println!("{:<16} {}", i18n::tr("inspect.content"), i18n::tr("inspect.content_hidden"));
+    let padded = compact.ends_with('=') || compact.contains('+') || compact.contains('/');
+    if compact.is_empty() || !is_base64 || (padded && compact.len() % 4 != 0) {
+        eprintln!("{}", red!(i18n::tr("inspect.truncated").to_string()));
+    }
+    Ok(())
+}
+
+fn cmd_inbox(mut args: Vec<String>) -> Result<()> {
+    let tags = take_tags(&mut args);
+    let flagged = take_flag(&mut args, "--flagged");
+    let entries = history::load()?;
+    let selected = entries.iter()
+        .filter(|entry| entry.direction == "received")
+        .filter(|entry| !flagged || entry.flagged)
+        .filter(|entry| tags.iter().all(|tag| entry.tags.contains(tag)));
+    for entry in selected {
+        let peer = if entry.trust == "unknown" {
+            red!(entry.peer.clone())
+        }
+        else {
+            green!(entry.peer.clone())
+        };
+        let mut location = if entry.pruned {
+            format!("{} [{}]", entry.path, i18n::tr("prune.pruned"))
+        }
+        else if entry.archive.is_empty() {
+            entry.path.clone()
+        }
+        else {
+            format!("{} [{} {}]", entry.path, i18n::tr("archive.archived_in"), entry.archive)
+        };
+        let mark = if entry.flagged { red!("!".to_string()) } else { " ".to_string() };
+        let tags = if entry.tags.is_empty() { String::new() } else { format!(" #{}", entry.tags.join(" #")) };
+        if !entry.mime_type.is_empty() {
+            location = format!("{} ({})", location, entry.mime_type);
+        }
+        println!("{:>5}{} {} {:<7} {} -> {} {}{}", entry.id, mark, entry.time, entry.kind, peer, entry.identity, grey!(location), tags);
+    }
+    Ok(())
+}
+
+fn prune(settings: &Settings, dry_run: bool) -> Result<Vec<history::Entry>> {
+    let mut stored: Vec<history::Entry> = history::load()?
+        .into_iter()
+        .filter(|entry| entry.direction == "received" && !entry.pruned && entry.archive.is_empty())
+        .collect();
+    let mut expired = Vec::new();
+    let max_age = settings.get("retention.max_age");
+    if !max_age.is_empty() {
+        let cutoff = chrono::Utc::now() - settings::parse_age(max_age).ok_or(anyhow!("Invalid age for retention.max_age."))?;
+        let (old, kept): (Vec<_>, Vec<_>) = stored.into_iter().partition(|entry| {
+            chrono::DateTime::parse_from_rfc3339(&entry.time)
+                .map(|time| time < cutoff)
+                .unwrap_or(false)
+        });
+        expired.extend(old);
+        stored = kept;
+    }
+    let max_count = settings.get("retention.max_count").parse::<usize>()
+        .map_err(|_| anyhow!("Invalid number for retention.max_count."))?;
+    if max_count > 0 && stored.len() > max_count {
+        expired.extend(stored.drain(..stored.len() - max_count));
+    }
+    let max_bytes = settings.get_size("retention.max_bytes")?;
+    let mut total: u64 = stored.iter().map(|entry| entry.bytes).sum();
+    while max_bytes > 0 && total > max_bytes && !stored.is_empty() {
+        let entry = stored.remove(0);
+        total -= entry.bytes;
+        expired.push(entry);
+    }
+    if !dry_run {
+        for entry in expired.iter_mut() {
+            match std::fs::remove_file(&entry.path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(_) => return Err(anyhow!("Unable to remove {}.", entry.path)),
+            }
+            entry.pruned = true;
+            history::update(entry)?;
+        }
+        if !expired.is_empty() {
+            audit::record("prune", &expired.len().to_string(), "")?;
+        }
+    }
+    Ok(expired)
+}
+
+fn cmd_prune(mut args: Vec<String>) -> Result<()> {
+    let dry_run = take_flag(&mut args, "--dry-run");
+    let expired = prune(&Settings::load()?, dry_run)?;
+    for entry in &expired {
+        println!("{:>5} {} {}", entry.id, entry.time, grey!(entry.path.clone()));
+    }
+    let summary = if dry_run { i18n::tr("prune.would_remove") } else { i18n::tr("prune.removed") };
+    eprintln!("{} {}", expired.len(), summary);
+    Ok(())
+}
+
+fn cmd_archive(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let age = take_option(&mut args, "--older-than").unwrap_or_else(|| help());
+    let cutoff = chrono::Utc::now() - settings::parse_age(&age).ok_or(anyhow!("Invalid age {} (use e.g. 30d, 6m, 1y).", age))?;
+    let mut entries = Vec::new();
+    let mut files = Vec::new();
+    for entry in history::load()? {
+        let old = chrono::DateTime::parse_from_rfc3339(&entry.time)
+            .map(|time| time < cutoff)
+            .unwrap_or(false);
+        if entry.direction != "received" || entry.quarantined || entry.pruned || !entry.archive.is_empty() || !old {
+            continue;
+        }
+        match std::fs::read(&entry.path) {
+            Ok(contents) => {
+                files.push((entry.id, contents));
+                entries.push(entry);
+            }
+            Err(_) => eprintln!("{}", grey!(format!("{} {}", i18n::tr("archive.missing"), entry.path))),
+        }
+    }
+    if files.is_empty() {
+        println!("{}", i18n::tr("archive.nothing"));
+        return Ok(());
+    }
+    let dir = archive::dir_buf();
+    fsutil::create_dir_private(&dir)?;
+    let name = format!("{}.gqg", chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
+    let path = dir.join(&name);
+    fsutil::write_private_file(&path, seal_to_self(db, &archive::pack(&files)?)?.as_bytes())?;
+    for mut entry in entries {
+        entry.archive = name.clone();
+        history::update(&entry)?;
+        std::fs::remove_file(&entry.path).map_err(|_| anyhow!("Unable to remove {}.", entry.path))?;
+    }
+    println!("{} {} {}", files.len(), i18n::tr("archive.done"), path.display());
+    Ok(())
+}
+
+fn cmd_unarchive(args: Vec<String>, db: &Database) -> Result<()> {
+    let id = arg!(&args, 2).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+    let name = history::find(id)?.archive;
+    if name.is_empty() {
+        return Err(anyhow!(i18n::tr("archive.not_archived")));
+    }
+    let path = archive::dir_buf().join(&name);
+    let files = archive::unpack(&unseal(db, &path)?)?;
+    let entries = history::load()?;
+    for (id, contents) in &files {
+        let mut entry = match entries.iter().find(|entry| entry.id == *id && entry.archive == name) {
+            Some(entry) => entry.clone(),
+            None => continue,
+        };
+        let destination = std::path::PathBuf::from(&entry.path);
+        if let Some(dir) = destination.parent() {
+            std::fs::create_dir_all(dir).map_err(|_| anyhow!("Unable to create {}.", dir.display()))?;
+        }
+        fsutil::write_file(&destination, contents)?;
+        entry.archive = String::new();
+        history::update(&entry)?;
+    }
+    std::fs::remove_file(&path).map_err(|_| anyhow!("Unable to remove {}.", path.display()))?;
+    println!("{} {}", files.len(), i18n::tr("archive.restored"));
+    Ok(())
+}
+
+fn stored_contents(db: &Database, entry: &history::Entry) -> Result<Vec<u8>> {
+    if !std::path::Path::new(&entry.path).exists() {
+        return Err(anyhow!(i18n::tr("verify.missing")));
+    }
+    if entry.sealed {
+        unseal(db, std::path::Path::new(&entry.path))
+    }
+    else {
+        std::fs::read(&entry.path).map_err(|_| anyhow!("Unable to read {}.", entry.path))
+    }
+}
+
+fn cmd_read(args: Vec<String>, db: &Database) -> Result<()> {
+    let id = arg!(&args, 2).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+    let data = stored_contents(db, &history::find(id)?)?;
+    std::io::stdout().write_all(&data).map_err(|_| anyhow!("Unable to write stdout."))
+}
+
+fn open_stored(db: &Database, id: u64) -> Result<()> {
+    let entry = history::find(id)?;
+    let data = stored_contents(db, &entry)?;
+    if entry.kind == "message" {
+        return interact::page(&String::from_utf8_lossy(&data));
+    }
+    if entry.mime_type.starts_with("text/") && entry.mime_type != "text/html" && std::str::from_utf8(&data).is_ok() {
+        return interact::page(&String::from_utf8_lossy(&data));
+    }
+    if let Some(reason) = mime::assess(&entry.mime_type) {
+        return Err(anyhow!("{} {}.", i18n::tr("open.dangerous"), reason));
+    }
+    let mut name = std::path::Path::new(&entry.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().trim_end_matches(".gqg").to_string())
+        .unwrap_or_default();
+    if let Some(extension) = mime::extension(&entry.mime_type).filter(|_| !mime::matches_extension(&name, &entry.mime_type)) {
+        name = format!("{}.{}", name, extension);
+    }
+    let dir = std::env::temp_dir().join(format!("gqg-view-{}",
+        sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8))));
+    fsutil::create_dir_private(&dir)?;
+    let path = dir.join(fsutil::sanitize_file_name(&name));
+    let result = fsutil::write_private_file(&path, &data)
+        .and_then(|()| uri::launch(&path))
+        .and_then(|()| interact::prompt_line(i18n::tr("open.press_enter")).map(|_| ()));
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+fn load_search_index(db: &Database, identity: &str) -> Result<Option<search::Index>> {
+    let path = search::path_buf(identity);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(search::Index::parse(&unseal(db, &path)?)?))
+}
+
+fn store_search_index(db: &Database, identity: &str, index: &search::Index) -> Result<()> {
+    fsutil::create_dir_private(&search::dir_buf())?;
+    fsutil::replace_private_file(&search::path_buf(identity), seal_to_self(db, &index.encode()?)?.as_bytes())
+}
+
+fn searchable_text(peer: &str, path: &str, kind: &str, contents: &[u8]) -> String {
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut text = format!("{} {}", peer, file_name);
+    if kind == "message" {
+        text.push(' ');
+        text.push_str(&String::from_utf8_lossy(contents));
+    }
+    text
+}
+
+fn index_received(db: &Database, id: u64, peer: &str, path: &str, kind: &str, contents: &[u8]) -> Result<()> {
+    let identity = db.get_active_identity().name.to_string();
+    let mut index = load_search_index(db, &identity)?.unwrap_or_default();
+    index.add(id, &searchable_text(peer, path, kind, contents));
+    store_search_index(db, &identity, &index)
+}
+
+fn cmd_search(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let identity = db.get_active_identity().name.to_string();
+    if take_flag(&mut args, "--rebuild") {
+        let mut index = search::Index::default();
+        let mut count = 0;
+        for entry in history::load()? {
+            if entry.direction != "received" || entry.pruned || !entry.archive.is_empty() {
+                continue;
+            }
+            match stored_contents(db, &entry) {
+                Ok(contents) => {
+                    index.add(entry.id, &searchable_text(&entry.peer, &entry.path, &entry.kind, &contents));
+                    count += 1;
+                }
+                Err(err) => eprintln!("{}", grey!(format!("{} {}: {}", i18n::tr("search.skipped"), entry.id, err))),
+            }
+        }
+        store_search_index(db, &identity, &index)?;
+        eprintln!("{} {}", count, i18n::tr("search.indexed"));
+        return Ok(());
+    }
+    let terms: Vec<String> = args.iter().skip(2).cloned().collect();
+    if terms.is_empty() {
+        help();
+    }
+    let index = load_search_index(db, &identity)?.ok_or(anyhow!(i18n::tr("search.no_index")))?;
+    let ids = index.query(&terms);
+    for entry in history::load()?.iter().filter(|entry| ids.binary_search(&entry.id).is_ok()) {
+        println!("{:>5} {} {:<7} {} {}", entry.id, entry.time, entry.kind, entry.peer, grey!(entry.path.clone()));
+    }
+    Ok(())
+}
+
+fn cmd_tag_msg(mut args: Vec<String>) -> Result<()> {
+    let remove = take_flag(&mut args, "--remove");
+    let id = arg!(&args, 2).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+    let tag = arg!(&args, 3).clone();
+    let mut entry = history::find(id)?;
+    if remove {
+        entry.tags.retain(|existing| existing != &tag);
+    }
+    else if !entry.tags.contains(&tag) {
+        entry.tags.push(tag);
+    }
+    history::update(&entry)
+}
+
+fn cmd_flag(args: Vec<String>, flagged: bool) -> Result<()> {
+    let id = arg!(&args, 2).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+    let mut entry = history::find(id)?;
+    entry.flagged = flagged;
+    history::update(&entry)
+}
+
+fn thread_items(db: &Database, peer: &str) -> Result<Vec<transcript::Item>> {
+    let mut items = Vec::new();
+    for entry in history::load()?.into_iter().filter(|entry| entry.peer == peer) {
+        let contents = if entry.direction == "sent" {
+            if entry.path.is_empty() { None } else { unseal(db, std::path::Path::new(&entry.path)).ok() }
+        }
+        else if entry.pruned || !entry.archive.is_empty() {
+            None
+        }
+        else {
+            stored_contents(db, &entry).ok()
+        };
+        let body = match (entry.kind.as_str(), contents) {
+            ("message", Some(contents)) => Some(String::from_utf8_lossy(&contents).to_string()),
+            _ => None,
+        };
+        let file = if entry.direction == "sent" { entry.file_name.clone() } else { entry.path.clone() };
+        let trust = if entry.direction == "sent" { "self".to_string() } else { entry.trust.clone() };
+        items.push(transcript::Item {
+            id: entry.id,
+            time: entry.time,
+            direction: entry.direction,
+            trust,
+            kind: entry.kind,
+            file,
+            body,
+        });
+    }
+    Ok(items)
+}
+
+fn cmd_thread(args: Vec<String>, db: &Database) -> Result<()> {
+    let peer = resolve_friend(db, arg!(&args, 2))?;
+    for item in thread_items(db, &peer)? {
+        let sent = item.direction == "sent";
+        let speaker = if sent { green!(i18n::tr("thread.me").to_string()) } else { peer.clone() };
+        let unverified = if !sent && item.trust != "verified" {
+            format!(" {}", red!(format!("[{}]", i18n::tr("thread.unverified"))))
+        }
+        else {
+            String::new()
+        };
+        println!("{} {}{} {}", grey!(item.time.clone()), speaker, unverified, grey!(format!("#{}", item.id)));
+        if item.kind == "file" {
+            println!("    {} {}", i18n::tr("thread.file"), interact::sanitize_terminal(&item.file));
+        }
+        match &item.body {
+            Some(body) => {
+                for line in body.lines().map(interact::sanitize_terminal) {
+                    if sent {
+                        println!("    {}", green!(line));
+                    }
+                    else {
+                        println!("    {}", line);
+                    }
+                }
+            }
+            None if item.kind == "message" => println!("    {}", grey!(i18n::tr("thread.unavailable").to_string())),
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+fn cmd_export_thread(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let format = take_option(&mut args, "--format").unwrap_or_else(|| "md".to_string());
+    let peer = resolve_friend(db, arg!(&args, 2))?;
+    let items = thread_items(db, &peer)?;
+    let transcript = match format.as_str() {
+        "md" => transcript::markdown(&peer, &items),
+        "html" => transcript::html(&peer, &items),
+        "json" => transcript::json(&peer, &items)?,
+        _ => return Err(anyhow!("Unknown format {}. Expected one of: md, html, json.", format)),
+    };
+    print!("{}", transcript);
+    Ok(())
+}
+
+fn cmd_verify_stored(args: Vec<String>, db: &Database) -> Result<()> {
+    let id = arg!(&args, 2).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+    let entry = history::find(id)?;
+    if entry.sha256.is_empty() {
+        return Err(anyhow!(i18n::tr("verify.unrecorded")));
+    }
+    let data = stored_contents(db, &entry)?;
+    if sha256_hex(&data) != entry.sha256 {
+        return Err(anyhow!(i18n::tr("verify.mismatch")));
+    }
+    eprintln!("{}", green!(format!("{} {}", i18n::tr("verify.ok"), entry.sha256)));
+    Ok(())
+}
+
+fn cmd_release(args: Vec<String>) -> Result<()> {
+    let id = arg!(&args, 2).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+    let mut entry = history::find(id)?;
+    if !entry.quarantined {
+        return Err(anyhow!(i18n::tr("release.not_quarantined")));
+    }
+    let destination = std::path::PathBuf::from(&entry.release_path);
+    if let Some(dir) = destination.parent() {
+        std::fs::create_dir_all(dir).map_err(|_| anyhow!("Unable to create {}.", dir.display()))?;
+    }
+    fsutil::move_file(std::path::Path::new(&entry.path), &destination)?;
+    entry.path = entry.release_path.clone();
+    entry.release_path = String::new();
+    entry.quarantined = false;
+    history::update(&entry)?;
+    println!("{}", entry.path);
+    Ok(())
+}
+
+fn cmd_roster(mut args: Vec<String>, db: &mut Database) -> Result<()> {
+    let subcommand = arg!(&args, 2).clone();
+    match subcommand.as_str() {
+        "export" => {
+            let output = Output::take(&mut args);
+            let recipient = &resolve_friend(db, arg!(&args, 3))?;
+            let mut selected = Vec::new();
+            for name in args.iter().skip(4) {
+                selected.push(resolve_friend(db, name)?);
+            }
+            let entries: Vec<roster::Entry> = db.get_friends()
+                .into_iter()
+                .filter(|friend| !keyhistory::is_retired(&friend.name))
+                .filter(|friend| selected.is_empty() || selected.contains(&friend.name))
+                .map(|friend| roster::Entry {
+                    name: friend.name.to_string(),
+                    public_id: friend.get_public_id(),
+                    verified: trust::is_verified(&friend.name, &friend.get_public_id()),
+                })
+                .collect();
+            output.emit(recipient, encrypt_message(db, recipient, roster::encode(&entries).as_bytes())?)?;
+            audit::record("roster-export", recipient, &entries.len().to_string())
+        }
+        "import" => {
+            let signer = &resolve_friend(db, &take_option(&mut args, "--signer").unwrap_or_else(|| help()))?;
+            let payload = std::fs::read_to_string(arg!(&args, 3)).map_err(|_| anyhow!("Unable to open file."))?;
+            let (hint, bodies) = payload_bodies(&payload);
+            let keys: Vec<_> = db.get_identities()
+                .into_iter()
+                .filter(|id| match &hint {
+                    Some(hint) => &envelope::hint(&id.get_public_id()) == hint,
+                    None => true,
+                })
+                .map(|id| id.get_private_key())
+                .collect();
+            let msg = trial_decode(keys, move |key| bodies.iter().find_map(|body| gqg_lib::decode(key, body.clone()).ok()))
+                .ok_or(Error::DecryptFailed)?;
+            let signed = db.find_friend_by_key(&msg.sender)
+                .map(|friend| &friend.name == signer)
+                .unwrap_or(false);
+            if !signed {
+                return Err(anyhow!(i18n::tr("roster.bad_signer")));
+            }
+            let entries = match decoded_message(msg.data)? {
+                Some(contents) => roster::decode(&contents)?,
+                None => return Err(anyhow!("Not a roster.")),
+            };
+            let signer_verified = db.find_friend(signer)
+                .map(|friend| trust::is_verified(signer, &friend.get_public_id()))
+                .unwrap_or(false);
+            for entry in entries {
+                let existing = db.find_friend(&entry.name).map(|friend| friend.get_public_id());
+                match existing {
+                    Some(public_id) if public_id == entry.public_id => {
+                        println!("{} {}", grey!(format!("{:<9}", i18n::tr("roster.unchanged"))), entry.name);
+                    }
+                    Some(_) => {
+                        println!("{} {}", red!(format!("{:<9}", i18n::tr("roster.conflict"))), entry.name);
+                        continue;
+                    }
+                    None => {
+                        db.add_friend(entry.name.clone(), entry.public_id.clone())?;
+                        audit::record("roster-import", &entry.name, &entry.public_id)?;
+                        println!("{} {}", green!(format!("{:<9}", i18n::tr("roster.added"))), entry.name);
+                    }
+                }
+                if entry.verified && signer_verified && !trust::is_verified(&entry.name, &entry.public_id) {
+                    trust::set_verified(&entry.name, &entry.public_id, true)?;
+                }
+            }
+            Ok(())
+        }
+        _ => help(),
+    }
+}
+
+fn cmd_seal(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let output = Output::take(&mut args);
+    let settings = Settings::load()?;
+    let active_id = db.get_active_identity();
+    let (kind, file_name, contents) = if args.len() > 2 {
+        let file_path = &args[2];
+        let file_name = std::path::Path::new(file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(anyhow!(i18n::tr("error.invalid_path")))?
+            .to_string();
+        let size = std::fs::metadata(file_path).map_err(|_| anyhow!("Unable to open file."))?.len();
+        let limit = settings.get_size("limits.max_input")?;
+        if size > limit {
+            return Err(Error::TooLarge(limit).into());
+        }
+        ("file", Some(file_name), fsutil::read_file(std::path::Path::new(file_path))?)
+    }
+    else {
+        let contents: Box<dyn Deref<Target = [u8]>> = Box::new(read_stdin_bytes(&settings)?);
+        ("message", None, contents)
+    };
+    let data_type = match &file_name {
+        Some(file_name) => gqg_lib::Type::File { file_name },
+        None => gqg_lib::Type::Message,
+    };
+    let msg = gqg_lib::encode(
+        &active_id.get_private_key(),
+        &active_id.get_public_key(),
+        data_type, gqg_lib::EncodeFlags::None,
+        &frame::escape(&contents[..]))
+        .map_err(|err| anyhow!("GQG library: {:?}", err))?;
+    let name = active_id.name.to_string();
+    output.emit(&name, outgoing(&active_id.get_public_id(), msg.to_string())?)?;
+    record_sent(db, &name, kind, file_name.as_deref(), &contents[..])
+}
+
+pub fn seal_to_self(db: &Database, contents: &[u8]) -> Result<String> {
+    let active_id = db.get_active_identity();
+    gqg_lib::encode(
+        &active_id.get_private_key(),
+        &active_id.get_public_key(),
+        gqg_lib::Type::Message, gqg_lib::EncodeFlags::None,
+        &frame::escape(contents))
+        .map(|msg| msg.to_string())
+        .map_err(|err| anyhow!("GQG library: {:?}", err))
+}
+
+pub fn unseal(db: &Database, path: &std::path::Path) -> Result<Vec<u8>> {
+    let payload = std::fs::read_to_string(path).map_err(|_| anyhow!("Unable to read {}.", path.display()))?;
+    let active_id = db.get_active_identity();
+    let msg = gqg_lib::decode(&active_id.get_private_key(), payload.trim().to_string())
+        .map_err(|_| Error::DecryptFailed)?;
+    if msg.sender != active_id.get_public_key() {
+        return Err(anyhow!(i18n::tr("note.not_sealed")));
+    }
+    decoded_message(msg.data)?.ok_or_else(|| anyhow!(i18n::tr("note.not_sealed")))
+}
+
+fn decoded_message(data: gqg_lib::DecodedData) -> Result<Option<Vec<u8>>> {
+    match data {
+        gqg_lib::DecodedData::Message { contents } => Ok(Some(frame::open(contents.as_ref())?.to_vec())),
+        _ => Ok(None),
+    }
+}
+
+fn load_notes(db: &Database, identity: &str) -> Result<Vec<notes::Note>> {
+    let path = notes::index_path_buf(identity);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    notes::parse_index(&unseal(db, &path)?)
+}
+
+fn store_notes(db: &Database, identity: &str, index: &[notes::Note]) -> Result<()> {
+    fsutil::replace_private_file(&notes::index_path_buf(identity), seal_to_self(db, &notes::encode_index(index)?)?.as_bytes())
+}
+
+fn take_tags(args: &mut Vec<String>) -> Vec<String> {
+    let mut tags = Vec::new();
+    while let Some(tag) = take_option(args, "--tag") {
+        tags.push(tag);
+    }
+    tags
+}
+
+fn cmd_note(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let identity = db.get_active_identity().name.to_string();
+    let subcommand = arg!(&args, 2).clone();
+    match subcommand.as_str() {
+        "add" => {
+            let tags = take_tags(&mut args);
+            let title = arg!(&args, 3).clone();
+            let body = read_stdin(&Settings::load()?)?;
+            fsutil::create_dir_private(&notes::dir_buf(&identity))?;
+            let mut index = load_notes(db, &identity)?;
+            let id = index.iter().map(|note| note.id).max().unwrap_or(0) + 1;
+            let now = chrono::Utc::now().to_rfc3339();
+            fsutil::replace_private_file(&notes::body_path_buf(&identity, id), seal_to_self(db, body.as_bytes())?.as_bytes())?;
+            index.push(notes::Note { id, title, tags, created: now.clone(), updated: now });
+            store_notes(db, &identity, &index)?;
+            println!("{}", id);
+            Ok(())
+        }
+        "list" => {
+            let tag = take_option(&mut args, "--tag");
+            for note in load_notes(db, &identity)? {
+                if tag.as_ref().map_or(true, |tag| note.tags.contains(tag)) {
+                    println!("{:>5} {} {} {}", note.id, note.updated, note.title, grey!(note.tags.join(", ")));
+                }
+            }
+            Ok(())
+        }
+        "show" => {
+            let index = load_notes(db, &identity)?;
+            let note = &index[notes::position(&index, arg!(&args, 3))?];
+            std::io::stdout().write_all(&unseal(db, &notes::body_path_buf(&identity, note.id))?)?;
+            Ok(())
+        }
+        "edit" => {
+            let title = take_option(&mut args, "--title");
+            let tags = take_tags(&mut args);
+            let mut index = load_notes(db, &identity)?;
+            let i = notes::position(&index, arg!(&args, 3))?;
+            let path = notes::body_path_buf(&identity, index[i].id);
+            let body = if std::io::stdin().is_terminal() {
+                interact::edit(&unseal(db, &path)?)?
+            }
+            else {
+                read_stdin(&Settings::load()?)?.into_bytes()
+            };
+            fsutil::replace_private_file(&path, seal_to_self(db, &body)?.as_bytes())?;
+            if let Some(title) = title {
+                index[i].title = title;
+            }
+            if !tags.is_empty() {
+                index[i].tags = tags;
+            }
+            index[i].updated = chrono::Utc::now().to_rfc3339();
+            store_notes(db, &identity, &index)
+        }
+        "rm" => {
+            let mut index = load_notes(db, &identity)?;
+            let note = index.remove(notes::position(&index, arg!(&args, 3))?);
+            store_notes(db, &identity, &index)?;
+            std::fs::remove_file(notes::body_path_buf(&identity, note.id))
+                .map_err(|_| anyhow!("Unable to remove note {}.", note.id))
+        }
+        _ => help(),
+    }
+}
+
+fn load_vault(db: &Database, identity: &str) -> Result<BTreeMap<String, String>> {
+    let path = vault::path_buf(identity);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    vault::parse(&unseal(db, &path)?)
+}
+
+fn store_vault(db: &Database, identity: &str, secrets: &BTreeMap<String, String>) -> Result<()> {
+    fsutil::create_dir_private(&vault::dir_buf())?;
+    fsutil::replace_private_file(&vault::path_buf(identity), seal_to_self(db, &vault::encode(secrets)?)?.as_bytes())
+}
+
+fn cmd_vault(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let identity = db.get_active_identity().name.to_string();
+    let clip = take_flag(&mut args, "--clip");
+    let subcommand = arg!(&args, 2).clone();
+    match subcommand.as_str() {
+        "set" => {
+            let key = arg!(&args, 3).clone();
+            let value = read_stdin(&Settings::load()?)?;
+            let value = value.strip_suffix('\n').unwrap_or(&value);
+            let value = value.strip_suffix('\r').unwrap_or(value).to_string();
+            let mut secrets = load_vault(db, &identity)?;
+            secrets.insert(key, value);
+            store_vault(db, &identity, &secrets)
+        }
+        "get" => {
+            let secrets = load_vault(db, &identity)?;
+            let value = secrets.get(arg!(&args, 3)).ok_or(anyhow!(i18n::tr("vault.not_found")))?;
+            if !clip {
+                println!("{}", value);
+                return Ok(());
+            }
+            clipboard::copy(value.as_bytes())?;
+            let seconds = Settings::load()?.get("vault.clear_after").parse::<u64>()
+                .map_err(|_| anyhow!("Invalid number for vault.clear_after."))?;
+            if seconds > 0 {
+                clipboard::clear_after(seconds)?;
+                eprintln!("{} {}s.", i18n::tr("vault.copied"), seconds);
+            }
+            Ok(())
+        }
+        "list" => {
+            for key in load_vault(db, &identity)?.keys() {
+                println!("{}", key);
+            }
+            Ok(())
+        }
+        "rm" => {
+            let mut secrets = load_vault(db, &identity)?;
+            secrets.remove(arg!(&args, 3)).ok_or(anyhow!(i18n::tr("vault.not_found")))?;
+            store_vault(db, &identity, &secrets)
+        }
+        "clear-clipboard" => {
+            let seconds = arg!(&args, 3).parse::<u64>().unwrap_or(0);
+            std::thread::sleep(std::time::Duration::from_secs(seconds));
+            clipboard::copy(b"")
+        }
+        _ => help(),
+    }
+}
+
+fn cmd_sent(args: Vec<String>, db: &Database) -> Result<()> {
+    match arg!(&args, 2).as_str() {
+        "list" => {
+            for entry in history::load()?.iter().filter(|entry| entry.direction == "sent") {
+                let archived = if entry.path.is_empty() { "" } else { i18n::tr("sent.archived") };
+                println!("{:>5} {} {:<7} {} {} {}", entry.id, entry.time, entry.kind, entry.peer, grey!(entry.file_name.clone()), archived);
+            }
+            Ok(())
+        }
+        "show" => {
+            let id = arg!(&args, 3).parse::<u64>().map_err(|_| anyhow!("Invalid entry number."))?;
+            let entry = history::find(id)?;
+            if entry.direction != "sent" || entry.path.is_empty() {
+                return Err(anyhow!(i18n::tr("sent.not_archived")));
+            }
+            std::io::stdout().write_all(&unseal(db, std::path::Path::new(&entry.path))?)?;
+            Ok(())
+        }
+        _ => help(),
+    }
+}
+
+fn mail_password(db: &Database, settings: &Settings) -> Result<String> {
+    let key = settings.get("mail.password_key");
+    load_vault(db, &db.get_active_identity().name.to_string())?
+        .remove(key)
+        .ok_or(anyhow!("{} gqg vault set {}", i18n::tr("mail.no_password"), key))
+}
+
+fn cmd_mail(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let insecure = take_flag(&mut args, "--insecure");
+    let settings = Settings::load()?;
+    match arg!(&args, 2).as_str() {
+        "send" => {
+            let name = &resolve_friend(db, arg!(&args, 3))?;
+            let address = arg!(&args, 4);
+            let contents = read_message(name)?;
+            let payload = encrypt_message(db, name, &contents)?;
+            let queued = send_queued("mail", name, address, &payload, &settings, db);
+            record_sent(db, name, "message", None, &contents)?;
+            queued
+        }
+        "fetch" => {
+            let password = mail_password(db, &settings)?;
+            let mut received = Vec::new();
+            for (uid, payloads) in mail::fetch(&settings, &password)? {
+                let mut ok = true;
+                for payload in &payloads {
+                    ok = receive_in_background(payload, insecure, &settings, db) && ok;
+                }
+                if ok && !payloads.is_empty() {
+                    received.push(uid);
+                }
+            }
+            mail::mark_seen(&settings, &password, &received)
+        }
+        _ => help(),
+    }
+}
+
+fn take_transport(args: &mut Vec<String>, supported: &[&str]) -> Result<String> {
+    let transport = take_option(args, "--via").unwrap_or_else(|| supported[0].to_string());
+    if !supported.contains(&transport.as_str()) {
+        return Err(anyhow!("{} {}.", i18n::tr("error.unknown_transport"), supported.join(", ")));
+    }
+    Ok(transport)
+}
+
+fn mqtt_password(db: &Database, settings: &Settings) -> Result<Option<String>> {
+    if settings.get("mqtt.username").is_empty() {
+        return Ok(None);
+    }
+    let key = settings.get("mqtt.password_key");
+    let password = load_vault(db, &db.get_active_identity().name.to_string())?
+        .remove(key)
+        .ok_or(anyhow!("{} gqg vault set {}", i18n::tr("mqtt.no_password"), key))?;
+    Ok(Some(password))
+}
+
+fn cmd_push(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let transport = take_transport(&mut args, &["mqtt", "nostr"])?;
+    let settings = Settings::load()?;
+    let name = &resolve_friend(db, arg!(&args, 2))?;
+    let public_id = db.find_friend(name)
+        .map(|friend| friend.get_public_id())
+        .ok_or(Error::FriendNotFound)?;
+    let contents = read_message(name)?;
+    let payload = encrypt_message(db, name, &contents)?;
+    let hint = envelope::hint(&public_id);
+    let target = if transport == "nostr" { nostr::tag(&hint) } else { mqtt::topic(&settings, &hint) };
+    let queued = send_queued(&transport, name, &target, &payload, &settings, db);
+    record_sent(db, name, "message", None, &contents)?;
+    queued
+}
+
+fn cmd_chaff(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let transport = take_transport(&mut args, &["mqtt", "nostr"])?;
+    let count = take_option(&mut args, "--count")
+        .map(|count| count.parse::<u64>().map_err(|_| anyhow!("Invalid --count value.")))
+        .transpose()?
+        .unwrap_or(0);
+    let settings = Settings::load()?;
+    syslog::enable(&settings);
+    let mean = settings.get("chaff.interval").parse::<f64>().ok()
+        .filter(|mean| *mean > 0.0)
+        .ok_or(anyhow!("Invalid number for chaff.interval."))?;
+    let names: Vec<String> = match settings.get("chaff.friends") {
+        "" => db.get_friends()
+            .into_iter()
+            .map(|friend| friend.name.to_string())
+            .filter(|name| !keyhistory::is_retired(name))
+            .collect(),
+        list => list.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| resolve_friend(db, name))
+            .collect::<Result<_>>()?,
+    };
+    if names.is_empty() {
+        return Err(anyhow!(i18n::tr("chaff.no_friends")));
+    }
+    let mut sent = 0;
+    loop {
+        let name = &names[chaff::pick(names.len())];
+        let public_id = db.find_friend(name)
+            .map(|friend| friend.get_public_id())
+            .ok_or(Error::FriendNotFound)?;
+        let hint = envelope::hint(&public_id);
+        let item = delivery::Item {
+            transport: transport.clone(),
+            recipient: name.clone(),
+            target: if transport == "nostr" { nostr::tag(&hint) } else { mqtt::topic(&settings, &hint) },
+            payload: encrypt_to(db, name, gqg_lib::Type::Message, &chaff::generate())?,
+            ..Default::default()
+        };
+        if let Err(err) = deliver(&item, &settings, db) {
+            syslog::event(syslog::WARNING, "chaff-failed", &[("transport", &item.transport), ("error", &err.to_string())]);
+            eprintln!("{}", grey!(format!("{} {}", i18n::tr("chaff.failed"), err)));
+        }
+        sent += 1;
+        if count > 0 && sent >= count {
+            return Ok(());
+        }
+        std::thread::sleep(chaff::delay(mean));
+    }
+}
+
+fn deliver(item: &delivery::Item, settings: &Settings, db: &Database) -> Result<()> {
+    match item.transport.as_str() {
+        "nostr" => {
+            let accepted = nostr::publish(settings, &item.target, &item.payload)?;
+            eprintln!("{} {}", i18n::tr("nostr.accepted"), accepted);
+            Ok(())
+        }
+        "mqtt" => mqtt::publish(settings, mqtt_password(db, settings)?, &item.target, &item.payload),
+        "mail" => mail::send(settings, &mail_password(db, settings)?, &item.target, &item.payload),
+        transport => Err(anyhow!("Unknown transport {}.", transport)),
+    }
+}
+
+fn run_queue(settings: &Settings, db: &Database, only: Option<u64>) -> Result<Vec<u64>> {
+    let base = settings.get("queue.backoff").parse::<i64>()
+        .map_err(|_| anyhow!("Invalid number for queue.backoff."))?;
+    let max_attempts = settings.get("queue.max_attempts").parse::<u32>()
+        .map_err(|_| anyhow!("Invalid number for queue.max_attempts."))?;
+    let now = chrono::Utc::now().timestamp();
+    let mut items = delivery::load()?;
+    let mut delivered = Vec::new();
+    let mut failed = Vec::new();
+    for item in items.iter_mut() {
+        let selected = match only {
+            Some(id) => item.id == id,
+            None => !item.failed && item.next_attempt <= now,
+        };
+        if !selected {
+            continue;
+        }
+        item.attempts += 1;
+        let id = item.id.to_string();
+        match deliver(item, settings, db) {
+            Ok(()) => {
+                syslog::event(syslog::INFO, "delivered", &[("id", &id), ("transport", &item.transport), ("recipient", &item.recipient)]);
+                delivered.push(item.id);
+            }
+            Err(err) => {
+                item.last_error = err.to_string();
+                item.next_attempt = now + delivery::backoff(base, item.attempts);
+                item.failed = max_attempts > 0 && item.attempts >= max_attempts;
+                syslog::event(if item.failed { syslog::ERROR } else { syslog::WARNING },
+                    if item.failed { "delivery-given-up" } else { "delivery-failed" },
+                    &[("id", &id), ("transport", &item.transport), ("recipient", &item.recipient),
+                        ("attempts", &item.attempts.to_string()), ("error", &item.last_error)]);
+                eprintln!("{}", red!(format!("{} {} ({}): {}", i18n::tr("queue.attempt_failed"), item.id, item.recipient, err)));
+                failed.push(item.id);
+            }
+        }
+    }
+    items.retain(|item| !delivered.contains(&item.id));
+    delivery::store(&items)?;
+    Ok(failed)
+}
+
+fn send_queued(transport: &str, recipient: &str, target: &str, payload: &str, settings: &Settings, db: &Database) -> Result<()> {
+    let id = delivery::add(transport, recipient, target, payload)?;
+    if run_queue(settings, db, None)?.contains(&id) {
+        return Err(anyhow!("{} {}.", i18n::tr("queue.queued"), id));
+    }
+    Ok(())
+}
+
+fn cmd_subscribe(mut args: Vec<String>, db: &Database) -> Result<()> {
+    take_transport(&mut args, &["mqtt"])?;
+    let insecure = take_flag(&mut args, "--insecure");
+    let settings = Settings::load()?;
+    syslog::enable(&settings);
+    notify::set_long_running(true);
+    let hints: Vec<String> = db.get_identities()
+        .into_iter()
+        .map(|id| envelope::hint(&id.get_public_id()))
+        .collect();
+    let topics: Vec<String> = hints.iter().map(|hint| mqtt::topic(&settings, hint)).collect();
+    let client_id = format!("gqg-{}", hints.join("").chars().take(16).collect::<String>());
+    for topic in &topics {
+        eprintln!("{} {}", i18n::tr("mqtt.subscribed"), topic);
+    }
+    mqtt::subscribe(&settings, mqtt_password(db, &settings)?, &client_id, &topics, |payload| {
+        receive_in_background(&payload, insecure, &settings, db);
+    })
+}
+
+fn pull_local(dir: &std::path::Path, insecure: bool, settings: &Settings, db: &Database) -> Result<()> {
+    let paths = dropbox::list(dir)?;
+    let mut failed = 0;
+    for path in &paths {
+        let contents = std::fs::read_to_string(path).map_err(|_| anyhow!("Unable to read {}.", path.display()))?;
+        let blocks = if armor::is_armored(&contents) { armor::extract(&contents) } else { vec![contents] };
+        let mut received = !blocks.is_empty();
+        for block in &blocks {
+            match receive_payload(block, insecure, settings, db) {
+                Ok(arrival) => notify::arrival(settings, &arrival),
+                Err(err) => {
+                    received = false;
+                    eprintln!("{}", red!(format!("{} {}: {}", i18n::tr("error"), path.display(), err)));
+                }
+            }
+        }
+        if received {
+            std::fs::remove_file(path).map_err(|_| anyhow!("Unable to remove {}.", path.display()))?;
+        }
+        else {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} / {} {}", failed, paths.len(), i18n::tr("dropbox.kept")));
+    }
+    Ok(())
+}
+
+fn cmd_pull(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let local = take_flag(&mut args, "--local");
+    let dir = take_option(&mut args, "--dir");
+    let insecure = take_flag(&mut args, "--insecure");
+    let settings = Settings::load()?;
+    if local {
+        let dir = dir.map(std::path::PathBuf::from).unwrap_or_else(|| dropbox::dir_buf(&settings));
+        return pull_local(&dir, insecure, &settings, db);
+    }
+    take_transport(&mut args, &["nostr"])?;
+    let tags: Vec<String> = db.get_identities()
+        .into_iter()
+        .map(|id| nostr::tag(&envelope::hint(&id.get_public_id())))
+        .collect();
+    for (id, payload) in nostr::fetch(&settings, &tags)? {
+        if receive_in_background(&payload, insecure, &settings, db) {
+            nostr::mark_seen(&id)?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_open(mut args: Vec<String>, db: &mut Database) -> Result<()> {
+    let from = take_option(&mut args, "--from");
+    if args.len() <= 2 || from.is_some() {
+        let peer = from.map(|name| resolve_friend(db, &name)).transpose()?;
+        let latest = history::load()?
+            .into_iter()
+            .rev()
+            .find(|entry| {
+                entry.direction == "received" && entry.kind == "message" && !entry.pruned && entry.archive.is_empty()
+                    && peer.as_ref().map(|peer| &entry.peer == peer).unwrap_or(true)
+            })
+            .ok_or(anyhow!(i18n::tr("open.no_message")))?;
+        return open_stored(db, latest.id);
+    }
+    if let Ok(id) = arg!(&args, 2).parse::<u64>() {
+        return open_stored(db, id);
+    }
+    let (action, params) = uri::parse(arg!(&args, 2))?;
+    let param = |key: &str| params.get(key).cloned().ok_or(anyhow!("{} {}", i18n::tr("open.missing"), key));
+    match action.as_str() {
+        "befriend" => {
+            let name = param("name")?;
+            let key = param("key")?;
+            eprintln!("{} {}", name, grey!(fingerprint(&key)));
+            if !interact::confirm(i18n::tr("open.confirm_befriend"))? {
+                return Ok(());
+            }
+            db.add_friend(name.clone(), key.clone())?;
+            audit::record("befriend", &name, &key)
+        }
+        "msg" => {
+            let settings = Settings::load()?;
+            receive_payload(&param("payload")?, false, &settings, db)?;
+            Ok(())
+        }
+        _ => Err(anyhow!(i18n::tr("open.unknown"))),
+    }
+}
+
+fn cmd_register_handler() -> Result<()> {
+    let exe = std::env::current_exe().map_err(|_| anyhow!("Unable to find the gqg executable."))?;
+    uri::register(&exe)?;
+    eprintln!("{}", green!(i18n::tr("open.registered").to_string()));
+    Ok(())
+}
+
+fn cmd_discover(args: Vec<String>, db: &mut Database) -> Result<()> {
+    let address = arg!(&args, 2);
+    let (user, domain) = discovery::split_address(address)?;
+    let name = if args.len() > 3 { args[3].clone() } else { user.to_string() };
+    let https = discovery::lookup_well_known(user, domain);
+    let dns = discovery::lookup_dns(user, domain);
+    let dns_sigkey = dns.as_ref().and_then(|dns| dns.sigkey.clone());
+    let record = match (https, dns) {
+        (Some(https), Some(dns)) if https.id != dns.id => {
+            return Err(anyhow!(i18n::tr("discover.conflict")));
+        }
+        (Some(record), dns) => {
+            let source = if dns.is_some() { "discover.both" } else { "discover.https" };
+            eprintln!("{}", i18n::tr(source));
+            record
+        }
+        (None, Some(record)) => {
+            eprintln!("{}", red!(i18n::tr("discover.dns_only").to_string()));
+            record
+        }
+        (None, None) => {
+            return Err(anyhow!(i18n::tr("discover.not_found")));
+        }
+    };
+    let pinned = discovery::pinned(address)?;
+    let signer = record.published.as_ref().and(record.sigkey.clone());
+    match (&signer, &pinned, &dns_sigkey) {
+        (Some(signer), Some(pinned), _) if signer != pinned => {
+            return Err(anyhow!(i18n::tr("discover.sigkey_changed")));
+        }
+        (None, Some(_), _) => {
+            return Err(anyhow!(i18n::tr("discover.unsigned_pinned")));
+        }
+        (Some(signer), _, Some(dns_sigkey)) if signer != dns_sigkey => {
+            return Err(anyhow!(i18n::tr("discover.sigkey_conflict")));
+        }
+        (Some(_), pinned, dns_sigkey) => {
+            let status = if pinned.is_some() {
+                "discover.signed_pinned"
+            }
+            else if dns_sigkey.is_some() {
+                "discover.signed_dns"
+            }
+            else {
+                "discover.signed_new"
+            };
+            eprintln!("{} {}", i18n::tr(status), record.published.as_deref().unwrap_or_default());
+        }
+        (None, None, _) => {}
+    }
+    let public_id = record.id;
+    eprintln!("{} {}", name, grey!(fingerprint(&public_id)));
+    if !interact::confirm(i18n::tr("open.confirm_befriend"))? {
+        return Ok(());
+    }
+    db.add_friend(name.clone(), public_id.clone())?;
+    if let Some(signer) = &signer {
+        discovery::pin(address, signer)?;
+    }
+    audit::record("discover", &name, &format!("{} {}", address, public_id))
+}
+
+fn signing_key(db: &Database) -> Result<sodiumoxide::crypto::sign::SecretKey> {
+    let path = signing::path_buf(&db.get_active_identity().name.to_string());
+    if path.exists() {
+        return sodiumoxide::crypto::sign::SecretKey::from_slice(&unseal(db, &path)?)
+            .ok_or(anyhow!("Signing key is corrupted."));
+    }
+    let (_, secret_key) = sodiumoxide::crypto::sign::gen_keypair();
+    fsutil::create_dir_private(path.parent().unwrap())?;
+    fsutil::write_private_file(&path, seal_to_self(db, &secret_key.0)?.as_bytes())?;
+    Ok(secret_key)
+}
+
+fn cmd_signkey(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let format = take_option(&mut args, "--format").unwrap_or_else(|| "minisign".to_string());
+    let public_key = signing::public_key(&signing_key(db)?).ok_or(anyhow!("Signing key is corrupted."))?;
+    print!("{}", minisign::encode_public(&format, &public_key)?);
+    Ok(())
+}
+
+fn cmd_signfile(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let format = take_option(&mut args, "--format").unwrap_or_else(|| "minisign".to_string());
+    let output = take_option(&mut args, "-o").or_else(|| take_option(&mut args, "--output"));
+    let path = std::path::Path::new(arg!(&args, 2));
+    let output = match output {
+        Some(output) => std::path::PathBuf::from(output),
+        None => std::path::PathBuf::from(format!("{}.{}", path.display(), minisign::extension(&format)?)),
+    };
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let signature = minisign::sign(&format, &file_name, &fsutil::read_file(path)?[..], &signing_key(db)?)?;
+    fsutil::write_file(&output, signature.as_bytes())?;
+    audit::record("signfile", &file_name, &format)?;
+    eprintln!("{}", output.display());
+    Ok(())
+}
+
+fn cmd_verifyfile(mut args: Vec<String>) -> Result<()> {
+    let key = take_option(&mut args, "--key").unwrap_or_else(|| help());
+    let signature = take_option(&mut args, "--sig");
+    let path = arg!(&args, 2);
+    let signature = match signature {
+        Some(signature) => signature,
+        None => ["minisig", "sig"].iter()
+            .map(|extension| format!("{}.{}", path, extension))
+            .find(|candidate| std::path::Path::new(candidate).exists())
+            .ok_or(anyhow!(i18n::tr("signfile.no_signature")))?,
+    };
+    let key = std::fs::read_to_string(&key).unwrap_or(key);
+    let signature = std::fs::read_to_string(&signature).map_err(|_| anyhow!("Unable to read {}.", signature))?;
+    let trusted = minisign::verify(&signature, &fsutil::read_file(std::path::Path::new(path))?[..], &key)?;
+    match trusted {
+        Some(trusted) => println!("{} {}", green!(i18n::tr("signfile.good").to_string()), grey!(trusted)),
+        None => println!("{}", green!(i18n::tr("signfile.good").to_string())),
+    }
+    Ok(())
+}
+
+fn cmd_publish(mut args: Vec<String>, db: &Database) -> Result<()> {
+    if take_option(&mut args, "--format").as_deref() != Some("well-known") {
+        help();
+    }
+    let active_id = db.get_active_identity();
+    let user = if args.len() > 2 { args[2].clone() } else { active_id.name.to_string() };
+    let public_id = active_id.get_public_id();
+    let secret_key = signing_key(db)?;
+    let sigkey = signing::encode_public(&signing::public_key(&secret_key).ok_or(anyhow!("Signing key is corrupted."))?);
+    let published = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let sig = signing::sign(discovery::signed_text(&public_id, &published, &sigkey).as_bytes(), &secret_key);
+    println!("# {} .well-known/gqg/{}", i18n::tr("publish.file"), user);
+    println!("v={}", discovery::VERSION);
+    println!("id={}", public_id);
+    println!("published={}", published);
+    println!("sigkey={}", sigkey);
+    println!("sig={}", sig);
+    println!();
+    println!("# {} {} TXT", i18n::tr("publish.dns"), discovery::dns_name(&user, "<domain>."));
+    println!("\"v={}; id={}; sigkey={}\"", discovery::VERSION, public_id, sigkey);
+    Ok(())
+}
+
+fn cmd_provision(mut args: Vec<String>, db: &mut Database) -> Result<()> {
+    let bundles = take_option(&mut args, "--bundles");
+    let trust_verified = take_flag(&mut args, "--trust-verified");
+    let text = std::fs::read_to_string(arg!(&args, 2)).map_err(|_| anyhow!("Unable to open file."))?;
+    let rows = roster::from_csv(&text)?;
+    if let Some(row) = rows.iter().find(|row| row.public_id.is_empty()) {
+        return Err(anyhow!("{} {}", i18n::tr("provision.missing_id"), row.name));
+    }
+    let verified_rows = rows.iter().filter(|row| row.verified).count();
+    let trust_verified = if verified_rows == 0 {
+        false
+    }
+    else if !trust_verified {
+        eprintln!("{}", grey!(format!("{} {}", verified_rows, i18n::tr("provision.verified_ignored"))));
+        false
+    }
+    else {
+        interact::confirm(&format!("{} {}", verified_rows, i18n::tr("provision.confirm_verified")))?
+    };
+    let mut contacts = Vec::new();
+    for row in &rows {
+        let existing = db.find_friend(&row.name).map(|friend| friend.get_public_id());
+        match existing {
+            Some(public_id) if public_id == row.public_id => {
+                println!("{} {}", grey!(format!("{:<9}", i18n::tr("roster.unchanged"))), row.name);
+            }
+            Some(_) => {
+                println!("{} {}", red!(format!("{:<9}", i18n::tr("roster.conflict"))), row.name);
+                continue;
+            }
+            None => {
+                db.add_friend(row.name.clone(), row.public_id.clone())?;
+                audit::record("provision", &row.name, &row.public_id)?;
+                println!("{} {}", green!(format!("{:<9}", i18n::tr("roster.added"))), row.name);
+            }
+        }
+        if row.verified && trust_verified && !trust::is_verified(&row.name, &row.public_id) {
+            trust::set_verified(&row.name, &row.public_id, true)?;
+        }
+        contacts.push(row.clone());
+    }
+    if let Some(dir) = bundles {
+        let dir = std::path::PathBuf::from(dir);
+        std::fs::create_dir_all(&dir).map_err(|_| anyhow!("Unable to create {}.", dir.display()))?;
+        for member in &contacts {
+            let entries: Vec<roster::Entry> = contacts.iter()
+                .filter(|contact| contact.name != member.name)
+                .cloned()
+                .collect();
+            let path = dir.join(format!("{}.gqg", fsutil::sanitize_file_name(&member.name)));
+            let payload = encrypt_message(db, &member.name, roster::encode(&entries).as_bytes())?;
+            fsutil::write_private_file(&path, format!("{}\n", payload).as_bytes())?;
+            eprintln!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn api_list(db: &Database) -> serde_json::Value {
+    let active = db.get_active_identity().name.to_string();
+    let identities: Vec<serde_json::Value> = db.get_identities()
+        .into_iter()
+        .map(|id| serde_json::json!({
+            "name": id.name.to_string(),
+            "id": id.get_public_id(),
+            "active": id.name.to_string() == active,
+        }))
+        .collect();
+    let friends: Vec<serde_json::Value> = db.get_friends()
+        .into_iter()
+        .filter(|friend| !keyhistory::is_retired(&friend.name))
+        .map(|friend| serde_json::json!({
+            "name": friend.name.to_string(),
+            "id": friend.get_public_id(),
+            "verified": trust::is_verified(&friend.name, &friend.get_public_id()),
+        }))
+        .collect();
+    serde_json::json!({ "identities": identities, "friends": friends })
+}
+
+fn api_route(method: &str, url: &str, body: &str, settings: &Settings, db: &Database) -> Result<(u16, serde_json::Value)> {
+    let path = url.split('?').next().unwrap_or("");
+    match (method, path) {
+        ("GET", "/list") => Ok((200, api_list(db))),
+        ("GET", "/inbox") => {
+            let entries: Vec<history::Entry> = history::load()?
+                .into_iter()
+                .filter(|entry| entry.direction == "received")
+                .collect();
+            Ok((200, serde_json::to_value(entries)?))
+        }
+        ("POST", "/receive") => {
+            Ok((200, serde_json::to_value(receive_payload(body, false, settings, db)?)?))
+        }
+        ("POST", _) if path.starts_with("/send/") => {
+            let name = &resolve_friend(db, &path["/send/".len()..])?;
+            let mut contents = body.as_bytes().to_vec();
+            if let Some(edited) = hooks::before_send(name, "message", None, &contents)? {
+                contents = edited;
+            }
+            let payload = encrypt_message(db, name, &contents)?;
+            record_sent(db, name, "message", None, &contents)?;
+            Ok((200, serde_json::json!({ "payload": payload })))
+        }
+        _ => Ok((404, serde_json::json!({ "error": "Not found." }))),
+    }
+}
+
+fn listen(settings: &Settings, listen: &str) -> Result<tiny_http::Server> {
+    let server = match (settings.get("serve.tls_cert"), settings.get("serve.tls_key")) {
+        ("", _) | (_, "") => tiny_http::Server::http(listen),
+        (cert, key) => tiny_http::Server::https(listen, tiny_http::SslConfig {
+            certificate: std::fs::read(cert).map_err(|_| anyhow!("Unable to read {}.", cert))?,
+            private_key: std::fs::read(key).map_err(|_| anyhow!("Unable to read {}.", key))?,
+        }),
+    }
+        .map_err(|err| anyhow!("Unable to listen on {}: {}", listen, err))?;
+    eprintln!("{} {}", i18n::tr("serve.listening"), listen);
+    Ok(server)
+}
+
+fn serve_dropbox(server: tiny_http::Server, settings: &Settings) -> Result<()> {
+    let limit = settings.get_size("limits.max_input")?;
+    let max_count = settings.get("serve.dropbox_max").parse::<usize>()
+        .map_err(|_| anyhow!("Invalid number for serve.dropbox_max."))?;
+    let dir = dropbox::dir_buf(settings);
+    eprintln!("{} {}", i18n::tr("dropbox.storing"), dir.display());
+    let json = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    for mut request in server.incoming_requests() {
+        let (status, value) = if *request.method() != tiny_http::Method::Post || request.url() != "/drop" {
+            (404, serde_json::json!({ "error": "Not found." }))
+        }
+        else {
+            let mut body = Vec::new();
+            let read = request.as_reader().take(limit.saturating_add(1)).read_to_end(&mut body);
+            let body = String::from_utf8(body).unwrap_or_default();
+            if read.is_err() || body.len() as u64 > limit {
+                (413, serde_json::json!({ "error": "Request too large." }))
+            }
+            else if !dropbox::looks_like_payload(&body) {
+                (400, serde_json::json!({ "error": "Not a gqg payload." }))
+            }
+            else {
+                match dropbox::store(&dir, &body, max_count) {
+                    Ok(_) => (201, serde_json::json!({ "stored": true })),
+                    Err(err) => (507, serde_json::json!({ "error": err.to_string() })),
+                }
+            }
+        };
+        syslog::event(if status < 400 { syslog::INFO } else { syslog::WARNING }, "dropbox",
+            &[("method", request.method().as_str()), ("url", request.url()), ("status", &status.to_string())]);
+        let response = tiny_http::Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(json.clone());
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+fn cmd_serve(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let address = take_option(&mut args, "--listen").unwrap_or_else(|| "127.0.0.1:7878".to_string());
+    let settings = Settings::load()?;
+    syslog::enable(&settings);
+    if take_flag(&mut args, "--dropbox") {
+        return serve_dropbox(listen(&settings, &address)?, &settings);
+    }
+    let new_token = take_flag(&mut args, "--new-token");
+    let token = match api::token()? {
+        Some(token) if !new_token => token,
+        _ => {
+            let token = api::new_token()?;
+            eprintln!("{} {}", i18n::tr("serve.token"), token);
+            token
+        }
+    };
+    let limit = settings.get_size("limits.max_input")?;
+    let server = listen(&settings, &address)?;
+    let json = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    for mut request in server.incoming_requests() {
+        let authorization = request.headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .map(|header| header.value.as_str().to_string());
+        let (status, value) = if !api::authorized(authorization.as_deref(), &token) {
+            (401, serde_json::json!({ "error": "Unauthorized." }))
+        }
+        else {
+            let mut body = Vec::new();
+            let read = request.as_reader().take(limit.saturating_add(1)).read_to_end(&mut body);
+            let method = request.method().as_str().to_string();
+            let url = request.url().to_string();
+            if read.is_err() || body.len() as u64 > limit {
+                (413, serde_json::json!({ "error": "Request too large." }))
+            }
+            else {
+                match api_route(&method, &url, &String::from_utf8_lossy(&body), &settings, db) {
+                    Ok(result) => result,
+                    Err(err) => (400, serde_json::json!({ "error": err.to_string() })),
+                }
+            }
+        };
+        syslog::event(if status < 400 { syslog::INFO } else { syslog::WARNING }, "request",
+            &[("method", request.method().as_str()), ("url", request.url()), ("status", &status.to_string())]);
+        let response = tiny_http::Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(json.clone());
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+fn selftest_kat() -> Vec<(&'static str, Result<()>)> {
+    let check = |actual: String, expected: &str| -> Result<()> {
+        if actual == expected { Ok(()) } else { Err(anyhow!("got {}", actual)) }
+    };
+    let mut results = Vec::new();
+    results.push(("sha256", check(sha256_hex(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")));
+    let scalar = sodiumoxide::hex::decode("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a").unwrap_or_default();
+    let x25519 = sodiumoxide::crypto::scalarmult::curve25519::Scalar::from_slice(&scalar)
+        .ok_or(anyhow!("bad vector"))
+        .and_then(|scalar| check(
+            sodiumoxide::hex::encode(sodiumoxide::crypto::scalarmult::curve25519::scalarmult_base(&scalar).0),
+            "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"));
+    results.push(("x25519", x25519));
+    let seed = sodiumoxide::hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap_or_default();
+    let ed25519 = sodiumoxide::crypto::sign::Seed::from_slice(&seed)
+        .ok_or(anyhow!("bad vector"))
+        .and_then(|seed| {
+            let (public_key, secret_key) = sodiumoxide::crypto::sign::keypair_from_seed(&seed);
+            check(sodiumoxide::hex::encode(public_key.0),
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")?;
+            check(sodiumoxide::hex::encode(sodiumoxide::crypto::sign::sign(b"", &secret_key)),
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b")
+        });
+    results.push(("ed25519", ed25519));
+    results
+}
+
+fn selftest_round_trip() -> Result<()> {
+    let mut db = Database::load();
+    db.add_identity("alice".to_string())?;
+    db.add_identity("bob".to_string())?;
+    let ids: Vec<(String, String)> = db.get_identities()
+        .into_iter()
+        .map(|id| (id.name.to_string(), id.get_public_id()))
+        .collect();
+    for (name, public_id) in &ids {
+        db.add_friend(name.clone(), public_id.clone())?;
+    }
+    let plaintext = sodiumoxide::randombytes::randombytes(4096);
+    let padded = padding::pad("padme", &frame::escape(&plaintext))?.ok_or(anyhow!("padding failed"))?;
+    let alice = db.get_identities().into_iter().find(|id| id.name == "alice").ok_or(anyhow!("keygen failed"))?;
+    let bob = db.get_identities().into_iter().find(|id| id.name == "bob").ok_or(anyhow!("keygen failed"))?;
+    let to = db.find_friend(&"bob".to_string()).ok_or(anyhow!("befriend failed"))?.get_public_key();
+    let encoded = gqg_lib::encode(
+        &alice.get_private_key(),
+        &to,
+        gqg_lib::Type::Message, gqg_lib::EncodeFlags::None,
+        &padded)
+        .map_err(|err| anyhow!("encrypt: {:?}", err))?
+        .to_string();
+    let msg = gqg_lib::decode(&bob.get_private_key(), encoded.clone()).map_err(|err| anyhow!("decrypt: {:?}", err))?;
+    if db.find_friend_by_key(&msg.sender).map(|friend| friend.name.to_string()) != Some("alice".to_string()) {
+        return Err(anyhow!("sender mismatch"));
+    }
+    match decoded_message(msg.data)? {
+        Some(contents) if contents == plaintext => {}
+        Some(_) => return Err(anyhow!("plaintext mismatch")),
+        None => return Err(anyhow!("wrong payload type")),
+    }
+    if gqg_lib::decode(&alice.get_private_key(), encoded).is_ok() {
+        return Err(anyhow!("decrypted with the wrong key"));
+    }
+    Ok(())
+}
+
+fn cmd_selftest() -> Result<()> {
+    let mut results = selftest_kat();
+    if cfg!(target_os = "windows") {
+        println!("{:<12} {}", "round-trip", grey!(i18n::tr("selftest.skipped").to_string()));
+    }
+    else if fsutil::is_read_only() {
+        println!("{:<12} {}", "round-trip", grey!(i18n::tr("selftest.skipped_read_only").to_string()));
+    }
+    else {
+        let scratch = std::env::temp_dir().join(format!("gqg-selftest-{}",
+            sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8))));
+        fsutil::create_dir_private(&scratch)?;
+        let overrides = [
+            ("HOME", scratch.clone()),
+            ("XDG_CONFIG_HOME", scratch.join("config")),
+            ("XDG_DATA_HOME", scratch.join("data")),
+        ];
+        let result = profile::with_overrides(&overrides, || {
+            if std::path::Path::new(&Database::config_path()).starts_with(&scratch) {
+                selftest_round_trip()
+            }
+            else {
+                Err(anyhow!(i18n::tr("selftest.not_isolated")))
+            }
+        });
+        results.push(("round-trip", result));
+        let _ = std::fs::remove_dir_all(&scratch);
+    }
+    let mut failed = 0;
+    for (stage, result) in &results {
+        match result {
+            Ok(()) => println!("{:<12} {}", stage, green!(i18n::tr("selftest.pass").to_string())),
+            Err(err) => {
+                failed += 1;
+                println!("{:<12} {} {}", stage, red!(i18n::tr("selftest.fail").to_string()), err);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} {}", failed, i18n::tr("selftest.failed")));
+    }
+    Ok(())
+}
+
+fn cmd_debug_decode(mut args: Vec<String>, db: &Database) -> Result<()> {
+    let settings = Settings::load()?;
+    let mut payload = match take_option(&mut args, "--volumes") {
+        Some(first) => volumes::join(std::path::Path::new(&first))?,
+        None => read_stdin(&settings)?,
+    };
+    let stage = |name: &str, ok: bool, detail: String| {
+        let status = if ok { green!(i18n::tr("selftest.pass").to_string()) } else { red!(i18n::tr("selftest.fail").to_string()) };
+        println!("{:<10} {} {}", name, status, detail);
+    };
+    stage("input", !payload.trim().is_empty(), format!("{} bytes", payload.len()));
+    if armor::is_armored(&payload) {
+        match armor::extract(&payload).into_iter().next() {
+            Some(inner) => {
+                stage("armor", true, i18n::tr("debug.armor_stripped").to_string());
+                payload = inner;
+            }
+            None => {
+                stage("armor", false, i18n::tr("debug.armor_truncated").to_string());
+            }
+        }
+    }
+    let (hint, body) = envelope::unwrap(&payload);
+    let body = body.trim().to_string();
+    match body.char_indices().find(|(_, c)| !c.is_ascii_graphic() && !c.is_ascii_whitespace()) {
+        Some((offset, c)) => stage("charset", false, format!("{} {:?} @ {}", i18n::tr("debug.bad_char"), c, offset)),
+        None => stage("charset", true, String::new()),
+    }
+    let identities = db.get_identities();
+    let hinted: Vec<String> = identities.iter()
+        .filter(|id| hint.as_ref().map(|hint| &envelope::hint(&id.get_public_id()) == hint).unwrap_or(true))
+        .map(|id| id.name.to_string())
+        .collect();
+    match &hint {
+        None => stage("hint", true, i18n::tr("debug.no_hint").to_string()),
+        Some(hint) if hinted.is_empty() => stage("hint", false, format!("{} {}", hint, i18n::tr("debug.hint_unmatched"))),
+        Some(hint) => stage("hint", true, format!("{} -> {}", hint, hinted.join(", "))),
+    }
+    let mut decoded = None;
+    for id in identities.iter() {
+        match gqg_lib::decode(&id.get_private_key(), body.clone()) {
+            Ok(msg) => {
+                stage("decrypt", true, id.name.to_string());
+                decoded = Some(msg);
+                break;
+            }
+            Err(err) => stage("decrypt", false, format!("{}: {:?}", id.name, err)),
+        }
+    }
+    let msg = decoded.ok_or(Error::DecryptFailed)?;
+    let sender = match db.find_friend_by_key(&msg.sender) {
+        Some(friend) => match keyhistory::owner(&friend.name) {
+            Some(owner) => format!("{} ({})", owner, i18n::tr("receive.previous_key")),
+            None => friend.name.to_string(),
+        },
+        None => match identities.iter().find(|id| id.get_public_key() == msg.sender) {
+            Some(id) => id.name.to_string(),
+            None => i18n::tr("debug.unknown_sender").to_string(),
+        },
+    };
+    stage("sender", true, sender);
+    let (label, contents) = match msg.data {
+        gqg_lib::DecodedData::Message { contents } => {
+            let contents: &[u8] = contents.as_ref();
+            ("message".to_string(), contents.to_vec())
+        }
+        gqg_lib::DecodedData::File { file_name, contents } => {
+            let contents: &[u8] = contents.as_ref();
+            (format!("file {}", file_name), contents.to_vec())
+        }
+    };
+    let stripped = match padding::strip(&contents) {
+        Ok(stripped) => {
+            stage("padding", true, format!("{} -> {} bytes", contents.len(), stripped.len()));
+            stripped
+        }
+        Err(err) => {
+            stage("padding", false, err.to_string());
+            &contents[..]
+        }
+    };
+    stage("payload", true, format!("{}, {} bytes", label, stripped.len()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_bodies_find_the_hint_under_armor() {
+        let hint = envelope::hint("alice-public-id");
+        let wrapped = envelope::wrap(Some(hint.clone()), "AAAABBBBCCCCDDDD".to_string());
+        for payload in [wrapped.clone(), armor::armor(&wrapped), armor::armor(&wrapped).replace('\n', "\r\n")] {
+            let (found, bodies) = payload_bodies(&payload);
+            assert_eq!(found.as_deref(), Some(hint.as_str()), "{}", payload);
+            assert!(bodies.contains(&"AAAABBBBCCCCDDDD".to_string()), "{:?}", bodies);
+        }
+        let (found, bodies) = payload_bodies("AAAABBBBCCCCDDDD");
+        assert_eq!(found, None);
+        assert_eq!(bodies, vec!["AAAABBBBCCCCDDDD".to_string()]);
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use lettre::Transport;
+use crate::armor;
+use crate::settings::Settings;
+
+fn username(settings: &Settings) -> String {
+    match settings.get("mail.username") {
+        "" => settings.get("mail.from").to_string(),
+        username => username.to_string(),
+    }
+}
+
+fn port(settings: &Settings, key: &str) -> Result<u16> {
+    settings.get(key).parse::<u16>().map_err(|_| anyhow!("Invalid port for {}.", key))
+}
+
+fn required<'a>(settings: &'a Settings, key: &str) -> Result<&'a str> {
+    match settings.get(key) {
+        "" => Err(anyhow!("Set {} first (gqg config {} <value>).", key, key)),
+        value => Ok(value),
+    }
+}
+
+pub fn send(settings: &Settings, password: &str, to: &str, payload: &str) -> Result<()> {
+    let from = required(settings, "mail.from")?;
+    let email = lettre::Message::builder()
+        .from(from.parse().map_err(|_| anyhow!("Invalid address in mail.from."))?)
+        .to(to.parse().map_err(|_| anyhow!("Invalid address {}.", to))?)
+        .subject("gqg")
+        .header(lettre::message::header::ContentType::TEXT_PLAIN)
+        .body(if armor::is_armored(payload) { payload.to_string() } else { armor::armor(payload) })
+        .map_err(|err| anyhow!("Unable to build mail: {}", err))?;
+    let credentials = lettre::transport::smtp::authentication::Credentials::new(username(settings), password.to_string());
+    let mailer = lettre::SmtpTransport::starttls_relay(required(settings, "mail.smtp_host")?)
+        .map_err(|err| anyhow!("SMTP: {}", err))?
+        .port(port(settings, "mail.smtp_port")?)
+        .credentials(credentials)
+        .build();
+    mailer.send(&email).map_err(|err| anyhow!("SMTP: {}", err))?;
+    Ok(())
+}
+
+fn collect_text(part: &mailparse::ParsedMail, text: &mut String) {
+    if part.subparts.is_empty() {
+        if let Ok(body) = part.get_body() {
+            text.push_str(&body);
+            text.push('\n');
+        }
+    }
+    for subpart in &part.subparts {
+        collect_text(subpart, text);
+    }
+}
+
+type Session = imap::Session<native_tls::TlsStream<std::net::TcpStream>>;
+
+fn login(settings: &Settings, password: &str) -> Result<Session> {
+    let host = required(settings, "mail.imap_host")?;
+    let tls = native_tls::TlsConnector::builder()
+        .build()
+        .map_err(|err| anyhow!("TLS: {}", err))?;
+    let client = imap::connect((host, port(settings, "mail.imap_port")?), host, &tls)
+        .map_err(|err| anyhow!("IMAP: {}", err))?;
+    let mut session = client.login(username(settings), password)
+        .map_err(|(err, _)| anyhow!("IMAP: {}", err))?;
+    session.select(settings.get("mail.imap_folder")).map_err(|err| anyhow!("IMAP: {}", err))?;
+    Ok(session)
+}
+
+pub fn fetch(settings: &Settings, password: &str) -> Result<Vec<(u32, Vec<String>)>> {
+    let mut session = login(settings, password)?;
+    let unseen = session.uid_search("UNSEEN").map_err(|err| anyhow!("IMAP: {}", err))?;
+    let mut mails = Vec::new();
+    if !unseen.is_empty() {
+        let set = unseen.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+        let messages = session.uid_fetch(set, "BODY.PEEK[]").map_err(|err| anyhow!("IMAP: {}", err))?;
+        for message in messages.iter() {
+            let (uid, parsed) = match (message.uid, message.body().map(mailparse::parse_mail)) {
+                (Some(uid), Some(Ok(parsed))) => (uid, parsed),
+                _ => continue,
+            };
+            let mut text = String::new();
+            collect_text(&parsed, &mut text);
+            mails.push((uid, armor::extract(&text)));
+        }
+    }
+    session.logout().map_err(|err| anyhow!("IMAP: {}", err))?;
+    Ok(mails)
+}
+
+pub fn mark_seen(settings: &Settings, password: &str, uids: &[u32]) -> Result<()> {
+    if uids.is_empty() {
+        return Ok(());
+    }
+    let mut session = login(settings, password)?;
+    let set = uids.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+    session.uid_store(set, "+FLAGS (\\Seen)").map_err(|err| anyhow!("IMAP: {}", err))?;
+    session.logout().map_err(|err| anyhow!("IMAP: {}", err))?;
+    Ok(())
+}
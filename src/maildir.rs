@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use crate::settings::Settings;
+
+pub fn dir_buf(settings: &Settings) -> Result<PathBuf> {
+    match settings.get("receive.maildir") {
+        "" => Ok(dirs::home_dir().ok_or(anyhow!("Unable to find the home directory."))?.join("Maildir")),
+        dir => Ok(PathBuf::from(dir)),
+    }
+}
+
+pub fn new_path_buf(dir: &Path) -> PathBuf {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+    let name = format!("{}.P{}R{}.{}",
+        chrono::Utc::now().timestamp(),
+        std::process::id(),
+        sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8)),
+        host.replace(['/', ':'], "_"));
+    dir.join("new").join(name)
+}
+
+fn header_value(value: &str) -> String {
+    value.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+pub fn message(sender: &str, trust: &str, identity: &str, body: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(body);
+    let subject: String = text.lines().next().unwrap_or("").chars().take(78).collect();
+    let now = chrono::Local::now();
+    let local = header_value(sender).replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "_");
+    let mut out = format!(
+        "From: \"{}\" <{}@gqg.invalid>\nTo: {}\nDate: {}\nSubject: {}\nMessage-ID: <{}@gqg.invalid>\nX-GQG-Sender: {}\nX-GQG-Trust: {}\nMIME-Version: 1.0\nContent-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: 8bit\n\n",
+        header_value(sender).replace('"', "'"), local, header_value(identity), now.to_rfc2822(), header_value(&subject),
+        sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(16)),
+        header_value(sender), trust);
+    out.push_str(&text);
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+pub fn deliver(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().and_then(Path::parent).ok_or(anyhow!("Invalid Maildir path."))?;
+    for sub in ["tmp", "new", "cur"] {
+        crate::fsutil::create_dir_private(&dir.join(sub))?;
+    }
+    let temp = dir.join("tmp").join(path.file_name().ok_or(anyhow!("Invalid Maildir path."))?);
+    crate::fsutil::write_private_file(&temp, contents)?;
+    std::fs::rename(&temp, path).map_err(|_| anyhow!("Unable to deliver to {}.", dir.display()))
+}
@@ -0,0 +1,205 @@
+const MAGIC: &[u8] = b"GQGMIME1\n";
+const MAX_LEN: usize = 127;
+pub const DEFAULT: &str = "application/octet-stream";
+
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"%PDF-", "application/pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"ID3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"MZ", "application/vnd.microsoft.portable-executable"),
+    (b"\x7fELF", "application/x-executable"),
+    (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+    (b"\xca\xfe\xba\xbe", "application/x-mach-binary"),
+    (b"#!", "text/x-shellscript"),
+    (b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", "application/x-ole-storage"),
+];
+
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("html", "text/html"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("7z", "application/x-7z-compressed"),
+    ("tar", "application/x-tar"),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+    ("exe", "application/vnd.microsoft.portable-executable"),
+    ("msi", "application/x-msi"),
+    ("jar", "application/java-archive"),
+    ("sh", "text/x-shellscript"),
+];
+
+const DANGEROUS: &[&str] = &[
+    "application/vnd.microsoft.portable-executable",
+    "application/x-msdownload",
+    "application/x-dosexec",
+    "application/x-executable",
+    "application/x-elf",
+    "application/x-sharedlib",
+    "application/x-mach-binary",
+    "application/x-msi",
+    "application/java-archive",
+    "application/x-sh",
+    "text/x-shellscript",
+    "application/x-ole-storage",
+    "application/hta",
+];
+
+fn extension_of(file_name: &str) -> String {
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => extension.trim().to_lowercase(),
+        None => String::new(),
+    }
+}
+
+pub fn is_valid(mime_type: &str) -> bool {
+    let token = |part: &str| !part.is_empty()
+        && part.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c));
+    mime_type.len() <= MAX_LEN
+        && mime_type.split_once('/').map(|(kind, subtype)| token(kind) && token(subtype)).unwrap_or(false)
+}
+
+pub fn sniff(contents: &[u8]) -> Option<&'static str> {
+    SIGNATURES.iter()
+        .find(|(signature, _)| contents.starts_with(signature))
+        .map(|(_, mime_type)| *mime_type)
+}
+
+pub fn detect(file_name: &str, contents: &[u8]) -> &'static str {
+    if let Some(mime_type) = sniff(contents) {
+        return mime_type;
+    }
+    let extension = extension_of(file_name);
+    if let Some((_, mime_type)) = EXTENSIONS.iter().find(|(known, _)| *known == extension) {
+        return *mime_type;
+    }
+    let text = match std::str::from_utf8(&contents[..contents.len().min(4096)]) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    };
+    if text {
+        return "text/plain";
+    }
+    DEFAULT
+}
+
+pub fn extension(mime_type: &str) -> Option<&'static str> {
+    EXTENSIONS.iter()
+        .find(|(_, known)| *known == mime_type)
+        .map(|(extension, _)| *extension)
+}
+
+pub fn matches_extension(file_name: &str, mime_type: &str) -> bool {
+    let extension = extension_of(file_name);
+    EXTENSIONS.iter().any(|(known, known_type)| *known == extension && *known_type == mime_type)
+}
+
+pub fn assess(mime_type: &str) -> Option<String> {
+    if DANGEROUS.contains(&mime_type) {
+        return Some(format!("content type {}", mime_type));
+    }
+    None
+}
+
+pub fn wrap(mime_type: &str, contents: &[u8]) -> Vec<u8> {
+    let mut wrapped = MAGIC.to_vec();
+    wrapped.extend_from_slice(mime_type.as_bytes());
+    wrapped.push(b'\n');
+    wrapped.extend_from_slice(contents);
+    wrapped
+}
+
+pub fn unwrap(contents: &[u8]) -> (Option<String>, &[u8]) {
+    if !contents.starts_with(MAGIC) {
+        return (None, contents);
+    }
+    let end = contents[MAGIC.len()..].iter().take(MAX_LEN + 1).position(|&byte| byte == b'\n');
+    let mime_type = end
+        .map(|end| String::from_utf8_lossy(&contents[MAGIC.len()..MAGIC.len() + end]).to_string())
+        .filter(|mime_type| is_valid(mime_type));
+    match (end, mime_type) {
+        (Some(end), Some(mime_type)) => (Some(mime_type), &contents[MAGIC.len() + end + 1..]),
+        _ => (None, contents),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_by_content_then_extension() {
+        assert_eq!(detect("report.txt", b"%PDF-1.7"), "application/pdf");
+        assert_eq!(detect("photo.jpg", b"\x89PNG\r\n\x1a\nrest"), "image/png");
+        assert_eq!(detect("notes.MD", b"# title"), "text/markdown");
+        assert_eq!(detect("notes", "caf\u{e9}".as_bytes()), "text/plain");
+        assert_eq!(detect("blob", b"\xff\xfe\x00\x81"), DEFAULT);
+    }
+
+    #[test]
+    fn validates_types() {
+        assert!(is_valid("text/plain"));
+        assert!(is_valid("application/vnd.ms-excel"));
+        for invalid in ["", "text", "text/", "/plain", "text/plain; charset=utf-8", "text/pla\nin"] {
+            assert!(!is_valid(invalid), "{}", invalid);
+        }
+        assert!(!is_valid(&format!("a/{}", "b".repeat(MAX_LEN))));
+    }
+
+    #[test]
+    fn flags_dangerous_types() {
+        assert!(assess("application/x-executable").is_some());
+        assert!(assess(detect("run.sh", b"#!/bin/sh")).is_some());
+        assert!(assess("text/plain").is_none());
+        assert!(matches_extension("a.pdf", "application/pdf"));
+        assert!(!matches_extension("a.pdf.exe", "application/pdf"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let wrapped = wrap("image/png", b"contents");
+        assert_eq!(unwrap(&wrapped), (Some("image/png".to_string()), &b"contents"[..]));
+        assert_eq!(unwrap(b"contents"), (None, &b"contents"[..]));
+        let bad = [MAGIC, b"not a type\ncontents"].concat();
+        assert_eq!(unwrap(&bad), (None, &bad[..]));
+        let unterminated = [MAGIC, "a/".repeat(100).as_bytes()].concat();
+        assert_eq!(unwrap(&unterminated), (None, &unterminated[..]));
+    }
+
+    #[test]
+    fn receive_undoes_send_layers_in_reverse() {
+        for contents in [&b"GQGMIME1\ntext/plain\nlookalike"[..], b"GQGPAD1\nlookalike", b"plain"] {
+            let escaped = crate::frame::escape(contents);
+            let labelled = wrap("text/plain", &escaped);
+            let padded = crate::padding::pad("padme", &labelled).unwrap().unwrap();
+            let (declared, body) = unwrap(crate::padding::strip(&padded).unwrap());
+            assert_eq!(declared.as_deref(), Some("text/plain"));
+            assert_eq!(crate::frame::unescape(body), contents);
+            assert_eq!(unwrap(crate::padding::strip(&crate::frame::escape(contents)).unwrap()).0, None);
+        }
+    }
+}
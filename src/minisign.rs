@@ -0,0 +1,177 @@
+use anyhow::Result;
+use sodiumoxide::base64::Variant;
+use sodiumoxide::crypto::generichash;
+use sodiumoxide::crypto::hash::sha256;
+use sodiumoxide::crypto::sign;
+use crate::signing::{detached, verify_detached};
+
+const LEGACY: &[u8] = b"Ed";
+const PREHASHED: &[u8] = b"ED";
+const UNTRUSTED: &str = "untrusted comment: ";
+const TRUSTED: &str = "trusted comment: ";
+const KEY_LEN: usize = 2 + 8 + sign::PUBLICKEYBYTES;
+const SIG_LEN: usize = 2 + 8 + sign::SIGNATUREBYTES;
+
+pub fn extension(format: &str) -> Result<&'static str> {
+    match format {
+        "minisign" => Ok("minisig"),
+        "signify" => Ok("sig"),
+        _ => Err(anyhow!("Invalid --format value.")),
+    }
+}
+
+fn key_id(public_key: &sign::PublicKey) -> [u8; 8] {
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&sha256::hash(&public_key.0).0[..8]);
+    id
+}
+
+pub fn key_id_hex(public_key: &sign::PublicKey) -> String {
+    format!("{:016X}", u64::from_le_bytes(key_id(public_key)))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    sodiumoxide::base64::encode(bytes, Variant::Original)
+}
+
+fn prehash(data: &[u8]) -> Result<Vec<u8>> {
+    let mut state = generichash::State::new(Some(64), None).map_err(|_| anyhow!("Unable to hash file."))?;
+    state.update(data).map_err(|_| anyhow!("Unable to hash file."))?;
+    let digest = state.finalize().map_err(|_| anyhow!("Unable to hash file."))?;
+    Ok(digest.as_ref().to_vec())
+}
+
+pub fn encode_public(format: &str, public_key: &sign::PublicKey) -> Result<String> {
+    let id = key_id(public_key);
+    let comment = match format {
+        "minisign" => format!("minisign public key {}", key_id_hex(public_key)),
+        "signify" => "signify public key".to_string(),
+        _ => return Err(anyhow!("Invalid --format value.")),
+    };
+    let mut blob = LEGACY.to_vec();
+    blob.extend_from_slice(&id);
+    blob.extend_from_slice(&public_key.0);
+    Ok(format!("{}{}\n{}\n", UNTRUSTED, comment, encode(&blob)))
+}
+
+pub fn sign(format: &str, file_name: &str, data: &[u8], secret_key: &sign::SecretKey) -> Result<String> {
+    let public_key = crate::signing::public_key(secret_key).ok_or(anyhow!("Signing key is corrupted."))?;
+    let (algorithm, signature) = match format {
+        "minisign" => (PREHASHED, detached(&prehash(data)?, secret_key)),
+        "signify" => (LEGACY, detached(data, secret_key)),
+        _ => return Err(anyhow!("Invalid --format value.")),
+    };
+    let mut blob = algorithm.to_vec();
+    blob.extend_from_slice(&key_id(&public_key));
+    blob.extend_from_slice(&signature);
+    if format == "signify" {
+        return Ok(format!("{}verify with gqg signkey --format signify\n{}\n", UNTRUSTED, encode(&blob)));
+    }
+    let trusted = format!("timestamp:{}\tfile:{}\thashed", chrono::Utc::now().timestamp(),
+        file_name.replace(|c: char| c.is_control(), "_"));
+    let mut global = signature;
+    global.extend_from_slice(trusted.as_bytes());
+    Ok(format!("{}signature from gqg secret key\n{}\n{}{}\n{}\n",
+        UNTRUSTED, encode(&blob), TRUSTED, trusted, encode(&detached(&global, secret_key))))
+}
+
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with(UNTRUSTED))
+}
+
+fn decode(line: &str, len: usize) -> Option<Vec<u8>> {
+    sodiumoxide::base64::decode(line, Variant::Original).ok().filter(|bytes| bytes.len() == len)
+}
+
+pub fn verify(signature: &str, data: &[u8], public_key: &str) -> Result<Option<String>> {
+    let key = lines(public_key).next()
+        .and_then(|line| decode(line, KEY_LEN))
+        .filter(|key| key.starts_with(LEGACY))
+        .ok_or(anyhow!("Invalid public key."))?;
+    let verifying_key = sign::PublicKey::from_slice(&key[10..]).ok_or(anyhow!("Invalid public key."))?;
+    let mut lines = lines(signature);
+    let blob = lines.next().and_then(|line| decode(line, SIG_LEN)).ok_or(anyhow!("Invalid signature file."))?;
+    if blob[2..10] != key[2..10] {
+        return Err(anyhow!(crate::i18n::tr("signfile.other_key")));
+    }
+    let valid = match &blob[..2] {
+        algorithm if algorithm == PREHASHED => verify_detached(&prehash(data)?, &blob[10..], &verifying_key),
+        algorithm if algorithm == LEGACY => verify_detached(data, &blob[10..], &verifying_key),
+        _ => return Err(anyhow!("Unsupported signature algorithm.")),
+    };
+    if !valid {
+        return Err(anyhow!(crate::i18n::tr("signfile.bad")));
+    }
+    let trusted = match lines.next().and_then(|line| line.strip_prefix(TRUSTED)) {
+        Some(trusted) => trusted.to_string(),
+        None if blob.starts_with(PREHASHED) => return Err(anyhow!("Invalid signature file.")),
+        None => return Ok(None),
+    };
+    let global = lines.next().and_then(|line| decode(line, sign::SIGNATUREBYTES)).ok_or(anyhow!("Invalid signature file."))?;
+    let mut signed = blob[10..].to_vec();
+    signed.extend_from_slice(trusted.as_bytes());
+    if !verify_detached(&signed, &global, &verifying_key) {
+        return Err(anyhow!(crate::i18n::tr("signfile.bad_comment")));
+    }
+    Ok(Some(trusted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypair(seed: u8) -> (sign::PublicKey, sign::SecretKey) {
+        sodiumoxide::init().unwrap();
+        sign::keypair_from_seed(&sign::Seed([seed; sign::SEEDBYTES]))
+    }
+
+    #[test]
+    fn minisign_round_trip() {
+        let (public_key, secret_key) = keypair(1);
+        let public = encode_public("minisign", &public_key).unwrap();
+        assert!(public.starts_with("untrusted comment: minisign public key "));
+        let signature = sign("minisign", "notes\n.txt", b"file contents", &secret_key).unwrap();
+        let trusted = verify(&signature, b"file contents", &public).unwrap().unwrap();
+        assert!(trusted.starts_with("timestamp:"));
+        assert!(trusted.ends_with("\tfile:notes_.txt\thashed"));
+    }
+
+    #[test]
+    fn signify_round_trip() {
+        let (public_key, secret_key) = keypair(2);
+        let public = encode_public("signify", &public_key).unwrap();
+        let signature = sign("signify", "f", b"file contents", &secret_key).unwrap();
+        assert_eq!(verify(&signature, b"file contents", &public).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let (public_key, secret_key) = keypair(3);
+        let public = encode_public("minisign", &public_key).unwrap();
+        let signature = sign("minisign", "f", b"file contents", &secret_key).unwrap();
+        assert!(verify(&signature, b"file contents!", &public).is_err());
+        let forged = signature.replace("\tfile:f\t", "\tfile:g\t");
+        assert_ne!(forged, signature);
+        assert!(verify(&forged, b"file contents", &public).is_err());
+        let other = encode_public("minisign", &keypair(4).0).unwrap();
+        assert!(verify(&signature, b"file contents", &other).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let (public_key, secret_key) = keypair(5);
+        let public = encode_public("minisign", &public_key).unwrap();
+        let signature = sign("minisign", "f", b"data", &secret_key).unwrap();
+        let without_comment: String = signature.lines().take(2).map(|line| format!("{}\n", line)).collect();
+        assert!(verify(&without_comment, b"data", &public).is_err());
+        assert!(verify("untrusted comment: x\nnot base64\n", b"data", &public).is_err());
+        assert!(verify(&signature, b"data", "untrusted comment: x\nRWQ=\n").is_err());
+        assert!(extension("gpg").is_err());
+        assert!(encode_public("gpg", &public_key).is_err());
+        assert!(sign("gpg", "f", b"data", &secret_key).is_err());
+        assert_eq!(extension("minisign").unwrap(), "minisig");
+        assert_eq!(extension("signify").unwrap(), "sig");
+    }
+}
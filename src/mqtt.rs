@@ -0,0 +1,57 @@
+use std::time::Duration;
+use anyhow::Result;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS, Transport};
+use crate::settings::Settings;
+
+fn options(settings: &Settings, client_id: &str, password: Option<String>) -> Result<MqttOptions> {
+    let host = settings.get("mqtt.host");
+    if host.is_empty() {
+        return Err(anyhow!("Set mqtt.host first (gqg config mqtt.host <value>)."));
+    }
+    let port = settings.get("mqtt.port").parse::<u16>().map_err(|_| anyhow!("Invalid port for mqtt.port."))?;
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if settings.get("mqtt.tls") == "true" {
+        options.set_transport(Transport::tls_with_default_config());
+    }
+    if let Some(password) = password {
+        options.set_credentials(settings.get("mqtt.username"), password);
+    }
+    Ok(options)
+}
+
+pub fn topic(settings: &Settings, hint: &str) -> String {
+    format!("{}/{}", settings.get("mqtt.topic_prefix"), hint)
+}
+
+pub fn publish(settings: &Settings, password: Option<String>, topic: &str, payload: &str) -> Result<()> {
+    let client_id = format!("gqg-{}", sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(6)));
+    let (mut client, mut connection) = Client::new(options(settings, &client_id, password)?, 10);
+    client.publish(topic, QoS::AtLeastOnce, false, payload.as_bytes().to_vec())
+        .map_err(|err| anyhow!("MQTT: {}", err))?;
+    for event in connection.iter() {
+        if let Event::Incoming(Packet::PubAck(_)) = event.map_err(|err| anyhow!("MQTT: {}", err))? {
+            break;
+        }
+    }
+    let _ = client.disconnect();
+    Ok(())
+}
+
+pub fn subscribe<F>(settings: &Settings, password: Option<String>, client_id: &str, topics: &[String], mut on_payload: F) -> Result<()>
+where
+    F: FnMut(String),
+{
+    let mut options = options(settings, client_id, password)?;
+    options.set_clean_session(false);
+    let (mut client, mut connection) = Client::new(options, 10);
+    for topic in topics {
+        client.subscribe(topic, QoS::AtLeastOnce).map_err(|err| anyhow!("MQTT: {}", err))?;
+    }
+    for event in connection.iter() {
+        if let Event::Incoming(Packet::Publish(publish)) = event.map_err(|err| anyhow!("MQTT: {}", err))? {
+            on_payload(String::from_utf8_lossy(&publish.payload).to_string());
+        }
+    }
+    Ok(())
+}
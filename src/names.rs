@@ -0,0 +1,94 @@
+use anyhow::Result;
+
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut diagonal = row[0];
+        row[0] = i;
+        for j in 1..=b.len() {
+            let above = row[j];
+            row[j] = if a[i - 1] == b[j - 1] {
+                diagonal
+            }
+            else {
+                1 + diagonal.min(above).min(row[j - 1])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+fn suggestions(name: &str, candidates: &[String]) -> Vec<String> {
+    let name = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &String)> = candidates.iter()
+        .map(|candidate| (distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|&(d, candidate)| d <= limit || candidate.to_lowercase().starts_with(&name))
+        .collect();
+    matches.sort();
+    matches.into_iter().take(3).map(|(_, candidate)| candidate.clone()).collect()
+}
+
+pub fn resolve(name: &str, candidates: &[String], allow_prefix: bool, not_found: &str) -> Result<String> {
+    if candidates.iter().any(|candidate| candidate == name) {
+        return Ok(name.to_string());
+    }
+    if allow_prefix {
+        let lower = name.to_lowercase();
+        let prefixed: Vec<&String> = candidates.iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&lower))
+            .collect();
+        if prefixed.len() == 1 {
+            return Ok(prefixed[0].clone());
+        }
+    }
+    let suggestions = suggestions(name, candidates);
+    if suggestions.is_empty() {
+        Err(anyhow!("{}", not_found))
+    }
+    else {
+        Err(anyhow!("{} Did you mean: {}?", not_found, suggestions.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<String> {
+        ["alice", "alicia", "bob", "Bobby"].iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("alice", "alice"), 0);
+        assert_eq!(distance("alice", "alcie"), 2);
+        assert_eq!(distance("bob", "bobby"), 2);
+        assert_eq!(distance("", "bob"), 3);
+    }
+
+    #[test]
+    fn exact_names_win() {
+        assert_eq!(resolve("bob", &candidates(), true, "Not found.").unwrap(), "bob");
+        assert_eq!(resolve("alice", &candidates(), false, "Not found.").unwrap(), "alice");
+    }
+
+    #[test]
+    fn unique_prefixes_resolve_only_when_allowed() {
+        assert_eq!(resolve("BOBB", &candidates(), true, "Not found.").unwrap(), "Bobby");
+        assert!(resolve("bobb", &candidates(), false, "Not found.").is_err());
+        assert!(resolve("ali", &candidates(), true, "Not found.").is_err());
+    }
+
+    #[test]
+    fn errors_suggest_close_names() {
+        let err = resolve("alise", &candidates(), false, "Not found.").unwrap_err().to_string();
+        assert_eq!(err, "Not found. Did you mean: alice?");
+        let err = resolve("zed", &candidates(), true, "Not found.").unwrap_err().to_string();
+        assert_eq!(err, "Not found.");
+    }
+}
@@ -0,0 +1,182 @@
+use std::io::Write;
+use std::net::TcpStream;
+use std::time::Duration;
+use anyhow::Result;
+use secp256k1::{KeyPair, Message, Secp256k1};
+use serde_json::{json, Value};
+use sodiumoxide::crypto::hash::sha256;
+use tungstenite::Message as Frame;
+use tungstenite::stream::MaybeTlsStream;
+use crate::settings::Settings;
+
+const KIND: u64 = 7457;
+const TAG: &str = "t";
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn relays(settings: &Settings) -> Result<Vec<String>> {
+    let relays: Vec<String> = settings.get("nostr.relays")
+        .split(',')
+        .map(|relay| relay.trim().to_string())
+        .filter(|relay| !relay.is_empty())
+        .collect();
+    if relays.is_empty() {
+        return Err(anyhow!("Set nostr.relays first (gqg config nostr.relays <wss://...>)."));
+    }
+    Ok(relays)
+}
+
+pub fn tag(hint: &str) -> String {
+    format!("gqg-{}", hint)
+}
+
+fn event(tag: &str, content: &str) -> Result<Value> {
+    let secp = Secp256k1::new();
+    let keypair = KeyPair::from_seckey_slice(&secp, &sodiumoxide::randombytes::randombytes(32))
+        .map_err(|_| anyhow!("Unable to create a Nostr key."))?;
+    let pubkey = keypair.x_only_public_key().0.to_string();
+    let created_at = chrono::Utc::now().timestamp();
+    let tags = json!([[TAG, tag]]);
+    let serialized = serde_json::to_string(&json!([0, pubkey, created_at, KIND, tags, content]))?;
+    let id = sha256::hash(serialized.as_bytes()).0;
+    let message = Message::from_slice(&id).map_err(|_| anyhow!("Unable to sign the Nostr event."))?;
+    let sig = secp.sign_schnorr_no_aux_rand(&message, &keypair);
+    Ok(json!({
+        "id": sodiumoxide::hex::encode(id),
+        "pubkey": pubkey,
+        "created_at": created_at,
+        "kind": KIND,
+        "tags": tags,
+        "content": content,
+        "sig": sig.to_string(),
+    }))
+}
+
+fn connect(relay: &str) -> Result<tungstenite::WebSocket<MaybeTlsStream<TcpStream>>> {
+    let (socket, _) = tungstenite::connect(relay).map_err(|err| anyhow!("{}: {}", relay, err))?;
+    let stream = match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => Some(stream),
+        MaybeTlsStream::Rustls(stream) => Some(stream.get_ref()),
+        _ => None,
+    };
+    if let Some(stream) = stream {
+        stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(|err| anyhow!("{}: {}", relay, err))?;
+    }
+    Ok(socket)
+}
+
+fn publish_to(relay: &str, event: &Value) -> Result<()> {
+    let mut socket = connect(relay)?;
+    socket.send(Frame::Text(json!(["EVENT", event]).to_string())).map_err(|err| anyhow!("{}: {}", relay, err))?;
+    loop {
+        if let Frame::Text(text) = socket.read().map_err(|err| anyhow!("{}: {}", relay, err))? {
+            let reply: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+            if reply[0] == "OK" && reply[1] == event["id"] {
+                let _ = socket.close(None);
+                if reply[2] == true {
+                    return Ok(());
+                }
+                return Err(anyhow!("{}: {}", relay, reply[3].as_str().unwrap_or("rejected")));
+            }
+        }
+    }
+}
+
+pub fn publish(settings: &Settings, tag: &str, payload: &str) -> Result<usize> {
+    let event = event(tag, payload)?;
+    let mut accepted = 0;
+    for relay in relays(settings)? {
+        match publish_to(&relay, &event) {
+            Ok(()) => accepted += 1,
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    if accepted == 0 {
+        return Err(anyhow!("No relay accepted the event."));
+    }
+    Ok(accepted)
+}
+
+fn fetch_from(relay: &str, tags: &[String], events: &mut Vec<(String, String)>) -> Result<()> {
+    let mut socket = connect(relay)?;
+    let subscription = sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8));
+    let filter = json!({ "kinds": [KIND], "#t": tags });
+    socket.send(Frame::Text(json!(["REQ", subscription, filter]).to_string())).map_err(|err| anyhow!("{}: {}", relay, err))?;
+    loop {
+        if let Frame::Text(text) = socket.read().map_err(|err| anyhow!("{}: {}", relay, err))? {
+            let reply: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+            if reply[0] == "EVENT" && reply[1] == subscription.as_str() {
+                if let (Some(id), Some(content)) = (reply[2]["id"].as_str(), reply[2]["content"].as_str()) {
+                    events.push((id.to_string(), content.to_string()));
+                }
+            }
+            else if reply[0] == "EOSE" || reply[0] == "CLOSED" {
+                let _ = socket.send(Frame::Text(json!(["CLOSE", subscription]).to_string()));
+                let _ = socket.close(None);
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn seen_path_buf() -> std::path::PathBuf {
+    crate::settings::state_path_buf("nostr-seen")
+}
+
+pub fn fetch(settings: &Settings, tags: &[String]) -> Result<Vec<(String, String)>> {
+    let seen = std::fs::read_to_string(seen_path_buf()).unwrap_or_default();
+    let mut events = Vec::new();
+    for relay in relays(settings)? {
+        if let Err(err) = fetch_from(&relay, tags, &mut events) {
+            eprintln!("{}", err);
+        }
+    }
+    let mut fresh: Vec<(String, String)> = Vec::new();
+    for (id, content) in events {
+        if seen.lines().any(|line| line == id) || fresh.iter().any(|(fresh_id, _)| *fresh_id == id) {
+            continue;
+        }
+        fresh.push((id, content));
+    }
+    Ok(fresh)
+}
+
+pub fn mark_seen(id: &str) -> Result<()> {
+    let mut file = crate::fsutil::append_private(&seen_path_buf())
+        .map_err(|_| anyhow!("Unable to record fetched events."))?;
+    writeln!(file, "{}", id).map_err(|_| anyhow!("Unable to record fetched events."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use secp256k1::XOnlyPublicKey;
+    use secp256k1::schnorr::Signature;
+
+    #[test]
+    fn events_are_signed_over_their_id() {
+        sodiumoxide::init().unwrap();
+        let event = event(&tag("00ff00ff00ff00ff"), "payload").unwrap();
+        assert_eq!(event["kind"], KIND);
+        assert_eq!(event["tags"], json!([["t", "gqg-00ff00ff00ff00ff"]]));
+        let serialized = serde_json::to_string(&json!([
+            0, event["pubkey"], event["created_at"], event["kind"], event["tags"], event["content"]
+        ])).unwrap();
+        let id = sha256::hash(serialized.as_bytes()).0;
+        assert_eq!(event["id"], sodiumoxide::hex::encode(id));
+        let secp = Secp256k1::new();
+        let pubkey = XOnlyPublicKey::from_str(event["pubkey"].as_str().unwrap()).unwrap();
+        let sig = Signature::from_str(event["sig"].as_str().unwrap()).unwrap();
+        let message = Message::from_slice(&id).unwrap();
+        assert!(secp.verify_schnorr(&sig, &message, &pubkey).is_ok());
+        let mut tampered = id;
+        tampered[0] ^= 1;
+        assert!(secp.verify_schnorr(&sig, &Message::from_slice(&tampered).unwrap(), &pubkey).is_err());
+    }
+
+    #[test]
+    fn events_use_fresh_keys() {
+        sodiumoxide::init().unwrap();
+        assert_ne!(event("t", "a").unwrap()["pubkey"], event("t", "a").unwrap()["pubkey"]);
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Note {
+    pub id: u64,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub created: String,
+    pub updated: String,
+}
+
+pub fn dir_buf(identity: &str) -> PathBuf {
+    crate::settings::state_path_buf("notes").join(crate::fsutil::sanitize_file_name(identity))
+}
+
+pub fn index_path_buf(identity: &str) -> PathBuf {
+    dir_buf(identity).join("index.gqg")
+}
+
+pub fn body_path_buf(identity: &str, id: u64) -> PathBuf {
+    dir_buf(identity).join(format!("{}.gqg", id))
+}
+
+pub fn parse_index(contents: &[u8]) -> Result<Vec<Note>> {
+    serde_json::from_slice(contents).map_err(|_| anyhow!("Note index is corrupted."))
+}
+
+pub fn encode_index(notes: &[Note]) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(notes)?)
+}
+
+pub fn position(notes: &[Note], id: &str) -> Result<usize> {
+    let id = id.parse::<u64>().map_err(|_| anyhow!("Invalid note number."))?;
+    notes.iter()
+        .position(|note| note.id == id)
+        .ok_or(anyhow!("No note {}.", id))
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::Result;
+use serde::Serialize;
+use crate::settings::Settings;
+
+static LONG_RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub fn set_long_running(long_running: bool) {
+    LONG_RUNNING.store(long_running, Ordering::Relaxed);
+}
+
+#[derive(Serialize)]
+pub struct Arrival {
+    pub sender: String,
+    pub trust: String,
+    pub kind: String,
+    pub path: String,
+}
+
+pub fn arrival(settings: &Settings, arrival: &Arrival) {
+    if settings.get("notify.desktop") != "true" || arrival.kind == "chaff" {
+        return;
+    }
+    let on_click = if LONG_RUNNING.load(Ordering::Relaxed) { settings.get("notify.on_click").to_string() } else { String::new() };
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .summary(&format!("gqg: {}", arrival.kind))
+        .body(&format!("{} ({})", arrival.sender, arrival.trust));
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if !on_click.is_empty() {
+            notification.action("default", "Open");
+        }
+        if let Ok(handle) = notification.show() {
+            if !on_click.is_empty() {
+                let env = [
+                    ("GQG_SENDER".to_string(), arrival.sender.clone()),
+                    ("GQG_TRUST".to_string(), arrival.trust.clone()),
+                    ("GQG_TYPE".to_string(), arrival.kind.clone()),
+                    ("GQG_OUTPUT".to_string(), arrival.path.clone()),
+                ];
+                std::thread::spawn(move || {
+                    handle.wait_for_action(|action| {
+                        if action == "default" {
+                            let env: Vec<(&str, &str)> = env.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+                            let _ = crate::hooks::run(&on_click, &env);
+                        }
+                    });
+                });
+            }
+        }
+    }
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = on_click;
+        let _ = notification.show();
+    }
+}
+
+pub fn webhook(settings: &Settings, arrival: &Arrival) -> Result<()> {
+    let url = settings.get("notify.webhook");
+    if url.is_empty() || arrival.kind == "chaff" {
+        return Ok(());
+    }
+    ureq::post(url)
+        .timeout(std::time::Duration::from_secs(10))
+        .set("Content-Type", "application/json")
+        .send_string(&serde_json::to_string(arrival)?)
+        .map_err(|err| anyhow!("{} {}", crate::i18n::tr("notify.webhook_failed"), err))?;
+    Ok(())
+}
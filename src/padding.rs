@@ -0,0 +1,109 @@
+use anyhow::Result;
+
+const MAGIC: &[u8] = b"GQGPAD1\n";
+const HEADER: u64 = MAGIC.len() as u64 + 8;
+const BUCKET_MIN: u64 = 256;
+const BUCKET_STEP: u64 = 1 << 20;
+
+fn padme(len: u64) -> u64 {
+    if len < 2 {
+        return len;
+    }
+    let exponent = 63 - len.leading_zeros() as u64;
+    let bits = 64 - exponent.leading_zeros() as u64;
+    let mask = (1u64 << (exponent - bits)) - 1;
+    (len + mask) & !mask
+}
+
+fn bucket(len: u64) -> u64 {
+    let mut size = BUCKET_MIN;
+    while size < len && size < BUCKET_STEP {
+        size *= 4;
+    }
+    if len <= size {
+        size
+    }
+    else {
+        len.div_ceil(BUCKET_STEP) * BUCKET_STEP
+    }
+}
+
+pub fn pad(policy: &str, contents: &[u8]) -> Result<Option<Vec<u8>>> {
+    let len = contents.len() as u64 + HEADER;
+    let target = match policy {
+        "off" => return Ok(None),
+        "padme" => padme(len),
+        "buckets" => bucket(len),
+        _ => return Err(anyhow!("Invalid value for send.padding.")),
+    };
+    let mut padded = Vec::with_capacity(target as usize);
+    padded.extend_from_slice(MAGIC);
+    padded.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+    padded.extend_from_slice(contents);
+    padded.resize(target as usize, 0);
+    Ok(Some(padded))
+}
+
+pub fn strip(contents: &[u8]) -> Result<&[u8]> {
+    if !contents.starts_with(MAGIC) {
+        return Ok(contents);
+    }
+    let mut word = [0u8; 8];
+    word.copy_from_slice(contents.get(MAGIC.len()..HEADER as usize).ok_or(anyhow!("Padding is corrupted."))?);
+    let len = u64::from_le_bytes(word);
+    let end = HEADER.checked_add(len)
+        .filter(|&end| end <= contents.len() as u64)
+        .ok_or(anyhow!("Padding is corrupted."))?;
+    Ok(&contents[HEADER as usize..end as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for policy in ["padme", "buckets"] {
+            for len in [0, 1, 255, 256, 1000, 70_000] {
+                let contents: Vec<u8> = (0..len).map(|i| i as u8).collect();
+                let padded = pad(policy, &contents).unwrap().unwrap();
+                assert!(padded.len() as u64 >= contents.len() as u64 + HEADER);
+                assert_eq!(strip(&padded).unwrap(), &contents[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn buckets_hide_length() {
+        let short = pad("buckets", b"hi").unwrap().unwrap();
+        let longer = pad("buckets", &[b'x'; 200]).unwrap().unwrap();
+        assert_eq!(short.len(), 256);
+        assert_eq!(longer.len(), 256);
+        assert_eq!(bucket(2 << 20), 2 << 20);
+        assert_eq!(bucket((2 << 20) + 1), 3 << 20);
+    }
+
+    #[test]
+    fn off_and_invalid_policy() {
+        assert!(pad("off", b"hello").unwrap().is_none());
+        assert!(pad("huge", b"hello").is_err());
+    }
+
+    #[test]
+    fn unpadded_passes_through() {
+        assert_eq!(strip(b"hello").unwrap(), b"hello");
+        assert_eq!(strip(b"").unwrap(), b"");
+    }
+
+    #[test]
+    fn malformed_padding() {
+        assert!(strip(MAGIC).is_err());
+        let mut padded = pad("padme", b"hello").unwrap().unwrap();
+        padded[MAGIC.len()..HEADER as usize].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(strip(&padded).is_err());
+        let mut truncated = MAGIC.to_vec();
+        truncated.extend_from_slice(&10u64.to_le_bytes());
+        truncated.extend_from_slice(b"short");
+        assert!(strip(&truncated).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use sodiumoxide::base64::Variant;
+use sodiumoxide::crypto::hash::sha256;
+
+pub const OFFER: &str = "gqg-pair1:";
+pub const ANSWER: &str = "gqg-pair2:";
+pub const REVEAL: &str = "gqg-pair3:";
+
+pub struct Party {
+    pub name: String,
+    pub public_id: String,
+    pub value: String,
+}
+
+pub fn nonce() -> String {
+    sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(16))
+}
+
+pub fn commit(public_id: &str, nonce: &str) -> String {
+    sodiumoxide::hex::encode(&sha256::hash(format!("{}\n{}", public_id, nonce).as_bytes()).0)
+}
+
+pub fn encode(prefix: &str, fields: &[&str]) -> String {
+    format!("{}{}", prefix, sodiumoxide::base64::encode(fields.join("\n"), Variant::UrlSafeNoPadding))
+}
+
+fn decode(prefix: &str, payload: &str) -> Result<Vec<String>> {
+    let encoded = payload.trim().strip_prefix(prefix).ok_or(anyhow!("Unexpected pairing payload."))?;
+    let raw = sodiumoxide::base64::decode(encoded, Variant::UrlSafeNoPadding)
+        .map_err(|_| anyhow!("Pairing payload is damaged."))?;
+    let text = String::from_utf8(raw).map_err(|_| anyhow!("Pairing payload is damaged."))?;
+    Ok(text.split('\n').map(|field| field.to_string()).collect())
+}
+
+pub fn decode_party(prefix: &str, payload: &str) -> Result<Party> {
+    let mut fields = decode(prefix, payload)?.into_iter();
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(name), Some(public_id), Some(value)) => Ok(Party { name, public_id, value }),
+        _ => Err(anyhow!("Pairing payload is incomplete.")),
+    }
+}
+
+pub fn decode_reveal(payload: &str) -> Result<String> {
+    decode(REVEAL, payload)?
+        .into_iter()
+        .next()
+        .ok_or(anyhow!("Pairing payload is incomplete."))
+}
+
+pub fn short_code(initiator_id: &str, responder_id: &str, initiator_nonce: &str, responder_nonce: &str) -> String {
+    let digest = sha256::hash(format!("{}\n{}\n{}\n{}",
+        initiator_id, responder_id, initiator_nonce, responder_nonce).as_bytes());
+    let value = u32::from_be_bytes([digest.0[0], digest.0[1], digest.0[2], digest.0[3]]) % 1_000_000;
+    format!("{:03} {:03}", value / 1000, value % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parties_round_trip() {
+        sodiumoxide::init().unwrap();
+        let nonce = nonce();
+        let payload = encode(OFFER, &["alice", "alice-id", &commit("alice-id", &nonce)]);
+        let party = decode_party(OFFER, &format!("  {}\n", payload)).unwrap();
+        assert_eq!((party.name.as_str(), party.public_id.as_str()), ("alice", "alice-id"));
+        assert_eq!(party.value, commit("alice-id", &nonce));
+        assert_eq!(decode_reveal(&encode(REVEAL, &[&nonce])).unwrap(), nonce);
+    }
+
+    #[test]
+    fn rejects_wrong_step_and_damage() {
+        let payload = encode(OFFER, &["alice", "alice-id", "value"]);
+        assert!(decode_party(ANSWER, &payload).is_err());
+        assert!(decode_party(OFFER, &format!("{}!", payload)).is_err());
+        assert!(decode_party(OFFER, &encode(OFFER, &["alice", "alice-id"])).is_err());
+    }
+
+    #[test]
+    fn commitments_bind_id_and_nonce() {
+        assert_eq!(commit("alice-id", "00"), commit("alice-id", "00"));
+        assert_ne!(commit("alice-id", "00"), commit("alice-id", "01"));
+        assert_ne!(commit("alice-id", "00"), commit("mallory-id", "00"));
+    }
+
+    #[test]
+    fn short_codes_depend_on_every_input() {
+        let code = short_code("a", "b", "00", "11");
+        assert_eq!(code.len(), 7);
+        assert_eq!(code.as_bytes()[3], b' ');
+        assert_eq!(code, short_code("a", "b", "00", "11"));
+        assert_ne!(code, short_code("b", "a", "00", "11"));
+        assert_ne!(code, short_code("a", "b", "00", "12"));
+    }
+}
@@ -0,0 +1,114 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use anyhow::Result;
+use gqg_lib::database::Database;
+use crate::i18n;
+
+const PORTABLE_MARKER: &str = "gqg-portable";
+const VARIABLES: &[&str] = &["HOME", "XDG_CONFIG_HOME", "XDG_DATA_HOME", "XDG_CACHE_HOME"];
+
+static ORIGINAL: OnceLock<Vec<(&'static str, Option<OsString>)>> = OnceLock::new();
+
+fn snapshot() -> Vec<(&'static str, Option<OsString>)> {
+    VARIABLES.iter().map(|&key| (key, std::env::var_os(key))).collect()
+}
+
+fn apply(values: &[(&'static str, Option<OsString>)]) {
+    for (key, value) in values {
+        match value {
+            Some(value) => std::env::set_var(key, value),
+            None => std::env::remove_var(key),
+        }
+    }
+}
+
+fn redirect(overrides: &[(&'static str, PathBuf)], unsupported: &str) -> Result<()> {
+    if cfg!(target_os = "windows") {
+        return Err(anyhow!(i18n::tr(unsupported)));
+    }
+    ORIGINAL.get_or_init(snapshot);
+    for (key, value) in overrides {
+        std::env::set_var(key, value);
+    }
+    Ok(())
+}
+
+pub fn with_overrides<T>(overrides: &[(&'static str, PathBuf)], f: impl FnOnce() -> T) -> T {
+    let saved = snapshot();
+    for (key, value) in overrides {
+        std::env::set_var(key, value);
+    }
+    let result = f();
+    apply(&saved);
+    result
+}
+
+pub fn with_original<T>(f: impl FnOnce() -> T) -> T {
+    match ORIGINAL.get() {
+        Some(original) => with_overrides(&[], || {
+            apply(original);
+            f()
+        }),
+        None => f(),
+    }
+}
+
+pub fn command<S: AsRef<OsStr>>(program: S) -> Command {
+    let mut command = Command::new(program);
+    for (key, value) in ORIGINAL.get().map(Vec::as_slice).unwrap_or_default() {
+        match value {
+            Some(value) => command.env(key, value),
+            None => command.env_remove(key),
+        };
+    }
+    command
+}
+
+pub fn enter_portable(forced: bool) -> Result<()> {
+    let exe = std::env::current_exe().map_err(|_| anyhow!("Unable to find the gqg executable."))?;
+    let dir = exe.parent().ok_or(anyhow!("Unable to find the gqg executable."))?;
+    if !forced && !dir.join(PORTABLE_MARKER).exists() {
+        return Ok(());
+    }
+    let profile = dir.join("gqg-profile");
+    redirect(&[
+        ("HOME", profile.clone()),
+        ("XDG_CONFIG_HOME", profile.join("config")),
+        ("XDG_DATA_HOME", profile.join("data")),
+        ("XDG_CACHE_HOME", profile.join("cache")),
+    ], "portable.windows")?;
+    crate::fsutil::create_dir_private(&profile)
+}
+
+pub fn use_config(path: &str) -> Result<()> {
+    let path = std::env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| PathBuf::from(path));
+    if Path::new(&Database::config_path()) == path {
+        return Ok(());
+    }
+    if cfg!(target_os = "windows") {
+        return Err(anyhow!(i18n::tr("config.windows")));
+    }
+    let probe = Path::new("/gqg-probe");
+    let probed = with_overrides(&[("HOME", probe.join("home")), ("XDG_CONFIG_HOME", probe.join("config"))], || {
+        PathBuf::from(Database::config_path())
+    });
+    let (variable, relative) = match probed.strip_prefix(probe.join("config")) {
+        Ok(relative) => ("XDG_CONFIG_HOME", relative.to_path_buf()),
+        Err(_) => ("HOME", probed.strip_prefix(probe.join("home")).map_err(|_| anyhow!(i18n::tr("config.windows")))?.to_path_buf()),
+    };
+    let layout = || anyhow!("{} {}. {}", i18n::tr("config.layout"), relative.display(), i18n::tr("config.not_arbitrary"));
+    let mut root = path.clone();
+    for component in relative.components().rev() {
+        if root.file_name() != Some(component.as_os_str()) {
+            return Err(layout());
+        }
+        root.pop();
+    }
+    redirect(&[(variable, root)], "config.windows")?;
+    if Path::new(&Database::config_path()) != path {
+        return Err(layout());
+    }
+    Ok(())
+}
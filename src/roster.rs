@@ -0,0 +1,86 @@
+use anyhow::Result;
+
+const HEADER: &str = "gqg-roster1";
+
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub public_id: String,
+    pub verified: bool,
+}
+
+fn clean(value: &str) -> String {
+    value.replace(|c: char| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+pub fn encode(entries: &[Entry]) -> String {
+    let mut body = format!("{}\n", HEADER);
+    for entry in entries {
+        body.push_str(&format!("{}\t{}\t{}\n", clean(&entry.name), entry.public_id, entry.verified));
+    }
+    body
+}
+
+pub fn decode(contents: &[u8]) -> Result<Vec<Entry>> {
+    let body = std::str::from_utf8(contents).map_err(|_| anyhow!("Not a roster."))?;
+    let mut lines = body.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(anyhow!("Not a roster."));
+    }
+    lines
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 3 {
+                return Err(anyhow!("Roster is damaged."));
+            }
+            Ok(Entry {
+                name: fields[0].to_string(),
+                public_id: fields[1].to_string(),
+                verified: fields[2] == "true",
+            })
+        })
+        .collect()
+}
+
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+pub fn from_csv(text: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv(line);
+        if i == 0 && fields[0].eq_ignore_ascii_case("name") {
+            continue;
+        }
+        if fields[0].is_empty() {
+            return Err(anyhow!("Missing name at line {}.", i + 1));
+        }
+        entries.push(Entry {
+            name: fields[0].clone(),
+            public_id: fields.get(1).cloned().unwrap_or_default(),
+            verified: fields.get(2).map(|trust| trust.as_str()) == Some("verified"),
+        });
+    }
+    Ok(entries)
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Index {
+    pub terms: BTreeMap<String, Vec<u64>>,
+}
+
+pub fn dir_buf() -> PathBuf {
+    crate::settings::state_path_buf("search")
+}
+
+pub fn path_buf(identity: &str) -> PathBuf {
+    dir_buf().join(format!("{}.gqg", crate::fsutil::sanitize_file_name(identity)))
+}
+
+pub fn tokenize(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+impl Index {
+    pub fn parse(contents: &[u8]) -> Result<Index> {
+        serde_json::from_slice(contents).map_err(|_| anyhow!("Search index is corrupted."))
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn add(&mut self, id: u64, text: &str) {
+        for term in tokenize(text) {
+            let postings = self.terms.entry(term).or_default();
+            if postings.last() != Some(&id) {
+                postings.push(id);
+            }
+        }
+    }
+
+    fn lookup(&self, term: &str) -> BTreeSet<u64> {
+        match term.strip_suffix('*') {
+            Some(prefix) => self.terms
+                .range(prefix.to_string()..)
+                .take_while(|(key, _)| key.starts_with(prefix))
+                .flat_map(|(_, postings)| postings.iter().copied())
+                .collect(),
+            None => self.terms
+                .get(term)
+                .map(|postings| postings.iter().copied().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn query(&self, terms: &[String]) -> Vec<u64> {
+        let mut matches: Option<BTreeSet<u64>> = None;
+        for term in terms {
+            let term = term.to_lowercase();
+            let found = self.lookup(&term);
+            matches = Some(match matches {
+                None => found,
+                Some(matches) => matches.intersection(&found).copied().collect(),
+            });
+        }
+        matches.unwrap_or_default().into_iter().collect()
+    }
+}
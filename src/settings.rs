@@ -0,0 +1,517 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use gqg_lib::database::Database;
+
+const MAX_AGE_DAYS: i64 = 1_000_000;
+
+pub struct Setting {
+    pub key: &'static str,
+    pub default: &'static str,
+    pub values: &'static [&'static str],
+    pub description: &'static str,
+}
+
+pub static SETTINGS: &[Setting] = &[
+    Setting {
+        key: "receive.policy",
+        default: "warn",
+        values: &["strict", "warn", "allow"],
+        description: "Handling of messages from unknown senders.",
+    },
+    Setting {
+        key: "send.recipient_hint",
+        default: "false",
+        values: &["true", "false"],
+        description: "Embed a hint of the recipient key in outgoing payloads (receivers need this version). The hint is the same for every payload to a recipient, so it links those payloads together.",
+    },
+    Setting {
+        key: "send.armor",
+        default: "false",
+        values: &["true", "false"],
+        description: "Wrap send, sendfile and queue payloads in BEGIN/END armor so pasting, mail and chat copies survive mangling (receivers need this version).",
+    },
+    Setting {
+        key: "send.padding",
+        default: "off",
+        values: &["off", "padme", "buckets"],
+        description: "Pad plaintext before encryption so ciphertext length hides the message size (receivers need this version).",
+    },
+    Setting {
+        key: "send.mime_type",
+        default: "off",
+        values: &["off", "detect"],
+        description: "Label sent files with their detected content type (receivers need this version).",
+    },
+    Setting {
+        key: "send.keep_sent",
+        default: "false",
+        values: &["true", "false"],
+        description: "Keep a copy of everything sent, sealed to the active identity.",
+    },
+    Setting {
+        key: "names.prefix_match",
+        default: "false",
+        values: &["true", "false"],
+        description: "Accept unambiguous prefixes of friend and identity names.",
+    },
+    Setting {
+        key: "receive.message_name",
+        default: "{sender}_%Y-%m-%d_%H-%M-%S_%3f.txt",
+        values: &[],
+        description: "File name template for received messages (strftime, {sender}, {subject}).",
+    },
+    Setting {
+        key: "receive.file_name",
+        default: "{original}",
+        values: &[],
+        description: "File name template for received files (strftime, {date}, {sender}, {original}).",
+    },
+    Setting {
+        key: "receive.sender_dirs",
+        default: "false",
+        values: &["true", "false"],
+        description: "Store received content in one subdirectory per sender.",
+    },
+    Setting {
+        key: "receive.dangerous",
+        default: "warn",
+        values: &["warn", "refuse-unverified"],
+        description: "Handling of executables, scripts and macro documents from senders not verified by pair.",
+    },
+    Setting {
+        key: "receive.quarantine",
+        default: "unknown",
+        values: &["off", "unknown", "unverified"],
+        description: "Senders whose content goes to the quarantine directory until released.",
+    },
+    Setting {
+        key: "receive.at_rest",
+        default: "plaintext",
+        values: &["plaintext", "sealed"],
+        description: "Store received content as plaintext, or sealed to the active identity for gqg read and gqg open.",
+    },
+    Setting {
+        key: "receive.delivery",
+        default: "files",
+        values: &["files", "maildir"],
+        description: "Store received messages as loose files, or as Maildir entries for mail readers.",
+    },
+    Setting {
+        key: "receive.maildir",
+        default: "",
+        values: &[],
+        description: "Maildir that receive.delivery = maildir delivers to (empty is ~/Maildir).",
+    },
+    Setting {
+        key: "search.index",
+        default: "false",
+        values: &["true", "false"],
+        description: "Add received messages to the encrypted search index used by gqg search.",
+    },
+    Setting {
+        key: "vault.clear_after",
+        default: "45",
+        values: &[],
+        description: "Seconds before a secret copied by vault get --clip is cleared (0 never clears).",
+    },
+    Setting {
+        key: "mail.from",
+        default: "",
+        values: &[],
+        description: "Sender address for gqg mail send.",
+    },
+    Setting {
+        key: "mail.username",
+        default: "",
+        values: &[],
+        description: "SMTP and IMAP login (empty uses mail.from).",
+    },
+    Setting {
+        key: "mail.password_key",
+        default: "mail",
+        values: &[],
+        description: "Vault secret holding the mail password.",
+    },
+    Setting {
+        key: "mail.smtp_host",
+        default: "",
+        values: &[],
+        description: "SMTP server used with STARTTLS.",
+    },
+    Setting {
+        key: "mail.smtp_port",
+        default: "587",
+        values: &[],
+        description: "SMTP submission port.",
+    },
+    Setting {
+        key: "mail.imap_host",
+        default: "",
+        values: &[],
+        description: "IMAP server used over TLS.",
+    },
+    Setting {
+        key: "mail.imap_port",
+        default: "993",
+        values: &[],
+        description: "IMAP port.",
+    },
+    Setting {
+        key: "mail.imap_folder",
+        default: "INBOX",
+        values: &[],
+        description: "Folder that gqg mail fetch reads unseen mail from.",
+    },
+    Setting {
+        key: "mqtt.host",
+        default: "",
+        values: &[],
+        description: "MQTT broker for push and subscribe.",
+    },
+    Setting {
+        key: "mqtt.port",
+        default: "8883",
+        values: &[],
+        description: "MQTT broker port.",
+    },
+    Setting {
+        key: "mqtt.tls",
+        default: "true",
+        values: &["true", "false"],
+        description: "Connect to the MQTT broker over TLS.",
+    },
+    Setting {
+        key: "mqtt.topic_prefix",
+        default: "gqg",
+        values: &[],
+        description: "Topic prefix; each recipient gets <prefix>/<key hint>.",
+    },
+    Setting {
+        key: "mqtt.username",
+        default: "",
+        values: &[],
+        description: "MQTT login (empty connects without credentials).",
+    },
+    Setting {
+        key: "mqtt.password_key",
+        default: "mqtt",
+        values: &[],
+        description: "Vault secret holding the MQTT password.",
+    },
+    Setting {
+        key: "nostr.relays",
+        default: "",
+        values: &[],
+        description: "Comma-separated Nostr relay URLs for push and pull.",
+    },
+    Setting {
+        key: "queue.backoff",
+        default: "30",
+        values: &[],
+        description: "Seconds before the first retry of a failed mail, mqtt or nostr delivery; doubles per attempt up to 6 hours.",
+    },
+    Setting {
+        key: "queue.max_attempts",
+        default: "10",
+        values: &[],
+        description: "Delivery attempts before a queued payload is marked failed (0 retries forever).",
+    },
+    Setting {
+        key: "chaff.interval",
+        default: "600",
+        values: &[],
+        description: "Mean seconds between dummy payloads sent by gqg chaff (intervals are random).",
+    },
+    Setting {
+        key: "chaff.friends",
+        default: "",
+        values: &[],
+        description: "Comma-separated friends gqg chaff sends to (empty is all friends).",
+    },
+    Setting {
+        key: "notify.desktop",
+        default: "false",
+        values: &["true", "false"],
+        description: "Show a desktop notification for payloads received by subscribe, pull and mail fetch.",
+    },
+    Setting {
+        key: "notify.on_click",
+        default: "",
+        values: &[],
+        description: "Program run when a notification is clicked (Linux and BSD). Only subscribe keeps running to wait for the click; pull and mail fetch exit first, so their notifications have no action.",
+    },
+    Setting {
+        key: "notify.webhook",
+        default: "",
+        values: &[],
+        description: "URL that gets a JSON POST with sender, trust, type and output path (never the plaintext) for payloads received by subscribe, pull and mail fetch.",
+    },
+    Setting {
+        key: "serve.tls_cert",
+        default: "",
+        values: &[],
+        description: "PEM certificate for gqg serve; with serve.tls_key, serves HTTPS.",
+    },
+    Setting {
+        key: "serve.tls_key",
+        default: "",
+        values: &[],
+        description: "PEM private key for gqg serve.",
+    },
+    Setting {
+        key: "serve.dropbox_dir",
+        default: "",
+        values: &[],
+        description: "Directory gqg serve --dropbox stores submissions in and gqg pull --local reads (empty is the state directory).",
+    },
+    Setting {
+        key: "serve.dropbox_max",
+        default: "1000",
+        values: &[],
+        description: "Submissions the drop box holds before refusing more (0 is unlimited).",
+    },
+    Setting {
+        key: "retention.max_age",
+        default: "",
+        values: &[],
+        description: "Prune received items older than this (d, w, m, y suffixes; empty keeps all).",
+    },
+    Setting {
+        key: "retention.max_count",
+        default: "0",
+        values: &[],
+        description: "Keep at most this many received items (0 is unlimited).",
+    },
+    Setting {
+        key: "retention.max_bytes",
+        default: "0",
+        values: &[],
+        description: "Keep at most this much received content (K, M, G suffixes; 0 is unlimited).",
+    },
+    Setting {
+        key: "retention.auto",
+        default: "false",
+        values: &["true", "false"],
+        description: "Prune after every receive.",
+    },
+    Setting {
+        key: "audit.key_max_age",
+        default: "1y",
+        values: &[],
+        description: "Identity age after which gqg audit suggests a new key (d, w, m, y suffixes).",
+    },
+    Setting {
+        key: "audit.unverified_traffic",
+        default: "20",
+        values: &[],
+        description: "Exchanges with an unverified friend before gqg audit flags it.",
+    },
+    Setting {
+        key: "files.permissions",
+        default: "private",
+        values: &["private", "umask"],
+        description: "Permissions of decrypted and exported files (umask for shared directories).",
+    },
+    Setting {
+        key: "undo.window",
+        default: "3600",
+        values: &[],
+        description: "Seconds within which gqg undo can reverse the last unfriend, rekey or trust change (0 is no limit).",
+    },
+    Setting {
+        key: "trash.days",
+        default: "30",
+        values: &[],
+        description: "Days an unfriended contact stays in the trash for gqg restore (0 keeps it forever).",
+    },
+    Setting {
+        key: "integrity.mac",
+        default: "on",
+        values: &["on", "off"],
+        description: "Create a key and keep a MAC over the database and settings, refusing to run when they change outside gqg. Once a key exists it is always checked; use gqg integrity off to remove it. The key is read from GQG_INTEGRITY_KEY, or next to the database when unset.",
+    },
+    Setting {
+        key: "limits.stdin_timeout",
+        default: "300",
+        values: &[],
+        description: "Seconds without input before reading piped stdin gives up (0 waits forever).",
+    },
+    Setting {
+        key: "limits.max_input",
+        default: "1G",
+        values: &[],
+        description: "Largest accepted input for send, sendfile and receive (K, M, G suffixes).",
+    },
+    Setting {
+        key: "log.syslog",
+        default: "off",
+        values: &["on", "off"],
+        description: "Log receives, deliveries, trust warnings and requests of serve, subscribe, chaff and queue run to syslog or journald.",
+    },
+    Setting {
+        key: "hooks.on_receive",
+        default: "",
+        values: &[],
+        description: "Program run after a message or file is received.",
+    },
+    Setting {
+        key: "hooks.on_send",
+        default: "",
+        values: &[],
+        description: "Program run on the plaintext before sending; non-zero exit aborts.",
+    },
+];
+
+pub fn find(key: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.key == key)
+}
+
+pub fn state_dir_buf() -> PathBuf {
+    Path::new(&Database::config_path())
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default()
+}
+
+pub fn state_path_buf(name: &str) -> PathBuf {
+    Path::new(&Database::config_path()).with_file_name(name)
+}
+
+pub struct Settings {
+    values: BTreeMap<String, String>,
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1].to_string()
+    }
+    else {
+        value.to_string()
+    }
+}
+
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.char_indices().last()? {
+        (i, 'k') | (i, 'K') => (&value[..i], 1u64 << 10),
+        (i, 'm') | (i, 'M') => (&value[..i], 1 << 20),
+        (i, 'g') | (i, 'G') => (&value[..i], 1 << 30),
+        (i, 't') | (i, 'T') => (&value[..i], 1 << 40),
+        _ => (value, 1),
+    };
+    digits.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+pub fn parse_age(age: &str) -> Option<chrono::Duration> {
+    let age = age.trim();
+    let (digits, days) = match age.char_indices().last()? {
+        (i, 'd') => (&age[..i], 1),
+        (i, 'w') => (&age[..i], 7),
+        (i, 'm') => (&age[..i], 30),
+        (i, 'y') => (&age[..i], 365),
+        _ => return None,
+    };
+    let count = digits.parse::<i64>().ok().filter(|&count| count >= 0)?;
+    let total = count.checked_mul(days).filter(|&total| total <= MAX_AGE_DAYS)?;
+    Some(chrono::Duration::days(total))
+}
+
+impl Settings {
+    pub fn path_buf() -> PathBuf {
+        state_path_buf("settings.conf")
+    }
+
+    pub fn load() -> Result<Settings> {
+        let contents = match std::fs::read_to_string(Settings::path_buf()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(_) => return Err(anyhow!("Unable to read settings file.")),
+        };
+        let mut values = BTreeMap::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or(anyhow!("Invalid settings file at line {}.", i + 1))?;
+            values.insert(key.trim().to_string(), unquote(value.trim()));
+        }
+        Ok(Settings { values })
+    }
+
+    pub fn get(&self, key: &str) -> &str {
+        match self.values.get(key) {
+            Some(value) => value,
+            None => find(key).map(|setting| setting.default).unwrap_or(""),
+        }
+    }
+
+    pub fn get_size(&self, key: &str) -> Result<u64> {
+        parse_size(self.get(key)).ok_or(anyhow!("Invalid size for {}.", key))
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let setting = find(key).ok_or(anyhow!("Unknown setting."))?;
+        if !setting.values.is_empty() && !setting.values.contains(&value) {
+            return Err(anyhow!("Invalid value. Expected one of: {}.", setting.values.join(", ")));
+        }
+        self.values.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut contents = String::new();
+        for (key, value) in &self.values {
+            contents.push_str(&format!("{} = \"{}\"\n", key, value));
+        }
+        crate::fsutil::replace_private_file(&Settings::path_buf(), contents.as_bytes())
+            .map_err(|_| anyhow!("Unable to write settings file."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("0"), Some(0));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("4k"), Some(4 << 10));
+        assert_eq!(parse_size(" 4 M "), Some(4 << 20));
+        assert_eq!(parse_size("2G"), Some(2 << 30));
+        assert_eq!(parse_size("1t"), Some(1 << 40));
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        for value in ["", "k", "-1", "1.5M", "1kb", "ten", "99999999999T"] {
+            assert_eq!(parse_size(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("30d"), Some(chrono::Duration::days(30)));
+        assert_eq!(parse_age("2w"), Some(chrono::Duration::days(14)));
+        assert_eq!(parse_age(" 6m "), Some(chrono::Duration::days(180)));
+        assert_eq!(parse_age("1y"), Some(chrono::Duration::days(365)));
+        assert_eq!(parse_age("0d"), Some(chrono::Duration::days(0)));
+    }
+
+    #[test]
+    fn rejects_bad_ages() {
+        for age in ["", "30", "d", "-1d", "1.5y", "1h", "999999999999y"] {
+            assert_eq!(parse_age(age), None, "{}", age);
+        }
+    }
+
+    #[test]
+    fn defaults_are_allowed_values() {
+        for setting in SETTINGS {
+            assert!(setting.values.is_empty() || setting.values.contains(&setting.default), "{}", setting.key);
+        }
+    }
+}
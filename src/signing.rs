@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+use sodiumoxide::base64::Variant;
+use sodiumoxide::crypto::sign;
+
+pub fn path_buf(identity: &str) -> PathBuf {
+    crate::settings::state_path_buf("signing").join(format!("{}.key", crate::fsutil::sanitize_file_name(identity)))
+}
+
+pub fn public_key(secret_key: &sign::SecretKey) -> Option<sign::PublicKey> {
+    sign::PublicKey::from_slice(&secret_key.0[sign::SECRETKEYBYTES - sign::PUBLICKEYBYTES..])
+}
+
+pub fn encode_public(public_key: &sign::PublicKey) -> String {
+    sodiumoxide::base64::encode(public_key.0, Variant::UrlSafeNoPadding)
+}
+
+pub fn decode_public(text: &str) -> Option<sign::PublicKey> {
+    let bytes = sodiumoxide::base64::decode(text.trim(), Variant::UrlSafeNoPadding).ok()?;
+    sign::PublicKey::from_slice(&bytes)
+}
+
+pub fn detached(data: &[u8], secret_key: &sign::SecretKey) -> Vec<u8> {
+    sign::sign(data, secret_key)[..sign::SIGNATUREBYTES].to_vec()
+}
+
+pub fn verify_detached(data: &[u8], signature: &[u8], public_key: &sign::PublicKey) -> bool {
+    if signature.len() != sign::SIGNATUREBYTES {
+        return false;
+    }
+    let mut signed = signature.to_vec();
+    signed.extend_from_slice(data);
+    sign::verify(&signed, public_key).is_ok()
+}
+
+pub fn sign(data: &[u8], secret_key: &sign::SecretKey) -> String {
+    sodiumoxide::base64::encode(detached(data, secret_key), Variant::UrlSafeNoPadding)
+}
+
+pub fn verify(data: &[u8], signature: &str, public_key: &sign::PublicKey) -> bool {
+    match sodiumoxide::base64::decode(signature.trim(), Variant::UrlSafeNoPadding) {
+        Ok(signature) => verify_detached(data, &signature, public_key),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const MAGIC: &[u8] = b"GQGSYNC1\n";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Binding {
+    pub friend: String,
+    pub name: String,
+    pub dir: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Change {
+    pub path: String,
+    pub sha256: String,
+    pub delete: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Header {
+    pub name: String,
+    pub changes: Vec<Change>,
+}
+
+fn dir_buf() -> PathBuf {
+    crate::settings::state_path_buf("sync")
+}
+
+fn bindings_path_buf() -> PathBuf {
+    dir_buf().join("bindings.json")
+}
+
+fn state_path_buf(binding: &Binding) -> PathBuf {
+    let key = crate::sha256_hex(format!("{}\n{}", binding.friend, binding.dir).as_bytes());
+    dir_buf().join(format!("{}.json", &key[..16]))
+}
+
+fn pending_path_buf(binding: &Binding) -> PathBuf {
+    state_path_buf(binding).with_extension("pending")
+}
+
+pub fn bindings() -> Result<Vec<Binding>> {
+    match std::fs::read(bindings_path_buf()) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|_| anyhow!("Sync bindings are corrupted.")),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read sync bindings.")),
+    }
+}
+
+pub fn bind(friend: &str, dir: &Path) -> Result<Binding> {
+    let dir = dir.to_str().ok_or(anyhow!("Invalid directory."))?.to_string();
+    let mut bindings = bindings()?;
+    if let Some(binding) = bindings.iter().find(|binding| binding.friend == friend && binding.dir == dir) {
+        return Ok(binding.clone());
+    }
+    let name = Path::new(&dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or(anyhow!("Invalid directory."))?;
+    if bindings.iter().any(|binding| binding.friend == friend && binding.name == name) {
+        return Err(anyhow!("Another folder named {} is already synced with {}.", name, friend));
+    }
+    let binding = Binding { friend: friend.to_string(), name, dir };
+    bindings.push(binding.clone());
+    crate::fsutil::create_dir_private(&dir_buf())?;
+    crate::fsutil::replace_private_file(&bindings_path_buf(), &serde_json::to_vec(&bindings)?)?;
+    Ok(binding)
+}
+
+pub fn load_state(binding: &Binding) -> Result<BTreeMap<String, String>> {
+    match std::fs::read(state_path_buf(binding)) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|_| anyhow!("Sync state is corrupted.")),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(_) => Err(anyhow!("Unable to read sync state.")),
+    }
+}
+
+pub fn store_state(binding: &Binding, state: &BTreeMap<String, String>) -> Result<()> {
+    crate::fsutil::create_dir_private(&dir_buf())?;
+    crate::fsutil::replace_private_file(&state_path_buf(binding), &serde_json::to_vec(state)?)
+}
+
+pub fn stage_state(binding: &Binding, state: &BTreeMap<String, String>) -> Result<()> {
+    crate::fsutil::create_dir_private(&dir_buf())?;
+    crate::fsutil::replace_private_file(&pending_path_buf(binding), &serde_json::to_vec(state)?)
+}
+
+pub fn confirm_state(binding: &Binding) -> Result<bool> {
+    match std::fs::rename(pending_path_buf(binding), state_path_buf(binding)) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(_) => Err(anyhow!("Unable to update sync state.")),
+    }
+}
+
+fn scan_into(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).map_err(|_| anyhow!("Unable to read {}.", dir.display()))?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let file_type = entry.file_type().map_err(|_| anyhow!("Unable to read {}.", path.display()))?;
+        if file_type.is_dir() {
+            scan_into(root, &path, files)?;
+        }
+        else if file_type.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            let contents = std::fs::read(&path).map_err(|_| anyhow!("Unable to read {}.", path.display()))?;
+            files.insert(relative, crate::sha256_hex(&contents));
+        }
+    }
+    Ok(())
+}
+
+pub fn scan(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    scan_into(dir, dir, &mut files)?;
+    Ok(files)
+}
+
+pub fn diff(previous: &BTreeMap<String, String>, current: &BTreeMap<String, String>) -> Vec<Change> {
+    let mut changes: Vec<Change> = current.iter()
+        .filter(|(path, sha256)| previous.get(*path) != Some(*sha256))
+        .map(|(path, sha256)| Change { path: path.clone(), sha256: sha256.clone(), delete: false })
+        .collect();
+    changes.extend(previous.keys()
+        .filter(|path| !current.contains_key(*path))
+        .map(|path| Change { path: path.clone(), sha256: String::new(), delete: true }));
+    changes
+}
+
+pub fn safe_join(dir: &Path, relative: &str) -> Option<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.as_os_str().is_empty() || relative.components().any(|component| !matches!(component, Component::Normal(_))) {
+        return None;
+    }
+    let mut target = dir.to_path_buf();
+    for component in relative.components() {
+        target.push(component);
+        match std::fs::symlink_metadata(&target) {
+            Ok(metadata) if metadata.file_type().is_symlink() => return None,
+            _ => {}
+        }
+    }
+    Some(target)
+}
+
+pub fn pack(header: &Header, bundles: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let header = serde_json::to_vec(header)?;
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    out.extend_from_slice(&header);
+    for bundle in bundles {
+        out.extend_from_slice(&(bundle.len() as u64).to_le_bytes());
+        out.extend_from_slice(bundle);
+    }
+    Ok(out)
+}
+
+fn take<'a>(data: &'a [u8], offset: &mut usize) -> Result<&'a [u8]> {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(data.get(*offset..*offset + 8).ok_or(anyhow!("Sync bundle is corrupted."))?);
+    let start = *offset + 8;
+    let end = start.checked_add(u64::from_le_bytes(word) as usize)
+        .filter(|&end| end <= data.len())
+        .ok_or(anyhow!("Sync bundle is corrupted."))?;
+    *offset = end;
+    Ok(&data[start..end])
+}
+
+pub fn unpack(contents: &[u8]) -> Result<Option<(Header, Vec<&[u8]>)>> {
+    if !contents.starts_with(MAGIC) {
+        return Ok(None);
+    }
+    let mut offset = MAGIC.len();
+    let header: Header = serde_json::from_slice(take(contents, &mut offset)?)
+        .map_err(|_| anyhow!("Sync bundle is corrupted."))?;
+    let mut bundles = Vec::new();
+    while offset < contents.len() {
+        bundles.push(take(contents, &mut offset)?);
+    }
+    Ok(Some((header, bundles)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+
+
+    #[test]
+    fn safe_join_stays_inside() {
+        let dir = testutil::scratch_dir("sync", "join");
+        assert_eq!(safe_join(&dir, "a/b.txt"), Some(dir.join("a").join("b.txt")));
+        for relative in ["", ".", "..", "../x", "a/../../x", "/etc/passwd", "./a"] {
+            assert_eq!(safe_join(&dir, relative), None, "{}", relative);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn safe_join_refuses_symlinks() {
+        let dir = testutil::scratch_dir("sync", "symlink");
+        std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", dir.join("file")).unwrap();
+        assert_eq!(safe_join(&dir, "link/x"), None);
+        assert_eq!(safe_join(&dir, "file"), None);
+        assert!(safe_join(&dir, "missing/x").is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let header = Header {
+            name: "docs".to_string(),
+            changes: vec![
+                Change { path: "a.txt".to_string(), sha256: "00".to_string(), delete: false },
+                Change { path: "b.txt".to_string(), sha256: String::new(), delete: true },
+            ],
+        };
+        let packed = pack(&header, &[b"first".to_vec(), Vec::new()]).unwrap();
+        let (unpacked, bundles) = unpack(&packed).unwrap().unwrap();
+        assert_eq!(unpacked.name, "docs");
+        assert_eq!(unpacked.changes.len(), 2);
+        assert!(unpacked.changes[1].delete);
+        assert_eq!(bundles, vec![&b"first"[..], &b""[..]]);
+    }
+
+    #[test]
+    fn malformed_bundles() {
+        let header = Header { name: "docs".to_string(), changes: Vec::new() };
+        let packed = pack(&header, &[b"first".to_vec()]).unwrap();
+        assert!(unpack(b"not a sync bundle").unwrap().is_none());
+        assert!(unpack(&packed[..packed.len() - 1]).is_err());
+        assert!(unpack(&packed[..MAGIC.len() + 4]).is_err());
+        let mut bad_header = MAGIC.to_vec();
+        bad_header.extend_from_slice(&3u64.to_le_bytes());
+        bad_header.extend_from_slice(b"{x}");
+        assert!(unpack(&bad_header).is_err());
+        let mut huge = MAGIC.to_vec();
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(unpack(&huge).is_err());
+    }
+
+    #[test]
+    fn diffs_states() {
+        let previous: BTreeMap<String, String> = [("a", "1"), ("b", "2"), ("c", "3")]
+            .iter()
+            .map(|(path, sha256)| (path.to_string(), sha256.to_string()))
+            .collect();
+        let current: BTreeMap<String, String> = [("a", "1"), ("b", "9"), ("d", "4")]
+            .iter()
+            .map(|(path, sha256)| (path.to_string(), sha256.to_string()))
+            .collect();
+        let changes: Vec<(String, bool)> = diff(&previous, &current).into_iter().map(|change| (change.path, change.delete)).collect();
+        assert_eq!(changes, vec![("b".to_string(), false), ("d".to_string(), false), ("c".to_string(), true)]);
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const ERROR: u8 = 3;
+pub const WARNING: u8 = 4;
+pub const INFO: u8 = 6;
+
+const FACILITY_USER: u8 = 1 << 3;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable(settings: &crate::settings::Settings) {
+    ENABLED.store(settings.get("log.syslog") == "on", Ordering::Relaxed);
+}
+
+fn field(value: &str) -> String {
+    let value: String = value.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+    if value.is_empty() || value.contains(|c: char| c == ' ' || c == '"' || c == '=') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    else {
+        value
+    }
+}
+
+#[cfg(unix)]
+fn send(line: &str) {
+    if let Ok(socket) = std::os::unix::net::UnixDatagram::unbound() {
+        for path in ["/dev/log", "/var/run/syslog", "/var/run/log"] {
+            if socket.send_to(line.as_bytes(), path).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn send(line: &str) {
+    eprintln!("{}", line);
+}
+
+pub fn event(severity: u8, event: &str, fields: &[(&str, &str)]) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut line = format!("<{}>gqg[{}]: event={}", FACILITY_USER | severity, std::process::id(), field(event));
+    for (key, value) in fields {
+        line.push_str(&format!(" {}={}", key, field(value)));
+    }
+    send(&line);
+}
@@ -0,0 +1,13 @@
+use std::path::PathBuf;
+
+pub fn scratch(module: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gqg-{}-{}-{}", module, name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+pub fn scratch_dir(module: &str, name: &str) -> PathBuf {
+    let dir = scratch(module, name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
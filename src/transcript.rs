@@ -0,0 +1,79 @@
+use std::fmt::Write;
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Item {
+    pub id: u64,
+    pub time: String,
+    pub direction: String,
+    pub trust: String,
+    pub kind: String,
+    pub file: String,
+    pub body: Option<String>,
+}
+
+fn speaker<'a>(peer: &'a str, item: &Item) -> &'a str {
+    if item.direction == "sent" { "me" } else { peer }
+}
+
+pub fn markdown(peer: &str, items: &[Item]) -> String {
+    let mut out = format!("# Conversation with {}\n", peer);
+    for item in items {
+        let _ = write!(out, "\n## {} {} ({}, #{})\n\n", item.time, speaker(peer, item), item.trust, item.id);
+        if item.kind == "file" {
+            let _ = writeln!(out, "File: `{}`", item.file);
+        }
+        match &item.body {
+            Some(body) => {
+                for line in body.lines() {
+                    let _ = writeln!(out, "> {}", line);
+                }
+            }
+            None if item.kind == "message" => {
+                let _ = writeln!(out, "*(content not available)*");
+            }
+            None => {}
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn html(peer: &str, items: &[Item]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Conversation with {0}</title></head><body>\n<h1>Conversation with {0}</h1>\n",
+        escape(peer));
+    for item in items {
+        let _ = writeln!(out, "<section class=\"{}\"><h2>{} {} ({}, #{})</h2>",
+            escape(&item.direction), escape(&item.time), escape(speaker(peer, item)), escape(&item.trust), item.id);
+        if item.kind == "file" {
+            let _ = writeln!(out, "<p>File: <code>{}</code></p>", escape(&item.file));
+        }
+        match &item.body {
+            Some(body) => {
+                let _ = writeln!(out, "<pre>{}</pre>", escape(body));
+            }
+            None if item.kind == "message" => {
+                let _ = writeln!(out, "<p><em>(content not available)</em></p>");
+            }
+            None => {}
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+pub fn json(peer: &str, items: &[Item]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "peer": peer,
+        "items": items,
+    }))?)
+}
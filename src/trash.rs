@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::journal::Friend;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Item {
+    pub name: String,
+    pub removed: i64,
+    pub friends: Vec<Friend>,
+}
+
+fn path_buf() -> PathBuf {
+    crate::settings::state_path_buf("trash.json")
+}
+
+fn load() -> Result<Vec<Item>> {
+    match std::fs::read(path_buf()) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(|_| anyhow!("Trash is corrupted.")),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(_) => Err(anyhow!("Unable to read the trash.")),
+    }
+}
+
+fn store(items: &[Item]) -> Result<()> {
+    crate::fsutil::replace_private_file(&path_buf(), &serde_json::to_vec_pretty(items)?)
+}
+
+pub fn purge(days: i64) -> Result<Vec<Item>> {
+    let mut items = load()?;
+    if days > 0 {
+        let cutoff = chrono::Utc::now().timestamp() - days.saturating_mul(DAY);
+        let before = items.len();
+        items.retain(|item| item.removed >= cutoff);
+        if items.len() != before && !crate::fsutil::is_read_only() {
+            store(&items)?;
+        }
+    }
+    Ok(items)
+}
+
+pub fn add(name: &str, friends: Vec<Friend>) -> Result<()> {
+    let mut items = load()?;
+    items.retain(|item| item.name != name);
+    items.push(Item {
+        name: name.to_string(),
+        removed: chrono::Utc::now().timestamp(),
+        friends,
+    });
+    store(&items)
+}
+
+pub fn take(name: &str) -> Result<Option<Item>> {
+    let mut items = load()?;
+    let item = match items.iter().position(|item| item.name == name) {
+        Some(index) => items.remove(index),
+        None => return Ok(None),
+    };
+    store(&items)?;
+    Ok(Some(item))
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use anyhow::Result;
+
+fn verified_path_buf() -> PathBuf {
+    crate::settings::state_path_buf("verified")
+}
+
+fn read_entries() -> Result<Vec<(String, String)>> {
+    let contents = match std::fs::read_to_string(verified_path_buf()) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(_) => return Err(anyhow!("Unable to read verified friends.")),
+    };
+    Ok(contents.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, id)| (name.to_string(), id.to_string()))
+        .collect())
+}
+
+fn write_entries(entries: &[(String, String)]) -> Result<()> {
+    let contents: String = entries.iter()
+        .map(|(name, id)| format!("{}\t{}\n", name, id))
+        .collect();
+    crate::fsutil::replace_private_file(&verified_path_buf(), contents.as_bytes())
+        .map_err(|_| anyhow!("Unable to write verified friends."))
+}
+
+pub fn is_verified(name: &str, public_id: &str) -> bool {
+    read_entries()
+        .map(|entries| entries.iter().any(|(n, id)| n == name && id == public_id))
+        .unwrap_or(false)
+}
+
+pub fn set_verified(name: &str, public_id: &str, verified: bool) -> Result<()> {
+    let mut entries = read_entries()?;
+    let previous = entries.iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, id)| id.clone())
+        .unwrap_or_default();
+    entries.retain(|(n, _)| n != name);
+    if verified {
+        entries.push((name.to_string(), public_id.to_string()));
+    }
+    write_entries(&entries)?;
+    let action = if verified { "verify" } else { "unverify" };
+    crate::journal::record(crate::journal::Entry {
+        action: action.to_string(),
+        subject: name.to_string(),
+        trust: true,
+        verified: previous,
+        ..Default::default()
+    })?;
+    crate::audit::record(action, name, public_id)
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::Result;
+
+const SCHEME: &str = "gqg:";
+
+fn percent_decode(value: &str) -> Result<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() && bytes[i + 1..i + 3].iter().all(u8::is_ascii_hexdigit) => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).map_err(|_| anyhow!("Invalid gqg: link."))?;
+                decoded.push(u8::from_str_radix(hex, 16).map_err(|_| anyhow!("Invalid gqg: link."))?);
+                i += 3;
+            }
+            b'%' => return Err(anyhow!("Invalid gqg: link.")),
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| anyhow!("Invalid gqg: link."))
+}
+
+pub fn parse(uri: &str) -> Result<(String, BTreeMap<String, String>)> {
+    let rest = uri.strip_prefix(SCHEME).ok_or(anyhow!("Not a gqg: link."))?;
+    let rest = rest.trim_start_matches('/');
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let mut params = BTreeMap::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params.insert(percent_decode(key)?, percent_decode(value)?);
+    }
+    Ok((action.trim_end_matches('/').to_string(), params))
+}
+
+#[cfg(target_os = "windows")]
+pub fn register(exe: &Path) -> Result<()> {
+    let command = format!("\"{}\" open \"%1\"", exe.display());
+    let keys: [&[&str]; 3] = [
+        &["HKCU\\Software\\Classes\\gqg", "/ve", "/d", "URL:gqg"],
+        &["HKCU\\Software\\Classes\\gqg", "/v", "URL Protocol", "/d", ""],
+        &["HKCU\\Software\\Classes\\gqg\\shell\\open\\command", "/ve", "/d", &command],
+    ];
+    for args in keys.iter() {
+        let status = crate::profile::command("reg")
+            .arg("add")
+            .args(args.iter())
+            .arg("/f")
+            .status()
+            .map_err(|_| anyhow!("Unable to run reg."))?;
+        if !status.success() {
+            return Err(anyhow!("Unable to register the gqg: handler."));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn register(_exe: &Path) -> Result<()> {
+    Err(anyhow!("On macOS, URL handlers are declared by an application bundle; gqg has none to register."))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn register(exe: &Path) -> Result<()> {
+    let dir = crate::profile::with_original(dirs::data_dir).ok_or(anyhow!("Unable to find the data directory."))?.join("applications");
+    std::fs::create_dir_all(&dir).map_err(|_| anyhow!("Unable to create {}.", dir.display()))?;
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=gqg\nExec=\"{}\" open %u\nTerminal=true\nNoDisplay=true\nMimeType=x-scheme-handler/gqg;\n",
+        exe.display());
+    std::fs::write(dir.join("gqg-handler.desktop"), entry)
+        .map_err(|_| anyhow!("Unable to write the desktop entry."))?;
+    let status = crate::profile::command("xdg-mime")
+        .args(["default", "gqg-handler.desktop", "x-scheme-handler/gqg"])
+        .status()
+        .map_err(|_| anyhow!("Unable to run xdg-mime."))?;
+    if !status.success() {
+        return Err(anyhow!("Unable to register the gqg: handler."));
+    }
+    Ok(())
+}
+
+pub fn launch(path: &Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = crate::profile::command("cmd");
+        command.args(["/C", "start", "/WAIT", ""]);
+        command
+    }
+    else if cfg!(target_os = "macos") {
+        let mut command = crate::profile::command("open");
+        command.arg("-W");
+        command
+    }
+    else {
+        crate::profile::command("xdg-open")
+    };
+    let status = command.arg(path)
+        .status()
+        .map_err(|_| anyhow!("Unable to start a viewer."))?;
+    if !status.success() {
+        return Err(anyhow!("Unable to open {}.", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("alice%20b+c").unwrap(), "alice b c");
+        assert_eq!(percent_decode("%E2%82%ac").unwrap(), "\u{20ac}");
+        assert_eq!(percent_decode("%2B%25").unwrap(), "+%");
+        assert_eq!(percent_decode("").unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_malformed_escapes() {
+        for value in ["%", "%4", "a%4", "%zz", "%+1", "%-1", "%FF", "%C3%28"] {
+            assert!(percent_decode(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_links() {
+        let (action, params) = parse("gqg:add?name=alice&id=gqg%3Aabc%3D").unwrap();
+        assert_eq!(action, "add");
+        assert_eq!(params.get("name").map(String::as_str), Some("alice"));
+        assert_eq!(params.get("id").map(String::as_str), Some("gqg:abc="));
+        let (action, params) = parse("gqg://send/?flag&&to=bob").unwrap();
+        assert_eq!(action, "send");
+        assert_eq!(params.get("flag").map(String::as_str), Some(""));
+        assert_eq!(params.len(), 2);
+        assert!(parse("https://example.com/?a=b").is_err());
+        assert!(parse("gqg:add?name=%zz").is_err());
+    }
+}
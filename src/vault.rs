@@ -0,0 +1,19 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use anyhow::Result;
+
+pub fn dir_buf() -> PathBuf {
+    crate::settings::state_path_buf("vault")
+}
+
+pub fn path_buf(identity: &str) -> PathBuf {
+    dir_buf().join(format!("{}.gqg", crate::fsutil::sanitize_file_name(identity)))
+}
+
+pub fn parse(contents: &[u8]) -> Result<BTreeMap<String, String>> {
+    serde_json::from_slice(contents).map_err(|_| anyhow!("Vault is corrupted."))
+}
+
+pub fn encode(secrets: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(secrets)?)
+}
@@ -0,0 +1,143 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+const HEADER: &str = "gqg-volume:";
+const MIN_VOLUME_SIZE: u64 = 1 << 10;
+
+fn volume_path(base: &Path, index: usize) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(format!(".{:03}", index));
+    PathBuf::from(name)
+}
+
+fn parse_header(line: &str) -> Option<(String, usize, usize)> {
+    let rest = line.strip_prefix(HEADER)?.trim();
+    let (set, position) = rest.split_once(' ')?;
+    let (index, count) = position.split_once('/')?;
+    Some((set.to_string(), index.parse().ok()?, count.parse().ok()?))
+}
+
+pub fn split(payload: &str, volume_size: u64, base: &Path) -> Result<Vec<PathBuf>> {
+    if volume_size < MIN_VOLUME_SIZE {
+        return Err(anyhow!("Volume size must be at least {} bytes.", MIN_VOLUME_SIZE));
+    }
+    let set = sodiumoxide::hex::encode(sodiumoxide::randombytes::randombytes(8));
+    let header_len = format!("{} {} 000/000\n", HEADER, set).len() as u64;
+    let chunk_size = (volume_size - header_len) as usize;
+    let mut chunks = Vec::new();
+    let mut rest = payload;
+    while !rest.is_empty() {
+        let mut end = chunk_size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    if chunks.len() > 999 {
+        return Err(anyhow!("Too many volumes; choose a larger volume size."));
+    }
+    let mut paths = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let path = volume_path(base, i + 1);
+        crate::fsutil::create_private(&path)
+            .and_then(|mut file| write!(file, "{} {} {:03}/{:03}\n{}", HEADER, set, i + 1, chunks.len(), chunk))
+            .map_err(|_| anyhow!("Unable to write {}.", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+pub fn join(first: &Path) -> Result<String> {
+    let name = first.to_string_lossy();
+    let base = name.strip_suffix(".001")
+        .ok_or(anyhow!("Expected the first volume (ending in .001)."))?;
+    let base = PathBuf::from(base);
+    let read = |index: usize| -> Result<(String, usize, String)> {
+        let path = volume_path(&base, index);
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|_| anyhow!("Volume {} is missing: {}", index, path.display()))?;
+        let (header, body) = contents.split_once('\n').unwrap_or((contents.as_str(), ""));
+        let (set, position, count) = parse_header(header)
+            .ok_or(anyhow!("Volume {} is damaged: {}", index, path.display()))?;
+        if position != index {
+            return Err(anyhow!("Volume {} is out of order: {}", index, path.display()));
+        }
+        Ok((set, count, body.to_string()))
+    };
+    let (set, count, first_body) = read(1)?;
+    for index in 2..=count {
+        let path = volume_path(&base, index);
+        if !path.is_file() {
+            return Err(anyhow!("Volume {} of {} is missing: {}", index, count, path.display()));
+        }
+    }
+    let mut payload = first_body;
+    for index in 2..=count {
+        let (volume_set, volume_count, body) = read(index)?;
+        if volume_set != set || volume_count != count {
+            return Err(anyhow!("Volume {} belongs to a different payload.", index));
+        }
+        payload.push_str(&body);
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+
+
+    fn payload() -> String {
+        (0..4000).map(|i| if i % 7 == 0 { '\u{e9}' } else { (b'A' + (i % 26) as u8) as char }).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = testutil::scratch_dir("volumes", "round-trip");
+        let paths = split(&payload(), 1024, &dir.join("out.gqg")).unwrap();
+        assert!(paths.len() > 4);
+        for path in &paths {
+            assert!(std::fs::metadata(path).unwrap().len() <= 1024);
+        }
+        assert_eq!(join(&paths[0]).unwrap(), payload());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_bad_sizes_and_names() {
+        let dir = testutil::scratch_dir("volumes", "bad-args");
+        assert!(split("payload", 100, &dir.join("out.gqg")).is_err());
+        let paths = split("payload", 1024, &dir.join("out.gqg")).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert!(join(&dir.join("out.gqg")).is_err());
+        assert!(join(&dir.join("missing.gqg.001")).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn detects_missing_damaged_and_foreign_volumes() {
+        let dir = testutil::scratch_dir("volumes", "damaged");
+        let paths = split(&payload(), 1024, &dir.join("a.gqg")).unwrap();
+        let other = split(&payload(), 1024, &dir.join("b.gqg")).unwrap();
+        std::fs::copy(&other[1], &paths[1]).unwrap();
+        assert!(join(&paths[0]).is_err());
+        std::fs::write(&paths[1], "not a volume\n").unwrap();
+        assert!(join(&paths[0]).is_err());
+        std::fs::copy(&paths[2], &paths[1]).unwrap();
+        assert!(join(&paths[0]).is_err());
+        std::fs::remove_file(&paths[1]).unwrap();
+        assert!(join(&paths[0]).is_err());
+        assert_eq!(join(&other[0]).unwrap(), payload());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parses_headers() {
+        assert_eq!(parse_header("gqg-volume: abcd 002/010"), Some(("abcd".to_string(), 2, 10)));
+        assert_eq!(parse_header("gqg-volume: abcd 2-10"), None);
+        assert_eq!(parse_header("volume: abcd 002/010"), None);
+    }
+}